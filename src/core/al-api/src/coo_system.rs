@@ -93,10 +93,10 @@ impl CooBaseFloat for f64 {
 }
 
 use cgmath::BaseFloat;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize, Hash)]
 pub enum CooSystem {
     ICRSJ2000 = 0,
     GAL = 1,
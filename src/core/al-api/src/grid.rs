@@ -3,6 +3,7 @@ use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use super::color::ColorRGB;
+use super::coo_system::CooSystem;
 #[wasm_bindgen]
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,6 +17,84 @@ pub struct GridCfg {
     pub label_size: Option<f32>,
     #[serde(default = "default_enabled")]
     pub enabled: Option<bool>,
+    /// The frame of the meridians and parallels, the one of the view by default
+    #[serde(default = "default_frame")]
+    pub frame: Option<GridFrame>,
+    /// The step between two meridians or parallels in degrees.
+    /// The step adapts to the field of view if null or negative
+    #[serde(default = "default_step")]
+    pub step: Option<f64>,
+    /// The way the labels of the meridians and parallels are written
+    #[serde(default = "default_label_format")]
+    pub label_format: Option<GridLabelFormat>,
+    /// The number of decimals of the labels written in decimal degrees.
+    /// It is deduced from the step between the lines if negative, the default
+    #[serde(default = "default_label_digits")]
+    pub label_digits: Option<i8>,
+    /// Label the lines where they cross the border of the view, marked by ticks,
+    /// instead of along the lines
+    #[serde(default = "default_border_axes")]
+    pub border_axes: Option<bool>,
+    /// The length of the ticks of the border axes in pixels
+    #[serde(default = "default_tick_length")]
+    pub tick_length: Option<f32>,
+    /// Where the labels of the border axes are written
+    #[serde(default = "default_border_labels")]
+    pub border_labels: Option<GridBorderLabels>,
+    /// The width of the lines in pixels
+    #[serde(default = "default_line_width")]
+    pub line_width: Option<f32>,
+    /// The lengths of the dashes and of the gaps between them in pixels.
+    /// The lines are solid if empty
+    #[wasm_bindgen(skip)]
+    #[serde(default = "default_line_dash")]
+    pub line_dash: Option<Vec<f32>>,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub enum GridFrame {
+    /// The frame of the view, followed when it changes
+    #[serde(rename = "view")]
+    View,
+    ICRSJ2000,
+    GAL,
+}
+
+impl GridFrame {
+    /// The coordinate system of the grid, `None` for the one of the view
+    pub fn coo_system(&self) -> Option<CooSystem> {
+        match self {
+            GridFrame::View => None,
+            GridFrame::ICRSJ2000 => Some(CooSystem::ICRSJ2000),
+            GridFrame::GAL => Some(CooSystem::GAL),
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GridBorderLabels {
+    /// In a margin around the frame of the axes
+    Outside,
+    /// Inside the frame of the axes, after the ticks
+    Inside,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GridLabelFormat {
+    /// Degrees, arcminutes and arcseconds for both the longitudes and the latitudes
+    Dms,
+    /// Decimal degrees
+    Decimal,
+    /// Hours, minutes and seconds for the longitudes, signed degrees, arcminutes
+    /// and arcseconds for the latitudes
+    Sexagesimal,
+    /// Decimal degrees suffixed by l for the longitudes and b for the latitudes
+    Galactic,
 }
 
 fn default_labels() -> Option<bool> {
@@ -33,3 +112,39 @@ fn default_color() -> Option<ColorRGB> {
 fn default_label_size() -> Option<f32> {
     None
 }
+
+fn default_frame() -> Option<GridFrame> {
+    None
+}
+
+fn default_step() -> Option<f64> {
+    None
+}
+
+fn default_label_format() -> Option<GridLabelFormat> {
+    None
+}
+
+fn default_label_digits() -> Option<i8> {
+    None
+}
+
+fn default_border_axes() -> Option<bool> {
+    None
+}
+
+fn default_tick_length() -> Option<f32> {
+    None
+}
+
+fn default_border_labels() -> Option<GridBorderLabels> {
+    None
+}
+
+fn default_line_width() -> Option<f32> {
+    None
+}
+
+fn default_line_dash() -> Option<Vec<f32>> {
+    None
+}
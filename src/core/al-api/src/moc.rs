@@ -8,9 +8,16 @@ pub struct MOC {
     uuid: String,
     opacity: f32,
     line_width: f32,
+    // The lengths of the dashes and gaps of the perimeter in pixels
+    line_dash: Vec<f32>,
     is_showing: bool,
     color: ColorRGB,
     adaptative_display: bool,
+
+    // Fill the cells instead of drawing their borders
+    fill: bool,
+    fill_color: ColorRGB,
+    fill_opacity: f32,
 }
 use std::convert::TryInto;
 use crate::Abort;
@@ -24,9 +31,14 @@ impl MOC {
             uuid,
             opacity,
             line_width,
+            line_dash: vec![],
             color,
             is_showing,
-            adaptative_display
+            adaptative_display,
+
+            fill: false,
+            fill_color: color,
+            fill_opacity: 0.5,
         }
     }
 
@@ -34,6 +46,29 @@ impl MOC {
     pub fn set_is_showing(&mut self, is_showing: bool) {
         self.is_showing = is_showing;
     }
+
+    /// Dash the perimeter of the MOC, alternating dashes and gaps of the given lengths in pixels
+    #[wasm_bindgen(setter)]
+    pub fn set_line_dash(&mut self, line_dash: Vec<f32>) {
+        self.line_dash = line_dash;
+    }
+
+    /// Fill the MOC, only its perimeter being stroked
+    #[wasm_bindgen(setter)]
+    pub fn set_fill(&mut self, fill: bool) {
+        self.fill = fill;
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_fill_color(&mut self, hex_color: String) {
+        let color = Color::hexToRgb(hex_color);
+        self.fill_color = color.try_into().unwrap_abort();
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_fill_opacity(&mut self, fill_opacity: f32) {
+        self.fill_opacity = fill_opacity;
+    }
 }
 
 impl MOC {
@@ -53,6 +88,10 @@ impl MOC {
         self.line_width
     }
 
+    pub fn get_line_dash(&self) -> &[f32] {
+        &self.line_dash
+    }
+
     pub fn is_showing(&self) -> bool {
         self.is_showing
     }
@@ -60,6 +99,18 @@ impl MOC {
     pub fn is_adaptative_display(&self) -> bool {
         self.adaptative_display
     }
+
+    pub fn is_filled(&self) -> bool {
+        self.fill
+    }
+
+    pub fn get_fill_color(&self) -> &ColorRGB {
+        &self.fill_color
+    }
+
+    pub fn get_fill_opacity(&self) -> f32 {
+        self.fill_opacity
+    }
 }
 
 impl Default for MOC {
@@ -68,9 +119,13 @@ impl Default for MOC {
             uuid: String::from("moc"),
             opacity: 1.0,
             line_width: 1.0,
+            line_dash: vec![],
             is_showing: true,
             color: ColorRGB {r: 1.0, g: 0.0, b: 0.0},
             adaptative_display: true,
+            fill: false,
+            fill_color: ColorRGB {r: 1.0, g: 0.0, b: 0.0},
+            fill_opacity: 0.5,
         }
     }
 }
\ No newline at end of file
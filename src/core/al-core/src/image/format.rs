@@ -132,6 +132,32 @@ impl ImageFormat for RGBA32F {
     }
 }
 
+// Half float color, renderable and blendable with EXT_color_buffer_float
+#[cfg(feature = "webgl2")]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct RGBA16F;
+#[cfg(feature = "webgl2")]
+impl ImageFormat for RGBA16F {
+    type P = [f32; 4];
+
+    const NUM_CHANNELS: usize = 4;
+
+    const FORMAT: u32 = WebGlRenderingCtx::RGBA;
+    const INTERNAL_FORMAT: i32 = WebGlRenderingCtx::RGBA16F as i32;
+    // The values are given as f32, converted by the driver
+    const TYPE: u32 = WebGlRenderingCtx::FLOAT;
+
+    fn decode(raw_bytes: &[u8]) -> Result<Bytes<'_>, &'static str> {
+        Ok(Bytes::Borrowed(raw_bytes))
+    }
+
+    type ArrayBufferView = js_sys::Float32Array;
+
+    unsafe fn view(s: &[<Self::P as Pixel>::Item]) -> Self::ArrayBufferView {
+        Self::ArrayBufferView::view(s)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct RGB32F;
 impl ImageFormat for RGB32F {
@@ -293,6 +319,30 @@ impl ImageFormat for R32I {
     }
 }
 
+#[cfg(feature = "webgl2")]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct RGBA32UI;
+#[cfg(feature = "webgl2")]
+impl ImageFormat for RGBA32UI {
+    type P = [u32; 4];
+
+    const NUM_CHANNELS: usize = 4;
+
+    const FORMAT: u32 = WebGlRenderingCtx::RGBA_INTEGER;
+    const INTERNAL_FORMAT: i32 = WebGlRenderingCtx::RGBA32UI as i32;
+    const TYPE: u32 = WebGlRenderingCtx::UNSIGNED_INT;
+
+    fn decode(raw_bytes: &[u8]) -> Result<Bytes<'_>, &'static str> {
+        Ok(Bytes::Borrowed(raw_bytes))
+    }
+
+    type ArrayBufferView = js_sys::Uint32Array;
+
+    unsafe fn view(s: &[<Self::P as Pixel>::Item]) -> Self::ArrayBufferView {
+        Self::ArrayBufferView::view(s)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 pub enum ChannelType {
     RGBA32F,
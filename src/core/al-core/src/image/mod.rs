@@ -114,6 +114,36 @@ impl ArrayBuffer for ArrayI32 {
     }
 }
 #[derive(Debug)]
+pub struct ArrayU32(js_sys::Uint32Array);
+impl AsRef<js_sys::Object> for ArrayU32 {
+    fn as_ref(&self) -> &js_sys::Object {
+        self.0.as_ref()
+    }
+}
+impl ArrayBuffer for ArrayU32 {
+    type Item = u32;
+
+    fn new(buf: &[Self::Item]) -> Self {
+        ArrayU32(buf.into())
+    }
+
+    fn empty(size: u32, blank_value: Self::Item) -> Self {
+        ArrayU32(js_sys::Uint32Array::new_with_length(size).fill(blank_value, 0, size))
+    }
+
+    fn to_vec(&self) -> Vec<Self::Item> {
+        self.0.to_vec()
+    }
+
+    fn set_index(&self, idx: u32, value: Self::Item) {
+        self.0.set_index(idx, value);
+    }
+
+    fn get(&self, idx: u32) -> Self::Item {
+        self.0.get_index(idx)
+    }
+}
+#[derive(Debug)]
 pub struct ArrayF32(js_sys::Float32Array);
 impl AsRef<js_sys::Object> for ArrayF32 {
     fn as_ref(&self) -> &js_sys::Object {
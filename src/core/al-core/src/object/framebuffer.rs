@@ -6,18 +6,39 @@ pub struct FrameBufferObject {
     gl: WebGlContext,
     fbo: WebGlFramebuffer,
     pub texture: Texture2D,
+    // The internal format, format and type of the texture when it is resized,
+    // the sRGB ones if the framebuffer has been created by `new`
+    format: Option<(i32, u32, u32)>,
 }
 use crate::webgl_ctx::WebGlContext;
 use crate::texture::Texture2D;
+use crate::image::format::{ImageFormat, RGBA8U};
 
 impl FrameBufferObject {
     pub fn new(gl: &WebGlContext, width: usize, height: usize) -> Result<Self, JsValue> {
+        Self::create::<RGBA8U>(gl, width, height, None)
+    }
+
+    /// Create a framebuffer rendering into a texture of a given format
+    ///
+    /// Fails if the format is not color renderable, e.g. a float format
+    /// without the EXT_color_buffer_float extension
+    pub fn with_format<F: ImageFormat>(gl: &WebGlContext, width: usize, height: usize) -> Result<Self, JsValue> {
+        Self::create::<F>(gl, width, height, Some((F::INTERNAL_FORMAT, F::FORMAT, F::TYPE)))
+    }
+
+    fn create<F: ImageFormat>(
+        gl: &WebGlContext,
+        width: usize,
+        height: usize,
+        format: Option<(i32, u32, u32)>,
+    ) -> Result<Self, JsValue> {
         let fbo = gl
             .create_framebuffer()
             .ok_or("failed to create framebuffer")?;
         gl.bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, Some(&fbo));
 
-        let texture = Texture2D::create_empty_with_format::<crate::image::format::RGBA8U>(
+        let texture = Texture2D::create_empty_with_format::<F>(
             gl,
             width as i32,
             height as i32,
@@ -44,12 +65,19 @@ impl FrameBufferObject {
         )?;
         texture.attach_to_framebuffer();
 
+        let status = gl.check_framebuffer_status(WebGlRenderingCtx::FRAMEBUFFER);
         gl.bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, None);
 
+        if status != WebGlRenderingCtx::FRAMEBUFFER_COMPLETE {
+            gl.delete_framebuffer(Some(&fbo));
+            return Err("framebuffer incomplete".into());
+        }
+
         Ok(Self {
             gl: gl.clone(),
             texture,
             fbo,
+            format,
         })
     }
 
@@ -62,25 +90,26 @@ impl FrameBufferObject {
         {
             //let pixels = [0, 0, 0, 0].iter().cloned().cycle().take(4*height*width).collect::<Vec<_>>();
             #[cfg(feature = "webgl2")]
-            self.texture
-                .bind_mut()
-                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-                    width as i32,
-                    height as i32,
-                    WebGlRenderingCtx::SRGB8_ALPHA8 as i32,
-                    WebGlRenderingCtx::RGBA,
-                    WebGlRenderingCtx::UNSIGNED_BYTE,
-                    None,
-                );
+            let default_format = (
+                WebGlRenderingCtx::SRGB8_ALPHA8 as i32,
+                WebGlRenderingCtx::RGBA,
+                WebGlRenderingCtx::UNSIGNED_BYTE,
+            );
             #[cfg(feature = "webgl1")]
+            let default_format = (
+                WebGlRenderingCtx::RGBA as i32,
+                WebGlRenderingCtx::RGBA,
+                WebGlRenderingCtx::UNSIGNED_BYTE,
+            );
+            let (internal_format, format, type_) = self.format.unwrap_or(default_format);
             self.texture
                 .bind_mut()
                 .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
                     width as i32,
                     height as i32,
-                    WebGlRenderingCtx::RGBA as i32,
-                    WebGlRenderingCtx::RGBA,
-                    WebGlRenderingCtx::UNSIGNED_BYTE,
+                    internal_format,
+                    format,
+                    type_,
                     None,
                 );
         }
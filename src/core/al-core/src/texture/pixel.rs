@@ -1,4 +1,4 @@
-use crate::image::{ArrayBuffer, ArrayF32, ArrayI16, ArrayI32, ArrayU8};
+use crate::image::{ArrayBuffer, ArrayF32, ArrayI16, ArrayI32, ArrayU32, ArrayU8};
 use crate::webgl_ctx::WebGlRenderingCtx;
 use wasm_bindgen::JsValue;
 
@@ -248,4 +248,25 @@ impl Pixel for [i32; 1] {
 
         Ok([pixels.to_vec()[0]])
     }
-}
\ No newline at end of file
+}
+#[cfg(feature = "webgl2")]
+impl Pixel for [u32; 4] {
+    type Item = u32;
+    type Container = ArrayU32;
+    const BLACK: Self = [0, 0, 0, 0];
+
+    fn read_pixel(gl: &WebGlContext, x: i32, y: i32) -> Result<Self, JsValue> {
+        let pixels = js_sys::Uint32Array::new_with_length(4);
+        gl.read_pixels_with_opt_array_buffer_view(
+            x,
+            y,
+            1,
+            1,
+            WebGlRenderingCtx::RGBA_INTEGER,
+            WebGlRenderingCtx::UNSIGNED_INT,
+            Some(&pixels),
+        )?;
+        let pixels = pixels.to_vec();
+        Ok([pixels[0], pixels[1], pixels[2], pixels[3]])
+    }
+}
@@ -1,21 +1,34 @@
 use crate::{
-    async_task::{BuildCatalogIndex, ParseTableTask, TaskExecutor, TaskResult, TaskType},
+    async_task::{
+        BuildCatalogIndex, MergeCatalogSources, ParseTableTask, PropagateSources, TaskExecutor, TaskResult,
+        TaskType,
+    },
     camera::CameraViewPort,
     downloader::Downloader,
     math::{
         self,
-        angle::{Angle, ArcDeg},
+        angle::{Angle, ArcDeg, ArcSec},
         lonlat::{LonLat, LonLatT},
     },
     renderable::{
-        catalog::{Manager, Source},
-        grid::ProjetedGrid,
+        catalog::{sort_by_hpx_cell, Kernel, KernelType, Manager, ProgressiveCatalog, Scaling, Source, Subset},
+        grid::{ProjetedGrids, DEFAULT_GRID},
+        lines,
         moc::MOC,
         ImageCfg,
     },
-    healpix::coverage::HEALPixCoverage,
+    healpix::{
+        coverage::HEALPixCoverage,
+        map::HEALPixMap,
+        time_coverage::{SpaceTimeCoverage, TimeCoverage},
+    },
     shader::ShaderManager,
     renderable::Layers,
+    table::{
+        delimited::{self, DelimitedParser},
+        votable::{self, VOTableParser},
+        Field, Rows, TableBuilder, TableReader, Value,
+    },
     tile_fetcher::TileFetcherQueue,
     time::DeltaTime,
 };
@@ -39,6 +52,7 @@ use cgmath::Vector4;
 use web_sys::WebGl2RenderingContext;
 
 use std::{cell::RefCell};
+use std::convert::TryFrom;
 use std::rc::Rc;
 
 use std::collections::HashSet;
@@ -48,6 +62,33 @@ use al_core::FrameBufferObject;
 
 use al_api::image::ImageParams;
 
+use cdshealpix::sph_geom::Polygon;
+
+// Beyond, the cell indices cannot be represented by javascript numbers
+const MAX_JS_HEALPIX_ORDER: u8 = 24;
+
+// Convert a position in degrees into radians, the longitude in [0, 2pi[
+fn check_lonlat(lon: f64, lat: f64) -> Result<(f64, f64), JsValue> {
+    if !lon.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(JsValue::from_str(&format!("Invalid position: ({}, {})", lon, lat)));
+    }
+
+    Ok((lon.rem_euclid(360.0).to_radians() % (2.0 * std::f64::consts::PI), lat.to_radians()))
+}
+
+// Convert the semi-axes and the position angle of an ellipse or a box in degrees into radians,
+// the major axis first and the position angle in [0, pi[
+fn check_axes(a: f64, b: f64, pa: f64) -> Result<(f64, f64, f64), JsValue> {
+    if !(a > 0.0 && b > 0.0 && a.max(b) <= 90.0 && pa.is_finite()) {
+        return Err(JsValue::from_str("The semi-axes must be in ]0, 90] degrees"));
+    }
+
+    let (a, b, pa) = if a >= b { (a, b, pa) } else { (b, a, pa + 90.0) };
+    let pa = pa.rem_euclid(180.0).to_radians() % std::f64::consts::PI;
+
+    Ok((a.to_radians(), b.to_radians(), pa))
+}
+
 pub struct App {
     pub gl: WebGlContext,
 
@@ -63,8 +104,8 @@ pub struct App {
     request_redraw: bool,
     rendering: bool,
 
-    // The grid renderable
-    grid: ProjetedGrid,
+    // The grids renderable
+    grids: ProjetedGrids,
     // The moc renderable
     moc: MOC,
     // Catalog manager
@@ -82,6 +123,8 @@ pub struct App {
     out_of_fov: bool,
     //tasks_finished: bool,
     catalog_loaded: bool,
+    // Counter keying the tasks appending sources to catalogs
+    num_append_tasks: u64,
     start_time_frame: Time,
     last_time_request_for_new_tiles: Time,
     request_for_new_tiles: bool,
@@ -175,7 +218,7 @@ impl App {
         let manager = Manager::new(&gl, &mut shaders, &camera, &resources)?;
 
         // Grid definition
-        let grid = ProjetedGrid::new(&gl, &camera, &resources, &projection)?;
+        let grids = ProjetedGrids::new(&gl, &camera, &resources, &projection)?;
 
         // Variable storing the location to move to
         let inertial_move_animation = None;
@@ -188,6 +231,7 @@ impl App {
         let prev_center = Vector3::new(0.0, 1.0, 0.0);
         let out_of_fov = false;
         let catalog_loaded = false;
+        let num_append_tasks = 0;
 
         let colormaps = Colormaps::new(&gl)?;
 
@@ -200,7 +244,7 @@ impl App {
 
         let request_for_new_tiles = true;
 
-        let moc = MOC::new(&gl);
+        let moc = MOC::new(&gl)?;
 
         gl.clear_color(0.15, 0.15, 0.15, 1.0);
 
@@ -223,8 +267,8 @@ impl App {
             time_start_blending,
             rendering,
             request_redraw,
-            // The grid renderable
-            grid,
+            // The grids renderable
+            grids,
             // MOCs renderable
             moc,
             // The catalog renderable
@@ -243,6 +287,7 @@ impl App {
 
             //tasks_finished,
             catalog_loaded,
+            num_append_tasks,
 
             tile_fetcher,
 
@@ -363,38 +408,113 @@ impl App {
 
         Ok(tiles_available)
     }*/
-    /*fn run_tasks(&mut self, dt: DeltaTime) -> Result<(), JsValue> {
+    fn run_tasks(&mut self, dt: DeltaTime) -> Result<(), JsValue> {
         let tasks_time = (dt.0 * 0.5).min(8.3);
         let results = self.exec.borrow_mut().run(tasks_time);
-        self.tasks_finished = !results.is_empty();
 
-        // Retrieve back all the tiles that have been
-        // copied to the GPU
-        // This is important for the tile buffer to know which
-        // requests can be reused to query more tiles
         for result in results {
             match result {
                 TaskResult::TableParsed {
                     name,
                     sources,
+                    attributes,
                     colormap,
                 } => {
-                    self.manager.add_catalog(
-                        name,
+                    self.manager.add_catalog::<ProjectionType>(
+                        name.clone(),
                         sources,
+                        attributes,
                         colormap,
                         &mut self.shaders,
                         &self.camera,
-                        self.surveys.get_view().unwrap_abort(),
                     );
+                    self.spawn_propagation_task(name);
                     self.catalog_loaded = true;
                     self.request_redraw = true;
-                } //TaskResult::TileSentToGPU { tile } => todo!()
+                }
+                TaskResult::TableParsingFailed { name, message } => {
+                    al_core::log::console_warn(format!("{} catalog: {}", name, message));
+                }
+                TaskResult::CatalogSourcesReady {
+                    name,
+                    sources,
+                    append,
+                } => {
+                    // The catalog may have been removed in the meantime
+                    let res = if append {
+                        self.manager
+                            .append_catalog_sources(&name, sources, Default::default())
+                            .map(|()| self.spawn_merge_task(name))
+                    } else {
+                        self.manager
+                            .set_catalog_sources(&name, sources, Default::default(), &self.camera)
+                            .map(|()| self.spawn_propagation_task(name))
+                    };
+
+                    if let Err(err) = res {
+                        al_core::log::console_warn(format!("{:?}", err));
+                    }
+                    self.request_redraw = true;
+                }
+                TaskResult::CatalogSourcesMerged { name, merge } => {
+                    // Outdated merges are discarded, the sources they were
+                    // appending being merged again if the catalog is still there
+                    match self.manager.set_catalog_merged_sources(&name, merge, &self.camera) {
+                        Ok(merged) => {
+                            if merged {
+                                self.spawn_propagation_task(name.clone());
+                            }
+                            self.spawn_merge_task(name);
+                        }
+                        Err(err) => al_core::log::console_warn(format!("{:?}", err)),
+                    }
+                    self.request_redraw = true;
+                }
+                TaskResult::CatalogSourcesPropagated {
+                    name,
+                    reference,
+                    sources,
+                    rows,
+                    generation,
+                } => {
+                    // Outdated propagations are discarded
+                    let res = self.manager.set_catalog_propagated_sources(
+                        &name,
+                        reference,
+                        sources,
+                        &rows,
+                        generation,
+                        &self.camera,
+                    );
+
+                    if let Err(err) = res {
+                        al_core::log::console_warn(format!("{:?}", err));
+                    }
+                    self.request_redraw = true;
+                }
+                TaskResult::ProgressiveCatalogReady {
+                    name,
+                    catalog,
+                    colormap,
+                } => {
+                    self.manager.add_progressive_catalog::<ProjectionType>(
+                        name,
+                        catalog,
+                        colormap,
+                        &mut self.shaders,
+                        &self.camera,
+                    );
+                    // Ask for the tiles of the view
+                    for query in self.manager.get_catalog_tiles_to_fetch(&self.camera) {
+                        self.downloader.fetch(query);
+                    }
+                    self.catalog_loaded = true;
+                }
             }
         }
 
         Ok(())
-    }*/
+    }
 }
 
 use al_api::cell::HEALPixCellProjeted;
@@ -451,19 +571,289 @@ impl App {
     }
 
     pub(crate) fn add_moc(&mut self, params: al_api::moc::MOC, moc: HEALPixCoverage) -> Result<(), JsValue> {
+        lines::check_dash(params.get_line_dash())?;
         self.moc.insert(moc, params, &self.camera, &self.projection);
 
         Ok(())
     }
 
+    pub(crate) fn add_space_time_moc(&mut self, params: al_api::moc::MOC, moc: SpaceTimeCoverage) -> Result<(), JsValue> {
+        lines::check_dash(params.get_line_dash())?;
+        self.moc.insert_space_time(moc, params, &self.camera, &self.projection);
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_space_time_moc(&self, params: &al_api::moc::MOC) -> Result<&SpaceTimeCoverage, JsValue> {
+        self.moc.get_space_time(params)
+            .ok_or_else(|| JsValue::from_str(&format!("{} Space-Time MOC not found", params.get_uuid())))
+    }
+
+    /// Set the time window the Space-Time MOCs are projected on
+    ///
+    /// They are displayed entirely if no window is given
+    pub(crate) fn set_time_window(&mut self, time_window: Option<TimeCoverage>) -> Result<(), JsValue> {
+        self.moc.set_time_window(time_window, &self.camera, &self.projection);
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    fn check_moc_order(order: u8) -> Result<(), JsValue> {
+        if order > MAX_JS_HEALPIX_ORDER {
+            Err(JsValue::from_str(&format!(
+                "The order of the MOC must be lower than {}",
+                MAX_JS_HEALPIX_ORDER + 1
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Add a MOC built by the user as a new layer if parameters are given
+    fn add_computed_moc(
+        &mut self,
+        moc: HEALPixCoverage,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<HEALPixCoverage, JsValue> {
+        if let Some(params) = params {
+            self.add_moc(params, moc.clone())?;
+            self.request_redraw = true;
+        }
+
+        Ok(moc)
+    }
+
+    /// Build the MOC of a cone
+    ///
+    /// # Arguments
+    ///
+    /// * `lon`, `lat` - The center of the cone in ICRS, in degrees
+    /// * `radius` - The radius of the cone in degrees
+    /// * `params` - The parameters of the MOC layer, the MOC not being displayed if not given
+    pub(crate) fn moc_from_cone(
+        &mut self,
+        lon: f64,
+        lat: f64,
+        radius: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<HEALPixCoverage, JsValue> {
+        Self::check_moc_order(order)?;
+        let (lon, lat) = check_lonlat(lon, lat)?;
+        if !(radius > 0.0 && radius <= 180.0) {
+            return Err(JsValue::from_str("The radius must be in ]0, 180] degrees"));
+        }
+
+        let moc = HEALPixCoverage::from_cone(lon, lat, radius.to_radians(), order);
+        self.add_computed_moc(moc, params)
+    }
+
+    /// Build the MOC of an ellipse
+    ///
+    /// # Arguments
+    ///
+    /// * `lon`, `lat` - The center of the ellipse in ICRS, in degrees
+    /// * `a`, `b` - The semi-axes of the ellipse, lower than 90 degrees
+    /// * `pa` - The position angle of the `a` axis, east of north, in degrees
+    /// * `params` - The parameters of the MOC layer, the MOC not being displayed if not given
+    pub(crate) fn moc_from_ellipse(
+        &mut self,
+        (lon, lat): (f64, f64),
+        (a, b): (f64, f64),
+        pa: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<HEALPixCoverage, JsValue> {
+        Self::check_moc_order(order)?;
+        let (lon, lat) = check_lonlat(lon, lat)?;
+        let (a, b, pa) = check_axes(a, b, pa)?;
+
+        let moc = HEALPixCoverage::from_ellipse(lon, lat, a, b, pa, order);
+        self.add_computed_moc(moc, params)
+    }
+
+    /// Build the MOC of a box delimited by great circles
+    ///
+    /// # Arguments
+    ///
+    /// * `lon`, `lat` - The center of the box in ICRS, in degrees
+    /// * `a`, `b` - The half-widths of the box, lower than 90 degrees
+    /// * `pa` - The position angle of the `a` axis, east of north, in degrees
+    /// * `params` - The parameters of the MOC layer, the MOC not being displayed if not given
+    pub(crate) fn moc_from_box(
+        &mut self,
+        (lon, lat): (f64, f64),
+        (a, b): (f64, f64),
+        pa: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<HEALPixCoverage, JsValue> {
+        Self::check_moc_order(order)?;
+        let (lon, lat) = check_lonlat(lon, lat)?;
+        let (a, b, pa) = check_axes(a, b, pa)?;
+
+        let moc = HEALPixCoverage::from_box(lon, lat, a, b, pa, order);
+        self.add_computed_moc(moc, params)
+    }
+
+    /// Build the MOC of a spherical polygon, the smallest of the two areas delimited by its vertices
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The (lon, lat) vertices in ICRS, in degrees
+    /// * `params` - The parameters of the MOC layer, the MOC not being displayed if not given
+    pub(crate) fn moc_from_polygon(
+        &mut self,
+        vertices: &[(f64, f64)],
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<HEALPixCoverage, JsValue> {
+        Self::check_moc_order(order)?;
+        if vertices.len() < 3 {
+            return Err(JsValue::from_str("A polygon must have at least 3 vertices"));
+        }
+        let vertices = vertices
+            .iter()
+            .map(|&(lon, lat)| check_lonlat(lon, lat))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let moc = HEALPixCoverage::from_polygon(&vertices, order);
+        self.add_computed_moc(moc, params)
+    }
+
+    /// Build the MOC of a zone delimited by meridians and parallels
+    ///
+    /// The zone crosses the primary meridian if `lon_min > lon_max`
+    ///
+    /// # Arguments
+    ///
+    /// * `lon_min`, `lat_min` - The bottom left corner in ICRS, in degrees
+    /// * `lon_max`, `lat_max` - The top right corner in ICRS, in degrees
+    /// * `params` - The parameters of the MOC layer, the MOC not being displayed if not given
+    pub(crate) fn moc_from_zone(
+        &mut self,
+        lon_min: f64,
+        lat_min: f64,
+        lon_max: f64,
+        lat_max: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<HEALPixCoverage, JsValue> {
+        Self::check_moc_order(order)?;
+        let (lon_min, lat_min) = check_lonlat(lon_min, lat_min)?;
+        let (lon_max, lat_max) = check_lonlat(lon_max, lat_max)?;
+        if lat_min >= lat_max {
+            return Err(JsValue::from_str("The minimum latitude must be lower than the maximum one"));
+        }
+        // The zone ends at 360 degrees rather than at 0
+        let lon_max = if lon_max == 0.0 { 2.0 * std::f64::consts::PI } else { lon_max };
+
+        let moc = HEALPixCoverage::from_zone(lon_min, lat_min, lon_max, lat_max, order);
+        self.add_computed_moc(moc, params)
+    }
+
+    /// Download a MOC and add it as a new layer once received
+    ///
+    /// Its serialization, FITS, JSON or ASCII, is guessed from its content
+    pub(crate) fn add_moc_from_url(&mut self, params: al_api::moc::MOC, url: String) -> Result<(), JsValue> {
+        // Checked before the MOC is fetched
+        lines::check_dash(params.get_line_dash())?;
+        self.downloader.fetch(query::Moc::layer(url, params));
+
+        Ok(())
+    }
+
+    pub(crate) fn get_moc_or_err(&self, params: &al_api::moc::MOC) -> Result<&HEALPixCoverage, JsValue> {
+        self.get_moc(params)
+            .ok_or_else(|| JsValue::from_str(&format!("{} MOC not found", params.get_uuid())))
+    }
+
+    // Insert the result of an operation between loaded MOCs as a new MOC layer
+    fn add_moc_from_operation(
+        &mut self,
+        params: al_api::moc::MOC,
+        op: impl FnOnce(&Self) -> Result<HEALPixCoverage, JsValue>,
+    ) -> Result<(), JsValue> {
+        let moc = op(self)?;
+        self.add_moc(params, moc)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn union_mocs(
+        &mut self,
+        lhs: &al_api::moc::MOC,
+        rhs: &al_api::moc::MOC,
+        params: al_api::moc::MOC,
+    ) -> Result<(), JsValue> {
+        self.add_moc_from_operation(params, |app| {
+            Ok(HEALPixCoverage(app.get_moc_or_err(lhs)?.union(app.get_moc_or_err(rhs)?)))
+        })
+    }
+
+    pub(crate) fn intersect_mocs(
+        &mut self,
+        lhs: &al_api::moc::MOC,
+        rhs: &al_api::moc::MOC,
+        params: al_api::moc::MOC,
+    ) -> Result<(), JsValue> {
+        self.add_moc_from_operation(params, |app| {
+            Ok(HEALPixCoverage(app.get_moc_or_err(lhs)?.intersection(app.get_moc_or_err(rhs)?)))
+        })
+    }
+
+    pub(crate) fn subtract_mocs(
+        &mut self,
+        lhs: &al_api::moc::MOC,
+        rhs: &al_api::moc::MOC,
+        params: al_api::moc::MOC,
+    ) -> Result<(), JsValue> {
+        self.add_moc_from_operation(params, |app| {
+            Ok(HEALPixCoverage(app.get_moc_or_err(lhs)?.minus(app.get_moc_or_err(rhs)?)))
+        })
+    }
+
+    pub(crate) fn symmetric_difference_mocs(
+        &mut self,
+        lhs: &al_api::moc::MOC,
+        rhs: &al_api::moc::MOC,
+        params: al_api::moc::MOC,
+    ) -> Result<(), JsValue> {
+        self.add_moc_from_operation(params, |app| {
+            Ok(HEALPixCoverage(app.get_moc_or_err(lhs)?.xor(app.get_moc_or_err(rhs)?)))
+        })
+    }
+
+    pub(crate) fn complement_moc(&mut self, moc: &al_api::moc::MOC, params: al_api::moc::MOC) -> Result<(), JsValue> {
+        self.add_moc_from_operation(params, |app| Ok(HEALPixCoverage(app.get_moc_or_err(moc)?.complement())))
+    }
+
+    pub(crate) fn degrade_moc(&mut self, moc: &al_api::moc::MOC, order: u8, params: al_api::moc::MOC) -> Result<(), JsValue> {
+        self.add_moc_from_operation(params, |app| {
+            let moc = app.get_moc_or_err(moc)?;
+            if order > moc.depth_max() {
+                return Err(JsValue::from_str(&format!(
+                    "The order must be lower than the one of the MOC: {}",
+                    moc.depth_max()
+                )));
+            }
+
+            Ok(HEALPixCoverage(moc.degraded(order)))
+        })
+    }
+
     pub(crate) fn remove_moc(&mut self, params: &al_api::moc::MOC) -> Result<(), JsValue> {
-        self.moc.remove(params, &self.camera)
+        self.moc.remove(params, &self.camera, &self.projection)
             .ok_or_else(|| JsValue::from_str("MOC not found"))?;
 
         Ok(())
     }
 
     pub(crate) fn set_moc_params(&mut self, params: al_api::moc::MOC) -> Result<(), JsValue> {
+        lines::check_dash(params.get_line_dash())?;
         self.moc.set_params(params, &self.camera, &self.projection)
             .ok_or_else(|| JsValue::from_str("MOC not found"))?;
         self.request_redraw = true;
@@ -471,8 +861,9 @@ impl App {
         Ok(())
     }
 
-    pub(crate) fn update(&mut self, _dt: DeltaTime) -> Result<(), JsValue> {
+    pub(crate) fn update(&mut self, dt: DeltaTime) -> Result<(), JsValue> {
         //let available_tiles = self.run_tasks(dt)?;
+        self.run_tasks(dt)?;
         if let Some(InertiaAnimation {
             time_start_anim,
             d0,
@@ -630,28 +1021,50 @@ impl App {
                             }
                         },
                         Resource::Moc(moc) => {
-                            let moc_url = moc.get_url();
-                            let url = &moc_url[..moc_url.find("/Moc.fits").unwrap_abort()];
-                            if let Some(hips) = self.layers.get_mut_hips_from_url(url) {
-                                let request::moc::Moc {
-                                    moc,
-                                    ..
-                                } = moc;
-    
-                                if let Some(moc) = &*moc.lock().unwrap_abort() {
-                                    hips.set_moc(moc.clone());
+                            let request::moc::Moc {
+                                moc,
+                                url,
+                                params,
+                                hips,
+                            } = moc;
+                            let moc = moc.lock().unwrap_abort().take();
+
+                            if hips {
+                                let hips_url = url.find("/Moc.fits").map(|idx| &url[..idx]);
+                                if let (Some(hips), Some(moc)) = (hips_url.and_then(|url| self.layers.get_mut_hips_from_url(url)), moc) {
+                                    hips.set_moc(moc);
 
                                     self.request_for_new_tiles = true;
                                     self.request_redraw = true;
-                                };
+                                }
+                            } else if let Some(moc) = moc {
+                                self.add_moc(params, moc)?;
+                                self.request_redraw = true;
+                            } else {
+                                al_core::log::console_warn(format!("{}: the MOC could not be loaded", url));
                             }
                         },
+                        Resource::CatalogTile(tile) => {
+                            self.manager.add_catalog_tile(tile);
+                        },
                     }
                 } else {
                     self.downloader.delay_rsc(rsc);
                 }
             }
 
+            // Gather the sources of the progressive catalogs whose tiles changed
+            let (replaced, appended) = self.manager.refresh_progressive_catalogs(&self.camera);
+            if !replaced.is_empty() {
+                self.request_redraw = true;
+            }
+            for name in replaced {
+                self.spawn_propagation_task(name);
+            }
+            for name in appended {
+                self.spawn_merge_task(name);
+            }
+
             if num_tile_received > 0 {
                 self.tile_fetcher
                     .notify(num_tile_received, &mut self.downloader);
@@ -695,10 +1108,11 @@ impl App {
         // Finally update the camera that reset the flag camera changed
         if has_camera_moved {
             // Catalogues update
-            /*if let Some(view) = self.layers.get_view() {
-                self.manager.update(&self.camera, view);
-            }*/
-            self.grid.update(&self.camera, &self.projection);
+            self.manager.update(&self.camera);
+            for query in self.manager.get_catalog_tiles_to_fetch(&self.camera) {
+                self.downloader.fetch(query);
+            }
+            self.grids.update(&self.camera, &self.projection);
             // MOCs update
             self.moc.update(&self.camera, &self.projection);
         }
@@ -769,7 +1183,7 @@ impl App {
             let gl = self.gl.clone();
             let camera = &self.camera;
 
-            let grid = &mut self.grid;
+            let grids = &mut self.grids;
             let layers = &mut self.layers;
             let catalogs = &self.manager;
             let colormaps = &self.colormaps;
@@ -786,7 +1200,7 @@ impl App {
                     // Draw the catalog
                     catalogs.draw(&gl, shaders, camera, colormaps, fbo_view)?;
 
-                    grid.draw(camera, shaders)?;
+                    grids.draw(camera)?;
 
                     Ok(())
                 },
@@ -832,13 +1246,13 @@ impl App {
             self.gl.clear(web_sys::WebGl2RenderingContext::COLOR_BUFFER_BIT);
 
             self.layers.draw(&self.camera, shaders, &self.colormaps, &self.projection)?;
-            self.moc.draw(shaders, &self.camera);
+            self.moc.draw(shaders, &self.camera, self.layers.get_raytracer());
 
             // Draw the catalog
             //let fbo_view = &self.fbo_view;
             //catalogs.draw(&gl, shaders, camera, colormaps, fbo_view)?;
-            //catalogs.draw(&gl, shaders, camera, colormaps, None, self.projection)?;
-            self.grid.draw(&self.camera, shaders)?;
+            self.manager.draw(&self.gl, shaders, &self.camera, &self.colormaps, None, &self.projection)?;
+            self.grids.draw(&self.camera)?;
 
             //let dpi  = self.camera.get_dpi();
             //ui.draw(&gl, dpi)?;
@@ -875,6 +1289,22 @@ impl App {
         Ok(())
     }
 
+    pub(crate) fn set_layer_moc_mask(&mut self, layer: &str, params: &al_api::moc::MOC, inverted: bool) -> Result<(), JsValue> {
+        let moc = self.moc.get(params)
+            .ok_or_else(|| JsValue::from_str(&format!("{} MOC not found", params.get_uuid())))?;
+        self.layers.set_moc_mask(layer, moc, inverted)?;
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn remove_layer_moc_mask(&mut self, layer: &str) {
+        self.layers.remove_moc_mask(layer);
+
+        self.request_redraw = true;
+    }
+
     pub(crate) fn add_image_survey(&mut self, hips_cfg: HiPSCfg) -> Result<(), JsValue> {
         let hips = self.layers.add_image_survey(&self.gl, hips_cfg, &mut self.camera, &self.projection)?;
         self.tile_fetcher.launch_starting_hips_requests(hips, &mut self.downloader);
@@ -1144,7 +1574,7 @@ impl App {
         self.camera.get_longitude_reversed()
     }
 
-    pub(crate) fn add_catalog(&mut self, name: String, table: JsValue, _colormap: String) {
+    pub(crate) fn add_catalog(&mut self, name: String, table: JsValue, colormap: String) {
         let mut exec_ref = self.exec.borrow_mut();
         let table = table;
 
@@ -1168,10 +1598,157 @@ impl App {
                 TaskResult::TableParsed {
                     name,
                     sources: results.into_boxed_slice(),
+                    attributes: Default::default(),
+                    colormap,
                 }
             });
     }
 
+    fn check_colormap(&self, colormap: &str) -> Result<(), JsValue> {
+        self.colormaps
+            .get_id(colormap)
+            .map(|_| ())
+            .ok_or_else(|| JsValue::from_str(&format!("{} colormap not found", colormap)))
+    }
+
+    // Parse a table as its bytes come and build the catalog index
+    fn spawn_table_task<R, S>(&mut self, name: String, colormap: String, mut reader: R, chunks: S)
+    where
+        R: TableReader + 'static,
+        S: futures::Stream<Item = Result<Vec<u8>, JsValue>> + 'static,
+    {
+        // Maximum number of discarded rows reported individually
+        const MAX_ROW_ERRORS_REPORTED: usize = 20;
+
+        self.exec
+            .borrow_mut()
+            .spawner()
+            .spawn(TaskType::ParseTableStreamTask(name.clone()), async move {
+                let parse = async {
+                    futures::pin_mut!(chunks);
+                    while let Some(chunk) = chunks.next().await {
+                        reader.feed(&chunk?)?;
+                    }
+
+                    let (sources, mut attributes, errors) = reader.finish()?;
+                    for err in errors.iter().take(MAX_ROW_ERRORS_REPORTED) {
+                        al_core::log::console_warn(format!(
+                            "{} catalog: line {} discarded: {}",
+                            name, err.line, err.message
+                        ));
+                    }
+                    if errors.len() > MAX_ROW_ERRORS_REPORTED {
+                        al_core::log::console_warn(format!(
+                            "{} catalog: {} rows discarded in total",
+                            name, errors.len()
+                        ));
+                    }
+
+                    let mut stream_sort = BuildCatalogIndex::new(sources);
+                    while stream_sort.next().await.is_some() {}
+
+                    // Reorder the attributes following the sorted sources
+                    attributes.permute(&stream_sort.rows);
+
+                    Ok::<_, JsValue>((stream_sort.sources, attributes))
+                };
+
+                match parse.await {
+                    Ok((sources, attributes)) => TaskResult::TableParsed {
+                        name,
+                        sources: sources.into_boxed_slice(),
+                        attributes,
+                        colormap,
+                    },
+                    Err(err) => TaskResult::TableParsingFailed {
+                        name,
+                        message: err.as_string().unwrap_or_else(|| format!("{:?}", err)),
+                    },
+                }
+            });
+    }
+
+    pub(crate) fn add_votable(&mut self, name: String, data: Vec<u8>, colormap: String) -> Result<(), JsValue> {
+        self.check_colormap(&colormap)?;
+
+        let chunks = futures::stream::once(async move { Ok(data) });
+        self.spawn_table_task(name, colormap, VOTableParser::new(), chunks);
+
+        Ok(())
+    }
+
+    pub(crate) fn add_votable_from_url(&mut self, name: String, url: String, colormap: String) -> Result<(), JsValue> {
+        self.check_colormap(&colormap)?;
+
+        self.spawn_table_task(name, colormap, VOTableParser::new(), fetch_chunks(url));
+
+        Ok(())
+    }
+
+    pub(crate) fn add_delimited_table(&mut self, name: String, data: Vec<u8>, colormap: String) -> Result<(), JsValue> {
+        self.check_colormap(&colormap)?;
+
+        let chunks = futures::stream::once(async move { Ok(data) });
+        self.spawn_table_task(name, colormap, DelimitedParser::new(), chunks);
+
+        Ok(())
+    }
+
+    pub(crate) fn add_delimited_table_from_url(&mut self, name: String, url: String, colormap: String) -> Result<(), JsValue> {
+        self.check_colormap(&colormap)?;
+
+        self.spawn_table_task(name, colormap, DelimitedParser::new(), fetch_chunks(url));
+
+        Ok(())
+    }
+
+    pub(crate) fn add_progressive_catalog(
+        &mut self,
+        name: String,
+        url: String,
+        min_order: u8,
+        max_order: u8,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.check_colormap(&colormap)?;
+
+        self.exec
+            .borrow_mut()
+            .spawner()
+            .spawn(TaskType::ParseCatalogMetadataTask(name.clone()), async move {
+                // The Metadata.xml gives the UCDs of the columns of the tiles
+                let metadata_url = format!("{}/Metadata.xml", url);
+                let parse = async {
+                    let mut parser = VOTableParser::new();
+
+                    let chunks = fetch_chunks(metadata_url);
+                    futures::pin_mut!(chunks);
+                    while let Some(chunk) = chunks.next().await {
+                        parser.feed(&chunk?)?;
+                    }
+
+                    Ok::<_, JsValue>(parser.fields().to_vec())
+                };
+
+                // Without metadata, the position is found from the column names of the tiles
+                let fields = parse.await.unwrap_or_else(|err| {
+                    al_core::log::console_warn(format!(
+                        "{} catalog: Metadata.xml could not be read: {:?}",
+                        name, err
+                    ));
+                    vec![]
+                });
+
+                TaskResult::ProgressiveCatalogReady {
+                    name,
+                    catalog: ProgressiveCatalog::new(url, min_order, max_order, fields),
+                    colormap,
+                }
+            });
+
+        Ok(())
+    }
+
     pub(crate) fn resize(&mut self, width: f32, height: f32) {
         self.camera.set_screen_size(width, height, &self.projection);
         self.camera.set_aperture(self.camera.get_aperture(), &self.projection);
@@ -1191,6 +1768,155 @@ impl App {
         self.layers.set_survey_url(past_url, new_url)
     }
 
+    pub(crate) fn remove_catalog(&mut self, name: String) -> Result<(), JsValue> {
+        self.manager.remove_catalog(&name)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn rename_catalog(&mut self, name: String, new_name: String) -> Result<(), JsValue> {
+        self.manager.rename_catalog(&name, new_name.clone())?;
+        self.spawn_merge_task(new_name);
+
+        Ok(())
+    }
+
+    // Sort new sources given as (ra, dec) pairs in degrees for an existing catalog
+    fn spawn_catalog_sources_task(&mut self, name: String, radec: Vec<f64>, append: bool) -> Result<(), JsValue> {
+        // Check the catalog exists before sorting its sources
+        self.manager.get_mut_catalog(&name)?;
+
+        let task = if append {
+            self.num_append_tasks += 1;
+            TaskType::AppendCatalogSourcesTask(name.clone(), self.num_append_tasks)
+        } else {
+            TaskType::SetCatalogSourcesTask(name.clone())
+        };
+
+        self.exec.borrow_mut().spawner().spawn(task, async move {
+            let sources = radec
+                .chunks_exact(2)
+                .filter(|p| p[0].is_finite() && (-90.0..=90.0).contains(&p[1]))
+                .map(|p| Source::from(&[p[0] as f32, p[1] as f32][..]))
+                .collect::<Vec<_>>();
+
+            // Sort the sources by chunks not to block the rendering
+            let mut stream_sort = BuildCatalogIndex::new(sources);
+            while stream_sort.next().await.is_some() {}
+
+            TaskResult::CatalogSourcesReady {
+                name,
+                sources: stream_sort.sources.into_boxed_slice(),
+                append,
+            }
+        });
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_sources(&mut self, name: String, radec: Vec<f64>) -> Result<(), JsValue> {
+        self.spawn_catalog_sources_task(name, radec, false)
+    }
+
+    pub(crate) fn append_catalog_sources(&mut self, name: String, radec: Vec<f64>) -> Result<(), JsValue> {
+        self.spawn_catalog_sources_task(name, radec, true)
+    }
+
+    // Merge the next sources appended to a catalog, chunk by chunk
+    // not to block the rendering
+    fn spawn_merge_task(&mut self, name: String) {
+        if let Some(merge) = self.manager.next_catalog_merge(&name) {
+            self.exec
+                .borrow_mut()
+                .spawner()
+                .spawn(TaskType::MergeCatalogSourcesTask(name.clone()), async move {
+                    let mut stream = MergeCatalogSources::new(merge);
+                    while stream.next().await.is_some() {}
+
+                    TaskResult::CatalogSourcesMerged {
+                        name,
+                        merge: stream.merge,
+                    }
+                });
+        }
+    }
+
+    // Move the sources of a catalog to the epoch of the manager,
+    // if it has proper motions
+    fn spawn_propagation_task(&mut self, name: String) {
+        let propagation = match self.manager.get_catalog(&name) {
+            Ok(catalog) => catalog.propagation(self.manager.get_epoch()),
+            Err(_) => None,
+        };
+
+        if let Some(propagation) = propagation {
+            self.exec
+                .borrow_mut()
+                .spawner()
+                .spawn(TaskType::PropagateCatalogTask(name.clone()), async move {
+                    let generation = propagation.generation;
+
+                    let mut stream = PropagateSources::new(propagation);
+                    while stream.next().await.is_some() {}
+                    let PropagateSources { propagation, sources } = stream;
+
+                    // The cells of the sources may have changed
+                    let mut stream_sort = BuildCatalogIndex::new(sources);
+                    while stream_sort.next().await.is_some() {}
+
+                    let reference = stream_sort
+                        .rows
+                        .iter()
+                        .map(|&r| propagation.reference[r as usize].clone())
+                        .collect();
+
+                    TaskResult::CatalogSourcesPropagated {
+                        name,
+                        reference,
+                        sources: stream_sort.sources.into_boxed_slice(),
+                        rows: stream_sort.rows,
+                        generation,
+                    }
+                });
+        }
+    }
+
+    /// Propagate the catalogs having proper motions to an epoch (in Julian years),
+    /// or move them back to their reference positions if `None`
+    pub(crate) fn set_catalogs_epoch(&mut self, epoch: Option<f64>) -> Result<(), JsValue> {
+        if epoch.map(|e| !e.is_finite()).unwrap_or(false) {
+            return Err("The epoch must be a finite number".into());
+        }
+
+        self.manager.set_epoch(epoch);
+        for name in self.manager.get_catalog_names() {
+            self.spawn_propagation_task(name);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_reference_epoch(&mut self, name: String, epoch: Option<f64>) -> Result<(), JsValue> {
+        if epoch.map(|e| !e.is_finite()).unwrap_or(false) {
+            return Err("The epoch must be a finite number".into());
+        }
+
+        self.manager.get_mut_catalog(&name)?.set_reference_epoch(epoch);
+        self.spawn_propagation_task(name);
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_colormap(&mut self, name: String, colormap: String) -> Result<(), JsValue> {
+        self.check_colormap(&colormap)?;
+
+        self.manager.get_mut_catalog(&name)?.set_colormap(colormap);
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
     pub(crate) fn set_catalog_opacity(&mut self, name: String, opacity: f32) -> Result<(), JsValue> {
         let catalog = self.manager.get_mut_catalog(&name).map_err(|e| {
             let err: JsValue = e.into();
@@ -1215,8 +1941,233 @@ impl App {
         Ok(())
     }
 
+    pub(crate) fn set_catalog_lod_budget(&mut self, name: String, budget: usize) -> Result<(), JsValue> {
+        self.manager.set_catalog_lod_budget(&name, budget, &self.camera)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_kernel(&mut self, name: String, kernel: String, width: f64) -> Result<(), JsValue> {
+        let kind = KernelType::try_from(kernel.as_str())?;
+        let kernel = Kernel::new(kind, ArcSec(width).into())?;
+
+        self.manager.set_catalog_kernel(&name, kernel, &self.camera)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_scaling(&mut self, name: String, max_density: Option<f64>) -> Result<(), JsValue> {
+        let scaling = match max_density {
+            Some(max_density) if max_density > 0.0 => Scaling::Fixed(max_density),
+            Some(_) => return Err("The maximum density must be positive".into()),
+            None => Scaling::Auto,
+        };
+
+        self.manager.set_catalog_scaling(&name, scaling, &self.camera)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_weight_column(&mut self, name: String, column: Option<String>) -> Result<(), JsValue> {
+        self.manager.set_catalog_weight_column(&name, column, &self.camera)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_catalog_density_map(
+        &self,
+        name: &str,
+        order: u8,
+        weight_column: Option<&str>,
+    ) -> Result<HEALPixMap, JsValue> {
+        if order > MAX_JS_HEALPIX_ORDER {
+            return Err(JsValue::from_str(&format!(
+                "The order of the map must be lower than {}",
+                MAX_JS_HEALPIX_ORDER + 1
+            )));
+        }
+
+        let map = self.manager.get_catalog(name)?.density_map(order, weight_column)?;
+        Ok(map)
+    }
+
+    // Render a density map as a catalog whose sources are the centers of the cells
+    // weighted by their value, each one spread over the area of its cell
+    pub(crate) fn add_catalog_density_map(
+        &mut self,
+        name: String,
+        order: u8,
+        weight_column: Option<String>,
+        layer: String,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.check_colormap(&colormap)?;
+        // The map does not replace an existing catalog
+        self.manager.check_catalog_absent(&layer)?;
+        let map = self.get_catalog_density_map(&name, order, weight_column.as_deref())?;
+
+        let fields = vec![
+            Field {
+                name: "ra".to_string(),
+                ucd: Some("pos.eq.ra;meta.main".to_string()),
+                unit: Some("deg".to_string()),
+                numeric: true,
+            },
+            Field {
+                name: "dec".to_string(),
+                ucd: Some("pos.eq.dec;meta.main".to_string()),
+                unit: Some("deg".to_string()),
+                numeric: true,
+            },
+            Field {
+                name: "value".to_string(),
+                numeric: true,
+                ..Default::default()
+            },
+        ];
+        let mut table = TableBuilder::with_position_columns(fields, 0, 1);
+        for (&cell, &value) in map.cells.iter().zip(map.values.iter()) {
+            let (lon, lat) = cdshealpix::nested::center(order, cell);
+            table.push_row(vec![
+                Value::Numeric(lon.to_degrees()),
+                Value::Numeric(lat.to_degrees()),
+                Value::Numeric(value),
+            ])?;
+        }
+        let (sources, mut attributes) = table.build();
+
+        // The nested cells are already nearly sorted
+        let rows = sort_by_hpx_cell(&sources);
+        let sources = rows.iter().map(|&r| sources[r as usize].clone()).collect::<Vec<_>>();
+        attributes.permute(&rows);
+
+        self.manager.add_catalog::<ProjectionType>(
+            layer.clone(),
+            sources.into_boxed_slice(),
+            attributes,
+            colormap,
+            &mut self.shaders,
+            &self.camera,
+        );
+
+        // A disk of the area of the cells
+        let cell_area = 4.0 * std::f64::consts::PI / (12_u64 << (2 * order as u64)) as f64;
+        let kernel = Kernel::new(KernelType::TopHat, Angle((cell_area / std::f64::consts::PI).sqrt()))?;
+        self.manager.set_catalog_weight_column(&layer, Some("value".to_string()), &self.camera)?;
+        self.manager.set_catalog_kernel(&layer, kernel, &self.camera)?;
+
+        self.catalog_loaded = true;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn add_catalog_coverage(&mut self, name: &str, order: u8, params: al_api::moc::MOC) -> Result<(), JsValue> {
+        let map = self.get_catalog_density_map(name, order, None)?;
+        self.add_moc(params, map.coverage())?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    /// Select the sources of a catalog inside a polygon drawn on the screen
+    ///
+    /// Returns the selected sources and the coverage of the polygon at `order`
+    ///
+    /// # Arguments
+    ///
+    /// * `screen_vertices` - The (x, y) vertices of the polygon in pixels
+    pub(crate) fn select_catalog_sources_in_polygon(
+        &mut self,
+        name: &str,
+        screen_vertices: &[(f64, f64)],
+        order: u8,
+    ) -> Result<(Rows, HEALPixCoverage), JsValue> {
+        Self::check_moc_order(order)?;
+        if screen_vertices.len() < 3 {
+            return Err(JsValue::from_str("A polygon must have at least 3 vertices"));
+        }
+
+        // Deproject the vertices into the ICRS frame of the catalogs
+        let view_system = self.camera.get_system();
+        let vertices = screen_vertices
+            .iter()
+            .map(|&(x, y)| {
+                let pos_world_space = self
+                    .projection
+                    .screen_to_world_space(&Vector2::new(x, y), &self.camera)
+                    .ok_or_else(|| JsValue::from_str("A vertex of the polygon is out of the projection"))?;
+                let pos_model_space = self.camera.get_final_rotation().rotate(&pos_world_space);
+                let (lon, lat) = math::lonlat::xyzw_to_radec(&coosys::apply_coo_system(
+                    view_system,
+                    &CooSystem::ICRSJ2000,
+                    &pos_model_space,
+                ));
+
+                Ok((lon.0, lat.0))
+            })
+            .collect::<Result<Vec<_>, JsValue>>()?;
+
+        let polygon = Polygon::new(
+            vertices
+                .iter()
+                .map(|&(lon, lat)| cdshealpix::sph_geom::coo3d::LonLat { lon, lat })
+                .collect(),
+        );
+        // The sources are looked for in the cells of depth 7 of the catalog index
+        let candidates = HEALPixCoverage::from_polygon(&vertices, 7);
+
+        let catalog = self.manager.get_mut_catalog(name)?;
+        catalog.select_in_polygon(&polygon, &candidates);
+        let (sources, attributes) = catalog.get_selection();
+
+        Ok((Rows::new(&sources, &attributes), HEALPixCoverage::from_polygon(&vertices, order)))
+    }
+
+    /// Serialize a subset of the sources of a catalog with their attributes
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - "csv" or "votable"
+    /// * `subset` - "all", "view" or "selection"
+    pub(crate) fn export_catalog(&mut self, name: &str, format: &str, subset: &str) -> Result<Vec<u8>, JsValue> {
+        let subset = Subset::try_from(subset)?;
+        let (sources, attributes) = self.manager.get_catalog_subset(name, subset, &self.camera)?;
+
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(delimited::write_csv(&sources, &attributes)),
+            "votable" | "xml" => Ok(votable::write_votable(name, &sources, &attributes)),
+            _ => Err(JsValue::from_str(&format!(
+                "{} format not supported. Expected: csv or votable",
+                format
+            ))),
+        }
+    }
+
     pub(crate) fn set_grid_cfg(&mut self, cfg: GridCfg) -> Result<(), JsValue> {
-        self.grid.set_cfg(cfg, &self.camera, &self.projection)?;
+        self.set_grid_cfg_by_id(DEFAULT_GRID, cfg)
+    }
+
+    pub(crate) fn set_grid_cfg_by_id(&mut self, id: &str, cfg: GridCfg) -> Result<(), JsValue> {
+        self.grids.set_cfg(id, cfg, &self.camera, &self.projection)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn add_grid(&mut self, id: String, cfg: GridCfg) -> Result<(), JsValue> {
+        self.grids.add(id, cfg, &self.camera, &self.projection)?;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn remove_grid(&mut self, id: &str) -> Result<(), JsValue> {
+        self.grids.remove(id)?;
         self.request_redraw = true;
 
         Ok(())
@@ -1424,3 +2375,36 @@ impl App {
         self.rendering
     }
 }
+
+// Stream of the bytes chunks of a file being downloaded
+fn fetch_chunks(url: String) -> impl futures::Stream<Item = Result<Vec<u8>, JsValue>> {
+    use futures::{TryFutureExt, TryStreamExt};
+    use js_sys::Uint8Array;
+    use wasm_streams::ReadableStream;
+    use web_sys::Response;
+
+    async move {
+        let window = web_sys::window().unwrap_abort();
+        let resp_value = JsFuture::from(window.fetch_with_str(&url)).await?;
+        let resp: Response = resp_value.dyn_into()?;
+        if !resp.ok() {
+            return Err(JsValue::from_str(&format!("{} could not be fetched: {}", url, resp.status_text())));
+        }
+
+        // Get the response's body as a JS ReadableStream
+        let raw_body = resp.body()
+            .ok_or_else(|| JsValue::from_str("Response has no body"))?;
+        let body = ReadableStream::from_raw(raw_body.dyn_into()?);
+
+        // Convert the JS ReadableStream to a Rust stream of bytes chunks
+        let chunks = body
+            .into_stream()
+            .and_then(|js_value| async move {
+                let bytes: Uint8Array = js_value.dyn_into()?;
+                Ok(bytes.to_vec())
+            });
+
+        Ok::<_, JsValue>(chunks)
+    }
+    .try_flatten_stream()
+}
@@ -7,10 +7,46 @@ use al_task_exec::Executor;
 pub type TaskExecutor = Executor<TaskType, TaskResult>;
 
 pub use crate::renderable::catalog::Source;
+use crate::table::Attributes;
+use crate::renderable::catalog::ProgressiveCatalog;
 pub enum TaskResult {
     TableParsed {
         name: String,
         sources: Box<[Source]>,
+        attributes: Attributes,
+        colormap: String,
+    },
+    TableParsingFailed {
+        name: String,
+        message: String,
+    },
+    // New sources of an existing catalog have been sorted
+    CatalogSourcesReady {
+        name: String,
+        sources: Box<[Source]>,
+        // Whether the sources are added to the ones of the catalog or replace them
+        append: bool,
+    },
+    // The sources of a catalog have been moved to another epoch and sorted
+    CatalogSourcesPropagated {
+        name: String,
+        // The positions at the reference epoch, in the order of the propagated sources
+        reference: Box<[Source]>,
+        sources: Box<[Source]>,
+        // Permutation of the sources to apply to the attributes
+        rows: Vec<u32>,
+        generation: u32,
+    },
+    // The sources appended to a catalog have been merged with its sources
+    CatalogSourcesMerged {
+        name: String,
+        merge: Merge,
+    },
+    // The Metadata.xml of a HiPS catalog has been retrieved
+    ProgressiveCatalogReady {
+        name: String,
+        catalog: ProgressiveCatalog,
+        colormap: String,
     },
     /*TileSentToGPU {
         tile: Tile,
@@ -21,6 +57,18 @@ pub enum TaskResult {
 pub enum TaskType {
     //ImageTile2GpuTask(Tile),
     ParseTableTask,
+    // Keyed by the name of the catalog so that
+    // several tables can be parsed concurrently
+    ParseTableStreamTask(String),
+    ParseCatalogMetadataTask(String),
+    // A new set of sources replaces the pending one
+    SetCatalogSourcesTask(String),
+    // Appended sources are all kept, hence the counter
+    AppendCatalogSourcesTask(String, u64),
+    // A new epoch replaces the pending propagation
+    PropagateCatalogTask(String),
+    // The appends of a catalog are merged one at a time
+    MergeCatalogSourcesTask(String),
 }
 
 use futures::stream::Stream;
@@ -95,11 +143,15 @@ use rand::Rng;
 use rand::SeedableRng;*/
 pub struct BuildCatalogIndex {
     pub sources: Vec<Source>,
+    // Original row index of each source. Once the index is built,
+    // it gives the permutation to apply to the attributes of the sources
+    pub rows: Vec<u32>,
     num_sorted_sources: usize,
     i: usize,
     j: usize,
     merging: bool,
     new_sorted_sources: Vec<Source>,
+    new_sorted_rows: Vec<u32>,
     ready: bool,
     chunk_size: usize,
     prev_num_sorted_sources: usize,
@@ -109,6 +161,8 @@ impl BuildCatalogIndex {
         let num_sorted_sources = 0;
         let merging = false;
         let new_sorted_sources = vec![];
+        let new_sorted_rows = vec![];
+        let rows = (0..(sources.len() as u32)).collect();
         let i = 0;
         let j = 0;
         let ready = false;
@@ -120,7 +174,9 @@ impl BuildCatalogIndex {
             i,
             j,
             new_sorted_sources,
+            new_sorted_rows,
             sources,
+            rows,
             ready,
             prev_num_sorted_sources,
             chunk_size,
@@ -147,8 +203,13 @@ impl Stream for BuildCatalogIndex {
             // Get a new chunk and sort it
             if !self.merging {
                 //let mut rng = StdRng::seed_from_u64(0);
-                // Get the chunk to sort
-                (&mut self.sources[a..b]).sort_unstable_by(|s1, s2| {
+                // Get the chunk to sort along with the row indices of its sources
+                let mut chunk = self.sources[a..b]
+                    .iter()
+                    .cloned()
+                    .zip(self.rows[a..b].iter().copied())
+                    .collect::<Vec<_>>();
+                chunk.sort_unstable_by(|(s1, _), (s2, _)| {
                     let (s1_lon, s1_lat) = s1.lonlat();
                     let (s2_lon, s2_lat) = s2.lonlat();
 
@@ -168,6 +229,10 @@ impl Stream for BuildCatalogIndex {
                     }
                     //ordering
                 });
+                for (k, (s, row)) in chunk.into_iter().enumerate() {
+                    self.sources[a + k] = s;
+                    self.rows[a + k] = row;
+                }
 
                 self.chunk_size = b - a;
                 self.prev_num_sorted_sources = a;
@@ -176,6 +241,7 @@ impl Stream for BuildCatalogIndex {
                 self.j = a;
                 self.num_sorted_sources = 0;
                 self.new_sorted_sources = Vec::with_capacity(b);
+                self.new_sorted_rows = Vec::with_capacity(b);
 
                 self.merging = true;
             } else {
@@ -186,14 +252,12 @@ impl Stream for BuildCatalogIndex {
                 // already sorted
                 let final_size = self.new_sorted_sources.capacity();
                 while self.num_sorted_sources < final_size {
-                    let v = if self.j == self.prev_num_sorted_sources + self.chunk_size {
-                        let v = self.sources[self.i].clone();
+                    let k = if self.j == self.prev_num_sorted_sources + self.chunk_size {
                         self.i += 1;
-                        v
+                        self.i - 1
                     } else if self.i == self.prev_num_sorted_sources {
-                        let v = self.sources[self.j].clone();
                         self.j += 1;
-                        v
+                        self.j - 1
                     } else {
                         let s1 = &self.sources[self.j];
                         let s2 = &self.sources[self.i];
@@ -203,17 +267,17 @@ impl Stream for BuildCatalogIndex {
                         let p1 = cdshealpix::nested::hash(7, s1_lon as f64, s1_lat as f64);
                         let p2 = cdshealpix::nested::hash(7, s2_lon as f64, s2_lat as f64);
                         if p1 <= p2 {
-                            let v = self.sources[self.j].clone();
                             self.j += 1;
-                            v
+                            self.j - 1
                         } else {
-                            let v = self.sources[self.i].clone();
                             self.i += 1;
-                            v
+                            self.i - 1
                         }
                     };
 
+                    let (v, row) = (self.sources[k].clone(), self.rows[k]);
                     self.new_sorted_sources.push(v);
+                    self.new_sorted_rows.push(row);
                     self.num_sorted_sources += 1;
 
                     // Every 10000 items sorted, we do a pending
@@ -225,6 +289,8 @@ impl Stream for BuildCatalogIndex {
                 let end = self.num_sorted_sources;
                 let new_sorted_sources = self.new_sorted_sources.clone();
                 self.sources.splice(..end, new_sorted_sources);
+                let new_sorted_rows = self.new_sorted_rows.clone();
+                self.rows.splice(..end, new_sorted_rows);
                 self.merging = false;
             }
 
@@ -233,6 +299,66 @@ impl Stream for BuildCatalogIndex {
     }
 }
 
+use crate::renderable::catalog::Propagation;
+// Task that propagates the sources of a catalog to an epoch
+pub struct PropagateSources {
+    pub propagation: Propagation,
+    pub sources: Vec<Source>,
+}
+
+impl PropagateSources {
+    pub fn new(propagation: Propagation) -> Self {
+        let sources = Vec::with_capacity(propagation.reference.len());
+        Self {
+            propagation,
+            sources,
+        }
+    }
+}
+
+const CHUNK_OF_SOURCES_TO_PROPAGATE: usize = 10000;
+impl Stream for PropagateSources {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let a = self.sources.len();
+        if a == self.propagation.reference.len() {
+            Poll::Ready(None)
+        } else {
+            let b = (a + CHUNK_OF_SOURCES_TO_PROPAGATE).min(self.propagation.reference.len());
+
+            let this = &mut *self;
+            this.sources.extend(this.propagation.propagate(a..b));
+
+            Poll::Pending
+        }
+    }
+}
+
+use crate::renderable::catalog::Merge;
+// Task that merges the sources appended to a catalog
+pub struct MergeCatalogSources {
+    pub merge: Merge,
+}
+
+impl MergeCatalogSources {
+    pub fn new(merge: Merge) -> Self {
+        Self { merge }
+    }
+}
+
+impl Stream for MergeCatalogSources {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.merge.step() {
+            Poll::Pending
+        } else {
+            Poll::Ready(None)
+        }
+    }
+}
+
 use cgmath::Vector3;
 /// Task that send a tile to the GPU
 pub struct ImageTile2GpuTask<I>
@@ -155,12 +155,25 @@ pub struct Moc {
     // The total url of the query
     pub url: Url,
     pub params: al_api::moc::MOC,
+    // Whether it is the coverage of a HiPS or a MOC layer
+    pub hips: bool,
 }
 impl Moc {
+    /// Query the coverage of a HiPS
     pub fn new(url: String, params: al_api::moc::MOC) -> Self {
         Moc {
             url,
             params,
+            hips: true,
+        }
+    }
+
+    /// Query a MOC to display as a new layer
+    pub fn layer(url: String, params: al_api::moc::MOC) -> Self {
+        Moc {
+            url,
+            params,
+            hips: false,
         }
     }
 }
@@ -176,4 +189,49 @@ impl Query for Moc {
     fn id(&self) -> QueryId {
         ("MOC", self.url().to_string())
     }
-}
\ No newline at end of file
+}
+/* ---------------------------------- */
+pub struct CatalogTile {
+    pub cell: HEALPixCell,
+    // The root url of the HiPS catalog
+    pub hips_url: Url,
+    // The total url of the query
+    pub url: Url,
+    // The columns described in the Metadata.xml of the HiPS catalog
+    pub fields: Rc<[Field]>,
+}
+
+use crate::table::Field;
+use std::rc::Rc;
+impl CatalogTile {
+    pub fn new(cell: &HEALPixCell, hips_url: &str, fields: Rc<[Field]>) -> Self {
+        let HEALPixCell(depth, idx) = *cell;
+
+        let dir_idx = (idx / 10000) * 10000;
+
+        let url = format!(
+            "{}/Norder{}/Dir{}/Npix{}.tsv",
+            hips_url, depth, dir_idx, idx
+        );
+
+        CatalogTile {
+            cell: *cell,
+            hips_url: hips_url.to_string(),
+            url,
+            fields,
+        }
+    }
+}
+
+use super::request::catalog::CatalogTileRequest;
+impl Query for CatalogTile {
+    type Request = CatalogTileRequest;
+
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    fn id(&self) -> QueryId {
+        ("CatalogTile", self.url().to_string())
+    }
+}
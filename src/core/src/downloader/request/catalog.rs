@@ -0,0 +1,125 @@
+use crate::downloader::query;
+
+use super::{Request, RequestType};
+use crate::downloader::QueryId;
+use crate::healpix::cell::HEALPixCell;
+
+// The sources of a tile and their attributes
+pub type TileTable = (Vec<Source>, Attributes);
+
+pub struct CatalogTileRequest {
+    pub id: QueryId,
+    pub url: Url,
+    pub hips_url: Url,
+    pub cell: HEALPixCell,
+    request: Request<TileTable>,
+}
+
+impl From<CatalogTileRequest> for RequestType {
+    fn from(request: CatalogTileRequest) -> Self {
+        RequestType::CatalogTile(request)
+    }
+}
+use crate::renderable::catalog::Source;
+use crate::renderable::Url;
+use crate::table::{delimited::DelimitedParser, Attributes, TableReader};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{RequestInit, RequestMode, Response};
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+
+use crate::downloader::query::Query;
+use crate::Abort;
+impl From<query::CatalogTile> for CatalogTileRequest {
+    // Create a tile request associated to a HiPS catalog
+    fn from(query: query::CatalogTile) -> Self {
+        let id = query.id();
+        let query::CatalogTile {
+            cell,
+            hips_url,
+            url,
+            fields,
+        } = query;
+
+        let url_clone = url.clone();
+
+        let window = web_sys::window().unwrap_abort();
+        let request = Request::new(async move {
+            let mut opts = RequestInit::new();
+            opts.method("GET");
+            opts.mode(RequestMode::Cors);
+
+            let request = web_sys::Request::new_with_str_and_init(&url_clone, &opts).unwrap_abort();
+            let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
+            // `resp_value` is a `Response` object.
+            debug_assert!(resp_value.is_instance_of::<Response>());
+            let resp: Response = resp_value.dyn_into()?;
+            if !resp.ok() {
+                // The tile does not exist
+                return Err(JsValue::from_str(&format!("{} not found", url_clone)));
+            }
+            let array_buffer = JsFuture::from(resp.array_buffer()?).await?;
+            let bytes = js_sys::Uint8Array::new(&array_buffer).to_vec();
+
+            // The tiles are tab separated tables
+            let mut parser = DelimitedParser::with_metadata(&fields);
+            parser.feed(&bytes)?;
+            let (sources, attributes, _) = parser.finish()?;
+
+            Ok((sources, attributes))
+        });
+
+        Self {
+            id,
+            url,
+            hips_url,
+            cell,
+            request,
+        }
+    }
+}
+
+use std::sync::{Arc, Mutex};
+pub struct CatalogTile {
+    pub cell: HEALPixCell,
+    // None if the tile could not be retrieved
+    pub table: Arc<Mutex<Option<TileTable>>>,
+    pub hips_url: Url,
+    pub url: Url,
+}
+
+impl CatalogTile {
+    pub fn get_url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn get_hips_url(&self) -> &Url {
+        &self.hips_url
+    }
+}
+
+impl<'a> From<&'a CatalogTileRequest> for Option<CatalogTile> {
+    fn from(request: &'a CatalogTileRequest) -> Self {
+        let CatalogTileRequest {
+            request,
+            url,
+            hips_url,
+            cell,
+            ..
+        } = request;
+        if request.is_resolved() {
+            let Request::<TileTable> {
+                data, ..
+            } = request;
+            Some(CatalogTile {
+                cell: *cell,
+                // This is a clone on a Arc, it is supposed to be fast
+                table: data.clone(),
+                hips_url: hips_url.clone(),
+                url: url.clone(),
+            })
+        } else {
+            None
+        }
+    }
+}
@@ -1,15 +1,13 @@
 use crate::downloader::query;
 
 use super::{Request, RequestType};
-use moclib::qty::Hpx;
-use moclib::deser::fits::MocType;
-use crate::healpix::coverage::Smoc;
 use crate::downloader::QueryId;
 
 pub struct MOCRequest {
     pub id: QueryId,
     pub url: Url,
     pub params: al_api::moc::MOC,
+    hips: bool,
     request: Request<HEALPixCoverage>,
 }
 
@@ -22,27 +20,8 @@ use crate::renderable::Url;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{RequestInit, RequestMode, Response};
 use wasm_bindgen::JsCast;
-use moclib::deser::fits;
 
-use moclib::moc::range::op::convert::convert_to_u64;
-
-/// Convenient type for Space-MOCs
-pub fn from_fits_hpx<T: Idx>(
-    moc: MocType<T, Hpx<T>, Cursor<&[u8]>>
-) -> Smoc {
-    match moc {
-        MocType::Ranges(moc) => convert_to_u64::<T, Hpx<T>, _, Hpx<u64>>(moc).into_range_moc(),
-        MocType::Cells(moc) => convert_to_u64::<T, Hpx<T>, _, Hpx<u64>>(
-            moc.into_cell_moc_iter().ranges()
-        ).into_range_moc(),
-    }
-}
 use crate::downloader::query::Query;
-use std::io::Cursor;
-use moclib::idx::Idx;
-use moclib::moc::{RangeMOCIterator, CellMOCIntoIterator, CellMOCIterator};
-use moclib::deser::fits::MocIdxType;
-use moclib::deser::fits::MocQtyType;
 use wasm_bindgen::JsValue;
 use crate::healpix::coverage::HEALPixCoverage;
 use crate::Abort;
@@ -53,6 +32,7 @@ impl From<query::Moc> for MOCRequest {
         let query::Moc {
             url,
             params,
+            hips,
         } = query;
 
         let url_clone = url.clone();
@@ -76,15 +56,8 @@ impl From<query::Moc> for MOCRequest {
             unsafe { bytes.set_len(num_bytes); }
             bytes_buf.copy_to(&mut bytes[..]);
     
-            // Coosys is permissive because we load a moc
-            let smoc = match fits::from_fits_ivoa_custom(Cursor::new(&bytes[..]), true).map_err(|e| JsValue::from_str(&e.to_string()))? {
-                MocIdxType::U16(MocQtyType::<u16, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-                MocIdxType::U32(MocQtyType::<u32, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-                MocIdxType::U64(MocQtyType::<u64, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-                _ => Err(JsValue::from_str("MOC not supported. Must be a HPX MOC"))
-            }?;
-
-            Ok(HEALPixCoverage(smoc))
+            // The serialization of the MOC is guessed from its content
+            HEALPixCoverage::from_bytes(&bytes).map_err(|e| JsValue::from_str(&e))
         });
 
         Self {
@@ -92,6 +65,7 @@ impl From<query::Moc> for MOCRequest {
             url,
             request,
             params,
+            hips,
         }
     }
 }
@@ -101,6 +75,8 @@ pub struct Moc {
     pub moc: Arc<Mutex<Option<HEALPixCoverage>>>,
     pub params: al_api::moc::MOC,
     pub url: Url,
+    /// Whether it is the coverage of a HiPS or a MOC layer
+    pub hips: bool,
 }
 
 impl Moc {
@@ -115,6 +91,7 @@ impl<'a> From<&'a MOCRequest> for Option<Moc> {
             request,
             url,
             params,
+            hips,
             ..
         } = request;
         if request.is_resolved() {
@@ -125,7 +102,8 @@ impl<'a> From<&'a MOCRequest> for Option<Moc> {
                 // This is a clone on a Arc, it is supposed to be fast
                 moc: data.clone(),
                 url: url.clone(),
-                params: params.clone()
+                params: params.clone(),
+                hips: *hips,
             })
         } else {
             None
@@ -4,6 +4,7 @@ pub mod allsky;
 pub mod tile;
 pub mod blank;
 pub mod moc;
+pub mod catalog;
 
 /* ------------------------------------- */
 
@@ -81,11 +82,13 @@ use allsky::AllskyRequest;
 use tile::TileRequest;
 use blank::PixelMetadataRequest;
 use moc::MOCRequest;
+use catalog::CatalogTileRequest;
 pub enum RequestType {
     Tile(TileRequest),
     Allsky(AllskyRequest),
     PixelMetadata(PixelMetadataRequest),
-    Moc(MOCRequest)
+    Moc(MOCRequest),
+    CatalogTile(CatalogTileRequest),
     //..
 }
 use crate::downloader::QueryId;
@@ -97,6 +100,7 @@ impl RequestType {
             RequestType::Allsky(request) => &request.id,
             RequestType::PixelMetadata(request) => &request.id,
             RequestType::Moc(request) => &request.id,
+            RequestType::CatalogTile(request) => &request.id,
         }
     }
 }
@@ -116,6 +120,9 @@ impl<'a> From<&'a RequestType> for Option<Resource> {
             RequestType::Moc(request) => {
                 Option::<Moc>::from(request).map(Resource::Moc)
             }
+            RequestType::CatalogTile(request) => {
+                Option::<CatalogTile>::from(request).map(Resource::CatalogTile)
+            }
         }
     }
 }
@@ -124,11 +131,13 @@ use allsky::Allsky;
 use tile::Tile;
 use blank::PixelMetadata;
 use moc::Moc;
+use catalog::CatalogTile;
 pub enum Resource {
     Tile(Tile),
     Allsky(Allsky),
     PixelMetadata(PixelMetadata),
-    Moc(Moc)
+    Moc(Moc),
+    CatalogTile(CatalogTile),
 }
 
 impl Resource {
@@ -138,6 +147,7 @@ impl Resource {
             Resource::Allsky(allsky) => allsky.get_url(),
             Resource::PixelMetadata(PixelMetadata { url, ..}) => url, 
             Resource::Moc(moc) => moc.get_url(),
+            Resource::CatalogTile(tile) => tile.get_url(),
         }
     }
 }
@@ -1,12 +1,34 @@
 use crate::math;
 use moclib::{
-    moc::range::RangeMOC,
+    deser::fits::{self, MocIdxType, MocQtyType, MocType},
+    idx::Idx,
+    moc::{
+        range::{op::convert::convert_to_u64, RangeMOC},
+        CellMOCIntoIterator, CellMOCIterator, CellOrCellRangeMOCIntoIterator, CellOrCellRangeMOCIterator,
+        RangeMOCIntoIterator, RangeMOCIterator,
+    },
     qty::Hpx
 };
-use cgmath::{Vector3, Vector4};
+use std::io::Cursor;
+use std::ops::Range;
+use cgmath::{InnerSpace, Vector3, Vector4, Zero};
+use crate::math::angle::Angle;
+
+// The depth of the coverage from which its bounding cone is computed
+const BOUNDING_CONE_DEPTH: u8 = 9;
 
 pub type Smoc = RangeMOC<u64, Hpx<u64>>;
 
+// Convert a space MOC read from a FITS file to a MOC of u64 ranges
+fn from_fits_hpx<T: Idx>(moc: MocType<T, Hpx<T>, Cursor<&[u8]>>) -> Smoc {
+    match moc {
+        MocType::Ranges(moc) => convert_to_u64::<T, Hpx<T>, _, Hpx<u64>>(moc).into_range_moc(),
+        MocType::Cells(moc) => {
+            convert_to_u64::<T, Hpx<T>, _, Hpx<u64>>(moc.into_cell_moc_iter().ranges()).into_range_moc()
+        }
+    }
+}
+
 use crate::healpix::cell::HEALPixCell;
 #[derive(Clone, Debug)]
 pub struct HEALPixCoverage(pub Smoc);
@@ -35,11 +57,62 @@ impl HEALPixCoverage {
         HEALPixCoverage(moc)
     }
 
+    /// The coverage of a spherical polygon
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The (lon, lat) vertices of the polygon, in radians.
+    ///   The polygon is the smallest of the two areas they delimit
+    /// * `depth` - The depth of the smallest HEALPix cells
+    pub fn from_polygon(vertices: &[(f64, f64)], depth: u8) -> Self {
+        let moc = RangeMOC::from_polygon(vertices, false, depth);
+        HEALPixCoverage(moc)
+    }
+
+    /// The coverage of a cone, the angles being given in radians
+    pub fn from_cone(lon: f64, lat: f64, radius: f64, depth: u8) -> Self {
+        let moc = RangeMOC::from_cone(lon, lat, radius, depth, 2);
+        HEALPixCoverage(moc)
+    }
+
+    /// The coverage of an elliptical cone, the angles being given in radians
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The semi-major axis, lower than pi/2
+    /// * `b` - The semi-minor axis, lower than `a`
+    /// * `pa` - The position angle of the major axis, east of north, in [0, pi[
+    pub fn from_ellipse(lon: f64, lat: f64, a: f64, b: f64, pa: f64, depth: u8) -> Self {
+        let moc = RangeMOC::from_elliptical_cone(lon, lat, a, b, pa, depth, 2);
+        HEALPixCoverage(moc)
+    }
+
+    /// The coverage of a box delimited by great circles, the angles being given in radians
+    ///
+    /// The axes and the position angle are constrained as for `from_ellipse`
+    pub fn from_box(lon: f64, lat: f64, a: f64, b: f64, pa: f64, depth: u8) -> Self {
+        let moc = RangeMOC::from_box(lon, lat, a, b, pa, depth);
+        HEALPixCoverage(moc)
+    }
+
+    /// The coverage of a zone delimited by meridians and parallels, the angles being given in radians
+    ///
+    /// The zone crosses the primary meridian if `lon_min > lon_max`
+    pub fn from_zone(lon_min: f64, lat_min: f64, lon_max: f64, lat_max: f64, depth: u8) -> Self {
+        let moc = RangeMOC::from_zone(lon_min, lat_min, lon_max, lat_max, depth);
+        HEALPixCoverage(moc)
+    }
+
     pub fn from_hpx_cells(depth: u8, hpx_idx: impl Iterator<Item = u64>, cap: Option<usize>) -> Self {
         let moc = RangeMOC::from_fixed_depth_cells(depth, hpx_idx, cap);
         HEALPixCoverage(moc)
     }
 
+    pub fn empty() -> Self {
+        let moc = RangeMOC::new_empty(0);
+        HEALPixCoverage(moc)
+    }
+
     pub fn allsky(depth_max: u8) -> Self {
         let moc = RangeMOC::new_full_domain(depth_max);
         HEALPixCoverage(moc)
@@ -73,6 +146,165 @@ impl HEALPixCoverage {
     pub fn depth(&self) -> u8 {
         self.0.depth_max()
     }
+
+    /// Parse a MOC 2.0 FITS file
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content of the file
+    /// * `permissive` - Accept FITS files whose coordinate system keyword is missing
+    pub fn from_fits(bytes: &[u8], permissive: bool) -> Result<Self, String> {
+        let moc = match fits::from_fits_ivoa_custom(Cursor::new(bytes), permissive).map_err(|e| e.to_string())? {
+            MocIdxType::U16(MocQtyType::<u16, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
+            MocIdxType::U32(MocQtyType::<u32, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
+            MocIdxType::U64(MocQtyType::<u64, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
+            _ => Err(String::from("MOC not supported. Must be a HPX MOC")),
+        }?;
+
+        Ok(HEALPixCoverage(moc))
+    }
+
+    /// Parse a MOC serialized in the JSON format of Aladin, i.e. `{"order": [cells]}`
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let moc = moclib::deser::json::from_json_aladin::<u64, Hpx<u64>>(json)
+            .map_err(|e| e.to_string())?
+            .into_cell_moc_iter()
+            .ranges()
+            .into_range_moc();
+
+        Ok(HEALPixCoverage(moc))
+    }
+
+    /// Parse a MOC serialized in the ASCII format of the MOC 2.0 standard, e.g. `3/1-5 4/42`
+    pub fn from_ascii(ascii: &str) -> Result<Self, String> {
+        let moc = moclib::deser::ascii::from_ascii_ivoa::<u64, Hpx<u64>>(ascii)
+            .map_err(|e| e.to_string())?
+            .into_cellcellrange_moc_iter()
+            .ranges()
+            .into_range_moc();
+
+        Ok(HEALPixCoverage(moc))
+    }
+
+    /// Parse a MOC whose serialization, FITS, JSON or ASCII, is guessed from its content
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.starts_with(b"SIMPLE") {
+            // Coosys is permissive because we load a moc
+            return Self::from_fits(bytes, true);
+        }
+
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| String::from("The MOC is neither a FITS, a JSON nor an ASCII MOC"))?
+            .trim();
+        if text.starts_with('{') {
+            Self::from_json(text)
+        } else {
+            Self::from_ascii(text)
+        }
+    }
+
+    /// The area covered in square degrees
+    pub fn area(&self) -> f64 {
+        self.coverage_percentage() * 4.0 * std::f64::consts::PI * (180.0 / std::f64::consts::PI).powi(2)
+    }
+
+    /// The number of cells of each depth, from 0 to the depth of the coverage, it is made of
+    pub fn num_cells_per_depth(&self) -> Vec<u64> {
+        let mut num_cells = vec![0; self.depth() as usize + 1];
+        for cell in (&self.0).into_range_moc_iter().cells() {
+            num_cells[cell.depth as usize] += 1;
+        }
+
+        num_cells
+    }
+
+    /// The cells the coverage is made of, given by their UNIQ index, i.e. `4 * 4^depth + idx`
+    pub fn uniq_cells(&self) -> Vec<u64> {
+        (&self.0)
+            .into_range_moc_iter()
+            .cells()
+            .map(|cell| cell.uniq_hpx())
+            .collect()
+    }
+
+    /// The ranges of cells of depth 29 the coverage is made of, the end of each range being excluded
+    pub fn ranges(&self) -> impl Iterator<Item = &Range<u64>> {
+        self.0.moc_ranges().iter()
+    }
+
+    /// A cone containing the coverage
+    ///
+    /// The center of the cone is the barycenter of the coverage and its radius the angular distance
+    /// to its farthest point. (lon, lat, radius) are given in radians, `None` being returned for an empty coverage.
+    pub fn bounding_cone(&self) -> Option<(f64, f64, f64)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        // A coarser coverage contains the coverage and has fewer cells
+        let moc = HEALPixCoverage(self.degraded(self.depth().min(BOUNDING_CONE_DEPTH)));
+
+        let center = (&moc.0)
+            .into_range_moc_iter()
+            .cells()
+            .map(|cell| {
+                let (lon, lat) = cdshealpix::nested::center(cell.depth, cell.idx);
+                // The cells are weighted by their area
+                let weight = 1.0 / (1_u64 << (2 * cell.depth)) as f64;
+                math::lonlat::radec_to_xyz(Angle(lon), Angle(lat)) * weight
+            })
+            .fold(Vector3::zero(), |sum, v| sum + v);
+        if center.magnitude2() < 1e-20 {
+            // The coverage is evenly spread on the sky
+            return Some((0.0, 0.0, std::f64::consts::PI));
+        }
+        let center = center.normalize();
+        let (lon, lat) = math::lonlat::xyz_to_radec(&center);
+
+        // The farthest point is on the perimeter unless the coverage contains the antipode of the center
+        let radius = if moc.contains_coo(&(-center).extend(1.0)) {
+            std::f64::consts::PI
+        } else {
+            moc.perimeter()
+                .into_iter()
+                .flatten()
+                .map(|(lon, lat)| {
+                    let v = math::lonlat::radec_to_xyz(Angle(lon), Angle(lat));
+                    center.cross(v).magnitude().atan2(center.dot(v))
+                })
+                .fold(0.0, f64::max)
+        };
+
+        Some((lon.0, lat.0, radius))
+    }
+
+    /// Serialize the coverage into a MOC 2.0 FITS file
+    pub fn to_fits(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = vec![];
+        moclib::deser::fits::ranges_to_fits_ivoa((&self.0).into_range_moc_iter(), None, None, &mut bytes)
+            .map_err(|e| e.to_string())?;
+
+        Ok(bytes)
+    }
+
+    /// Serialize the coverage into the ASCII format of the MOC 2.0 standard, e.g. `3/1-5 4/42`
+    pub fn to_ascii(&self) -> Result<String, String> {
+        self.0.to_ascii().map_err(|e| e.to_string())
+    }
+
+    /// Serialize the coverage into the JSON format of Aladin, i.e. `{"order": [cells]}`
+    pub fn to_json(&self) -> String {
+        let mut bytes = vec![];
+        // Writing into memory cannot fail
+        let _ = moclib::deser::json::to_json_aladin(
+            (&self.0).into_range_moc_iter().cells(),
+            &None,
+            "",
+            &mut bytes,
+        );
+
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
 }
 
 use core::ops::Deref;
@@ -83,3 +315,43 @@ impl Deref for HEALPixCoverage {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::HEALPixCoverage;
+
+    #[test]
+    fn serializations_round_trip() {
+        let moc = HEALPixCoverage::from_cone(1.0, 0.5, 0.1, 9);
+
+        let fits = moc.to_fits().unwrap();
+        assert_eq!(HEALPixCoverage::from_bytes(&fits).unwrap().0, moc.0);
+
+        let ascii = moc.to_ascii().unwrap();
+        assert_eq!(HEALPixCoverage::from_bytes(ascii.as_bytes()).unwrap().0, moc.0);
+
+        let json = moc.to_json();
+        assert_eq!(HEALPixCoverage::from_bytes(json.as_bytes()).unwrap().0, moc.0);
+    }
+
+    #[test]
+    fn ascii_ranges() {
+        let moc = HEALPixCoverage::from_ascii("1/0-3 2/16-19\n 3/").unwrap();
+        assert_eq!(moc.depth_max(), 3);
+        assert!((moc.coverage_percentage() - 5.0 / 48.0).abs() < 1e-12);
+        assert!(HEALPixCoverage::from_bytes(b"1/0-a").is_err());
+    }
+
+    #[test]
+    fn statistics() {
+        let moc = HEALPixCoverage::from_ascii("1/0-3 2/16-19 3/").unwrap();
+        assert!((moc.area() - 41252.96 * 5.0 / 48.0).abs() < 1e-2);
+        assert_eq!(moc.num_cells_per_depth(), vec![1, 1, 0, 0]);
+        assert_eq!(moc.uniq_cells(), vec![4, 20]);
+
+        let (lon, lat, radius) = HEALPixCoverage::from_cone(1.0, 0.5, 0.1, 9).bounding_cone().unwrap();
+        assert!((lon - 1.0).abs() < 1e-2 && (lat - 0.5).abs() < 1e-2);
+        assert!(radius > 0.1 && radius < 0.11);
+        assert!(HEALPixCoverage::allsky(3).bounding_cone().unwrap().2 >= std::f64::consts::PI);
+    }
+}
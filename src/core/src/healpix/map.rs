@@ -0,0 +1,150 @@
+//! Partial HEALPix maps
+//!
+//! Values (e.g. source counts) given for the non empty cells of a fixed depth,
+//! in the NESTED scheme.
+use super::coverage::HEALPixCoverage;
+
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HEALPixMap {
+    /// The order of the cells
+    pub order: u8,
+    /// The cell indices, in increasing order
+    pub cells: Vec<u64>,
+    /// The value of each cell
+    pub values: Vec<f64>,
+}
+
+impl HEALPixMap {
+    /// Sum weighted positions in the cells containing them
+    ///
+    /// # Arguments
+    ///
+    /// * `order` - The order of the map
+    /// * `positions` - (lon, lat, weight) with the coordinates in radians
+    pub fn from_positions(order: u8, positions: impl Iterator<Item = (f64, f64, f64)>) -> Self {
+        let mut sums = BTreeMap::new();
+        for (lon, lat, weight) in positions {
+            let idx = cdshealpix::nested::hash(order, lon, lat);
+            *sums.entry(idx).or_insert(0.0) += weight;
+        }
+
+        let (cells, values) = sums.into_iter().unzip();
+        Self { order, cells, values }
+    }
+
+    /// The coverage of the non empty cells
+    pub fn coverage(&self) -> HEALPixCoverage {
+        HEALPixCoverage::from_hpx_cells(self.order, self.cells.iter().copied(), Some(self.cells.len()))
+    }
+
+    /// Write the map in the FITS format of the HEALPix partial maps,
+    /// i.e. a binary table of (PIXEL, SIGNAL) rows
+    pub fn to_fits(&self) -> Vec<u8> {
+        let nside = 1_u64 << self.order;
+
+        let mut bytes = vec![];
+        write_header(
+            &mut bytes,
+            &[
+                ("SIMPLE", Card::Logical(true)),
+                ("BITPIX", Card::Integer(8)),
+                ("NAXIS", Card::Integer(0)),
+                ("EXTEND", Card::Logical(true)),
+            ],
+        );
+        write_header(
+            &mut bytes,
+            &[
+                ("XTENSION", Card::String("BINTABLE")),
+                ("BITPIX", Card::Integer(8)),
+                ("NAXIS", Card::Integer(2)),
+                // 8 bytes for the pixel index and 8 bytes for the value
+                ("NAXIS1", Card::Integer(16)),
+                ("NAXIS2", Card::Integer(self.cells.len() as i64)),
+                ("PCOUNT", Card::Integer(0)),
+                ("GCOUNT", Card::Integer(1)),
+                ("TFIELDS", Card::Integer(2)),
+                ("TTYPE1", Card::String("PIXEL")),
+                ("TFORM1", Card::String("K")),
+                ("TTYPE2", Card::String("SIGNAL")),
+                ("TFORM2", Card::String("D")),
+                ("PIXTYPE", Card::String("HEALPIX")),
+                ("ORDERING", Card::String("NESTED")),
+                ("COORDSYS", Card::String("C")),
+                ("NSIDE", Card::Integer(nside as i64)),
+                ("ORDER", Card::Integer(self.order as i64)),
+                ("INDXSCHM", Card::String("EXPLICIT")),
+                ("OBJECT", Card::String("PARTIAL")),
+                ("FIRSTPIX", Card::Integer(0)),
+                ("LASTPIX", Card::Integer((12 * nside * nside - 1) as i64)),
+            ],
+        );
+
+        for (cell, value) in self.cells.iter().zip(self.values.iter()) {
+            bytes.extend_from_slice(&(*cell as i64).to_be_bytes());
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        pad(&mut bytes, 0);
+
+        bytes
+    }
+}
+
+const FITS_BLOCK_SIZE: usize = 2880;
+
+enum Card<'a> {
+    Logical(bool),
+    Integer(i64),
+    String(&'a str),
+}
+
+fn write_header(bytes: &mut Vec<u8>, cards: &[(&str, Card)]) {
+    for (keyword, value) in cards {
+        let value = match value {
+            Card::Logical(v) => format!("{:>20}", if *v { "T" } else { "F" }),
+            Card::Integer(v) => format!("{:>20}", v),
+            // The string values are at least 8 characters long
+            Card::String(v) => format!("'{:<8}'", v),
+        };
+        bytes.extend_from_slice(format!("{:<8}= {:<70}", keyword, value).as_bytes());
+    }
+    bytes.extend_from_slice(format!("{:<80}", "END").as_bytes());
+    pad(bytes, b' ');
+}
+
+// Fill the last block
+fn pad(bytes: &mut Vec<u8>, byte: u8) {
+    let len = bytes.len().div_ceil(FITS_BLOCK_SIZE) * FITS_BLOCK_SIZE;
+    bytes.resize(len, byte);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HEALPixMap;
+    use std::convert::TryInto;
+
+    #[test]
+    fn partial_map_to_fits() {
+        let positions = [(0.1, 0.1, 1.0), (0.1, 0.1001, 2.0), (3.0, -1.0, 1.0)];
+        let map = HEALPixMap::from_positions(3, positions.iter().copied());
+
+        assert_eq!(map.cells.len(), 2);
+        assert_eq!(map.values.iter().sum::<f64>(), 4.0);
+        assert!(map.cells.windows(2).all(|w| w[0] < w[1]));
+
+        let fits = map.to_fits();
+        assert_eq!(fits.len(), 3 * 2880);
+
+        let header = String::from_utf8_lossy(&fits[2880..(2 * 2880)]);
+        assert!(header.starts_with("XTENSION= 'BINTABLE'"));
+        assert!(header.contains(&format!("NSIDE   = {:>20}", 8)));
+
+        // The first row of the table
+        let pixel = i64::from_be_bytes(fits[5760..5768].try_into().unwrap());
+        assert_eq!(pixel as u64, map.cells[0]);
+    }
+}
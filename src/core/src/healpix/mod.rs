@@ -1,3 +1,6 @@
 pub mod cell;
 pub mod coverage;
+pub mod perimeter;
+pub mod time_coverage;
+pub mod map;
 pub mod utils;
\ No newline at end of file
@@ -0,0 +1,229 @@
+use super::{cell::HEALPixCell, coverage::HEALPixCoverage};
+use crate::math::{angle::Angle, lonlat::radec_to_xyz};
+use cdshealpix::compass_point::{Cardinal, MainWind, Ordinal};
+use cgmath::{InnerSpace, Vector3};
+use moclib::{elem::cell::Cell, moc::{RangeMOCIntoIterator, RangeMOCIterator}};
+use std::collections::HashMap;
+
+// The depth from which the cells are small enough to have their sides drawn as they are
+const MIN_PERIMETER_DEPTH: u8 = 3;
+// The maximum number of times the side of a cell is halved to follow its curvature
+const MAX_SIDE_SUBDIVISION: u8 = 4;
+
+#[derive(Debug, PartialEq)]
+enum Overlap {
+    Full,
+    Partial,
+    Empty,
+}
+
+// How much of a cell is covered by a MOC
+fn cell_overlap(moc: &HEALPixCoverage, cell: &HEALPixCell) -> Overlap {
+    let HEALPixCell(depth, idx) = *cell;
+    let shift = 2 * (29 - depth);
+    let (start, end) = (idx << shift, (idx + 1) << shift);
+
+    let ranges = &moc.0.moc_ranges().0 .0;
+    // The first range ending after the beginning of the cell
+    let i = ranges.partition_point(|range| range.end <= start);
+    match ranges.get(i) {
+        Some(range) if range.start <= start && range.end >= end => Overlap::Full,
+        Some(range) if range.start < end => Overlap::Partial,
+        _ => Overlap::Empty,
+    }
+}
+
+// The 2 children of a cell along one of its sides.
+// The children of a nested cell are in its south, east, west and north quarters
+fn children_along_side(cell: &HEALPixCell, side: Ordinal) -> [HEALPixCell; 2] {
+    let HEALPixCell(depth, idx) = *cell;
+    let quarters = match side {
+        Ordinal::NE => [1, 3],
+        Ordinal::NW => [2, 3],
+        Ordinal::SE => [0, 1],
+        Ordinal::SW => [0, 2],
+    };
+
+    quarters.map(|quarter| HEALPixCell(depth + 1, 4 * idx + quarter))
+}
+
+// Add the parts of the side of a cell of the MOC lying on its perimeter
+fn side_on_perimeter(moc: &HEALPixCoverage, cell: HEALPixCell, side: Ordinal, sides: &mut Vec<(HEALPixCell, Ordinal)>) {
+    let HEALPixCell(depth, idx) = cell;
+    let direction = match side {
+        Ordinal::NE => MainWind::NE,
+        Ordinal::NW => MainWind::NW,
+        Ordinal::SE => MainWind::SE,
+        Ordinal::SW => MainWind::SW,
+    };
+    let overlap = cdshealpix::nested::neighbours(depth, idx, false)
+        .get(direction)
+        .map_or(Overlap::Empty, |&neighbour| cell_overlap(moc, &HEALPixCell(depth, neighbour)));
+
+    match overlap {
+        Overlap::Full => (),
+        Overlap::Empty if depth >= MIN_PERIMETER_DEPTH => sides.push((cell, side)),
+        // The neighbour is partially covered or the cell is too large to be drawn as it is
+        _ => {
+            for child in children_along_side(&cell, side) {
+                side_on_perimeter(moc, child, side, sides);
+            }
+        }
+    }
+}
+
+// The sides of the cells of a MOC lying on its perimeter
+fn sides(moc: &HEALPixCoverage) -> Vec<(HEALPixCell, Ordinal)> {
+    let mut sides = vec![];
+    for Cell { depth, idx, .. } in (&(moc.0)).into_range_moc_iter().cells() {
+        for side in [Ordinal::NE, Ordinal::NW, Ordinal::SE, Ordinal::SW] {
+            side_on_perimeter(moc, HEALPixCell(depth, idx), side, &mut sides);
+        }
+    }
+
+    sides
+}
+
+// The vertices of a cell are given in the order S, E, N, W so that
+// going along its sides from one vertex to the next one keeps the cell on the left
+fn side_vertices(side: Ordinal) -> (usize, usize, Cardinal, Cardinal) {
+    match side {
+        Ordinal::SE => (0, 1, Cardinal::S, Cardinal::E),
+        Ordinal::NE => (1, 2, Cardinal::E, Cardinal::N),
+        Ordinal::NW => (2, 3, Cardinal::N, Cardinal::W),
+        Ordinal::SW => (3, 0, Cardinal::W, Cardinal::S),
+    }
+}
+
+// Vertices closer than that are the same vertex computed from different cells.
+// The vertices of the cells of depth 29 are ~1e-9 rad apart
+const VERTEX_TOLERANCE: f64 = 1e-11;
+const VERTEX_QUANTUM: f64 = 1e-10;
+
+type VertexKey = (i64, i64, i64);
+
+fn vertex_key(v: &Vector3<f64>) -> VertexKey {
+    let quantize = |x: f64| (x / VERTEX_QUANTUM).floor() as i64;
+    (quantize(v.x), quantize(v.y), quantize(v.z))
+}
+
+impl HEALPixCoverage {
+    /// The perimeter of the coverage, i.e. its outer boundaries and the ones of its holes
+    ///
+    /// Each boundary is a closed polyline of (lon, lat) vertices given in radians, the first vertex
+    /// not being repeated at its end. Going along a polyline keeps the coverage on its left, east
+    /// being on the right, so that the holes are described clockwise.
+    pub fn perimeter(&self) -> Vec<Vec<(f64, f64)>> {
+        let depth_max = self.depth();
+        let sides = sides(self);
+
+        let to_xyz = |(lon, lat): (f64, f64)| radec_to_xyz(Angle(lon), Angle(lat));
+        let ends = sides
+            .iter()
+            .map(|(cell, side)| {
+                let vertices = cell.vertices();
+                let (from, to, _, _) = side_vertices(*side);
+                (to_xyz(vertices[from]), to_xyz(vertices[to]))
+            })
+            .collect::<Vec<_>>();
+
+        // The sides starting from each vertex
+        let mut starts: HashMap<VertexKey, Vec<usize>> = HashMap::new();
+        for (i, (start, _)) in ends.iter().enumerate() {
+            starts.entry(vertex_key(start)).or_default().push(i);
+        }
+
+        let mut used = vec![false; sides.len()];
+        let next_side = |end: &Vector3<f64>, used: &[bool]| -> Option<usize> {
+            let (x, y, z) = vertex_key(end);
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let next = starts.get(&(x + dx, y + dy, z + dz)).and_then(|candidates| {
+                            candidates.iter().copied().find(|&i| {
+                                !used[i] && (ends[i].0 - end).magnitude() < VERTEX_TOLERANCE
+                            })
+                        });
+                        if next.is_some() {
+                            return next;
+                        }
+                    }
+                }
+            }
+
+            None
+        };
+
+        let mut polylines = vec![];
+        for first in 0..sides.len() {
+            if used[first] {
+                continue;
+            }
+
+            let mut polyline = vec![];
+            let mut cur = Some(first);
+            while let Some(i) = cur {
+                used[i] = true;
+
+                let (HEALPixCell(depth, idx), side) = sides[i];
+                let (_, _, from, to) = side_vertices(side);
+                let n_segments = 1 << depth_max.saturating_sub(depth).min(MAX_SIDE_SUBDIVISION);
+                // The last vertex is the first one of the next side
+                polyline.extend(cdshealpix::nested::path_along_cell_side(depth, idx, &from, &to, false, n_segments).iter());
+
+                cur = next_side(&ends[i].1, &used);
+            }
+
+            polylines.push(polyline);
+        }
+
+        polylines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sides;
+    use crate::healpix::{cell::HEALPixCell, coverage::HEALPixCoverage};
+    use cdshealpix::compass_point::MainWind;
+
+    #[test]
+    fn perimeter_of_cells_of_different_depths() {
+        let a = 100;
+        let b = *cdshealpix::nested::neighbours(3, a, false).get(MainWind::NE).unwrap();
+        // The child of the neighbour touching the cell
+        let child = (4 * b..4 * b + 4)
+            .find(|&child| {
+                cdshealpix::nested::neighbours(4, child, false)
+                    .get(MainWind::SW)
+                    .map_or(false, |&n| n >> 2 == a)
+            })
+            .unwrap();
+        let moc = HEALPixCoverage::from_hpx_cells(4, (4 * a..4 * a + 4).chain(std::iter::once(child)), None);
+
+        let sides = sides(&moc);
+        // 3 sides and a half for the cell and 3 sides for the child
+        assert_eq!(sides.len(), 7);
+        let length: f64 = sides
+            .iter()
+            .map(|(HEALPixCell(depth, _), _)| 1.0 / (1 << (depth - 3)) as f64)
+            .sum();
+        assert_eq!(length, 5.0);
+
+        let perimeter = moc.perimeter();
+        assert_eq!(perimeter.len(), 1);
+        // The sides of depth 3 are divided in 2 and the ones of depth 4 are kept
+        assert_eq!(perimeter[0].len(), 3 * 2 + 1 + 3);
+    }
+
+    #[test]
+    fn perimeter_of_a_cell_with_a_hole() {
+        // The cell of depth 5 at i = j = 1 in the cell 100 of depth 3 is not covered
+        let hole = 16 * 100 + 3;
+        let moc = HEALPixCoverage::from_hpx_cells(5, (16 * 100..16 * 101).filter(|&idx| idx != hole), None);
+
+        let mut lengths = moc.perimeter().iter().map(|polyline| polyline.len()).collect::<Vec<_>>();
+        lengths.sort_unstable();
+        assert_eq!(lengths, vec![4, 16]);
+    }
+}
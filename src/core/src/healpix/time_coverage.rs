@@ -0,0 +1,156 @@
+use super::coverage::{HEALPixCoverage, Smoc};
+use moclib::{
+    deser::fits::{self, MocIdxType, MocQtyType, MocType, STMocType},
+    idx::Idx,
+    moc2d::HasTwoMaxDepth,
+    moc::{
+        range::{op::{convert::convert_to_u64, multi_op::kway_or}, RangeMOC},
+        CellMOCIntoIterator, CellMOCIterator, RangeMOCIterator,
+    },
+    qty::{MocQty, Time},
+};
+use std::io::Cursor;
+
+pub type Tmoc = RangeMOC<u64, Time<u64>>;
+
+// The time indices are microseconds since JD = 0
+const MICROSEC_PER_DAY: f64 = 86_400_000_000.0;
+
+fn from_fits_time<T: Idx>(moc: MocType<T, Time<T>, Cursor<&[u8]>>) -> Tmoc {
+    match moc {
+        MocType::Ranges(moc) => convert_to_u64::<T, Time<T>, _, Time<u64>>(moc).into_range_moc(),
+        MocType::Cells(moc) => convert_to_u64::<T, Time<T>, _, Time<u64>>(
+            moc.into_cell_moc_iter().ranges()
+        ).into_range_moc(),
+    }
+}
+
+/// A Time-MOC
+#[derive(Clone, Debug)]
+pub struct TimeCoverage(pub Tmoc);
+
+impl TimeCoverage {
+    /// The coverage of a time interval
+    ///
+    /// # Arguments
+    ///
+    /// * `jd_min` - The beginning of the interval in julian days
+    /// * `jd_max` - The end of the interval in julian days
+    pub fn from_jd_range(jd_min: f64, jd_max: f64) -> Self {
+        let start = (jd_min * MICROSEC_PER_DAY) as u64;
+        let end = (jd_max * MICROSEC_PER_DAY) as u64;
+
+        let moc = Tmoc::from_microsec_ranges_since_jd0(
+            Time::<u64>::MAX_DEPTH,
+            std::iter::once(start..end),
+            None,
+        );
+        TimeCoverage(moc)
+    }
+
+    /// Parse a T-MOC 2.0 FITS file
+    pub fn from_fits(bytes: &[u8]) -> Result<Self, String> {
+        let moc = match fits::from_fits_ivoa(Cursor::new(bytes)).map_err(|e| e.to_string())? {
+            MocIdxType::U16(MocQtyType::<u16, _>::Time(moc)) => Ok(from_fits_time(moc)),
+            MocIdxType::U32(MocQtyType::<u32, _>::Time(moc)) => Ok(from_fits_time(moc)),
+            MocIdxType::U64(MocQtyType::<u64, _>::Time(moc)) => Ok(from_fits_time(moc)),
+            _ => Err(String::from("MOC not supported. Must be a Time MOC")),
+        }?;
+
+        Ok(TimeCoverage(moc))
+    }
+
+    /// The first and the last instants covered, in julian days
+    pub fn jd_range(&self) -> Option<(f64, f64)> {
+        let start = self.0.first_index()?;
+        let end = self.0.last_index()?;
+
+        Some((start as f64 / MICROSEC_PER_DAY, end as f64 / MICROSEC_PER_DAY))
+    }
+
+    fn intersects(&self, moc: &Tmoc) -> bool {
+        !self.0.intersection(moc).is_empty()
+    }
+}
+
+/// A Space-Time MOC, i.e. a list of time intervals associated to the sky area observed during them
+pub struct SpaceTimeCoverage {
+    space_depth: u8,
+    elems: Vec<(Tmoc, Smoc)>,
+}
+
+impl SpaceTimeCoverage {
+    /// Parse a ST-MOC 2.0 FITS file
+    pub fn from_fits(bytes: &[u8]) -> Result<Self, String> {
+        let (space_depth, elems) = match fits::from_fits_ivoa(Cursor::new(bytes)).map_err(|e| e.to_string())? {
+            MocIdxType::U64(MocQtyType::TimeHpx(STMocType::V2(moc))) => {
+                (moc.depth_max_2(), moc.map(|elem| elem.mocs()).collect())
+            }
+            MocIdxType::U64(MocQtyType::TimeHpx(STMocType::PreV2(moc))) => {
+                (moc.depth_max_2(), moc.map(|elem| elem.mocs()).collect())
+            }
+            _ => return Err(String::from("MOC not supported. Must be a Space-Time MOC")),
+        };
+
+        Ok(Self { space_depth, elems })
+    }
+
+    /// The sky area observed during a time window
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The time window. The whole sky area observed is returned if `None`
+    pub fn time_fold(&self, window: Option<&TimeCoverage>) -> HEALPixCoverage {
+        let space_mocs = self
+            .elems
+            .iter()
+            .filter(|(time, _)| window.is_none_or(|window| window.intersects(time)))
+            .map(|(_, space)| space.clone())
+            .collect::<Vec<_>>();
+
+        if space_mocs.is_empty() {
+            HEALPixCoverage(Smoc::new(self.space_depth, Default::default()))
+        } else {
+            HEALPixCoverage(kway_or(Box::new(space_mocs.into_iter())))
+        }
+    }
+
+    /// The time intervals during which the sky has been observed
+    pub fn time_coverage(&self) -> TimeCoverage {
+        let time_mocs = self.elems.iter().map(|(time, _)| time.clone()).collect::<Vec<_>>();
+
+        if time_mocs.is_empty() {
+            TimeCoverage(Tmoc::new(Time::<u64>::MAX_DEPTH, Default::default()))
+        } else {
+            TimeCoverage(kway_or(Box::new(time_mocs.into_iter())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpaceTimeCoverage, TimeCoverage};
+    use crate::healpix::coverage::HEALPixCoverage;
+
+    #[test]
+    fn time_fold() {
+        let first = TimeCoverage::from_jd_range(2459000.0, 2459001.0);
+        let second = TimeCoverage::from_jd_range(2459010.0, 2459011.0);
+        let north = HEALPixCoverage::from_cone(0.0, 1.0, 0.1, 8);
+        let south = HEALPixCoverage::from_cone(0.0, -1.0, 0.1, 8);
+
+        let stmoc = SpaceTimeCoverage {
+            space_depth: 8,
+            elems: vec![(first.0, north.0.clone()), (second.0, south.0.clone())],
+        };
+
+        let window = TimeCoverage::from_jd_range(2459000.5, 2459002.0);
+        assert_eq!(stmoc.time_fold(Some(&window)).0, north.0);
+        let window = TimeCoverage::from_jd_range(2459005.0, 2459006.0);
+        assert!(stmoc.time_fold(Some(&window)).0.is_empty());
+        assert_eq!(stmoc.time_fold(None).0, north.0.union(&south.0));
+
+        let (jd_min, jd_max) = stmoc.time_coverage().jd_range().unwrap();
+        assert!((jd_min - 2459000.0).abs() < 1e-6 && (jd_max - 2459011.0).abs() < 1e-6);
+    }
+}
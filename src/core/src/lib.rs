@@ -79,6 +79,7 @@ pub mod math;
 pub mod renderable;
 mod shader;
 mod survey;
+mod table;
 mod tile_fetcher;
 mod time;
 mod fifo_cache;
@@ -86,13 +87,8 @@ mod fifo_cache;
 use crate::{
     camera::CameraViewPort, math::lonlat::LonLatT, shader::ShaderManager, time::DeltaTime,
     healpix::coverage::HEALPixCoverage,
+    healpix::time_coverage::{SpaceTimeCoverage, TimeCoverage},
 };
-use crate::downloader::request::moc::from_fits_hpx;
-use moclib::deser::fits::MocQtyType;
-use moclib::deser::fits::MocIdxType;
-use moclib::deser::fits;
-
-use std::io::Cursor;
 
 use al_api::hips::HiPSProperties;
 use al_api::coo_system::CooSystem;
@@ -108,7 +104,6 @@ use app::App;
 use cgmath::{Vector2};
 
 use math::angle::ArcDeg;
-use moclib::{qty::Hpx, moc::{CellMOCIterator, CellMOCIntoIterator, RangeMOCIterator}};
 
 #[wasm_bindgen]
 pub struct WebClient {
@@ -335,6 +330,30 @@ impl WebClient {
         self.app.swap_layers(&first_layer, &second_layer)
     }
 
+    /// Mask the display of a HiPS layer with a loaded MOC
+    ///
+    /// The MOC is copied so that removing it later does not unmask the layer
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The name of the layer
+    /// * `params` - The parameters of the MOC. The layer is shown only over it
+    /// * `inverted` - Show the layer only outside of the MOC instead
+    #[wasm_bindgen(js_name = setLayerMocMask)]
+    pub fn set_layer_moc_mask(&mut self, layer: String, params: &al_api::moc::MOC, inverted: bool) -> Result<(), JsValue> {
+        self.app.set_layer_moc_mask(&layer, params, inverted)
+    }
+
+    /// Show a masked HiPS layer entirely again
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The name of the layer
+    #[wasm_bindgen(js_name = removeLayerMocMask)]
+    pub fn remove_layer_moc_mask(&mut self, layer: String) {
+        self.app.remove_layer_moc_mask(&layer)
+    }
+
     #[wasm_bindgen(js_name = setHiPSUrl)]
     pub fn set_hips_url(&mut self, past_url: String, new_url: String) -> Result<(), JsValue> {
         self.app.set_hips_url(past_url, new_url)
@@ -381,6 +400,42 @@ impl WebClient {
         self.app.set_grid_cfg(cfg)
     }
 
+    /// Add a new coordinate grid drawn over the view
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The name identifying the grid
+    /// * `cfg` - The configuration of the grid (frame, color, opacity, labels, step)
+    #[wasm_bindgen(js_name = addGrid)]
+    pub fn add_grid(&mut self, id: String, cfg: JsValue) -> Result<(), JsValue> {
+        let cfg = serde_wasm_bindgen::from_value(cfg)?;
+
+        self.app.add_grid(id, cfg)
+    }
+
+    /// Remove a coordinate grid
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The name identifying the grid
+    #[wasm_bindgen(js_name = removeGrid)]
+    pub fn remove_grid(&mut self, id: String) -> Result<(), JsValue> {
+        self.app.remove_grid(&id)
+    }
+
+    /// Set the configuration of a coordinate grid
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The name identifying the grid
+    /// * `cfg` - The configuration of the grid (frame, color, opacity, labels, step)
+    #[wasm_bindgen(js_name = setGridConfigById)]
+    pub fn set_grid_cfg_by_id(&mut self, id: String, cfg: JsValue) -> Result<(), JsValue> {
+        let cfg = serde_wasm_bindgen::from_value(cfg)?;
+
+        self.app.set_grid_cfg_by_id(&id, cfg)
+    }
+
     /// Set the coordinate system for the view
     ///
     /// # Arguments
@@ -627,6 +682,109 @@ impl WebClient {
         Ok(())
     }
 
+    /// Add a catalog from the bytes of a VOTable file.
+    ///
+    /// The TABLEDATA, BINARY and BINARY2 serializations are supported.
+    /// The RA/Dec columns are found from their UCDs.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `data` - The bytes of the VOTable
+    /// * `colormap` - The name of the colormap. Check out the list of possible colormaps names `getAvailableColormapList`.
+    #[wasm_bindgen(js_name = addVOTable)]
+    pub fn add_votable(
+        &mut self,
+        name_catalog: String,
+        data: Vec<u8>,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.app.add_votable(name_catalog, data, colormap)
+    }
+
+    /// Add a catalog from the url of a VOTable file.
+    ///
+    /// The file is parsed as it is downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `url` - The url of the VOTable
+    /// * `colormap` - The name of the colormap. Check out the list of possible colormaps names `getAvailableColormapList`.
+    #[wasm_bindgen(js_name = addVOTableFromURL)]
+    pub fn add_votable_from_url(
+        &mut self,
+        name_catalog: String,
+        url: String,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.app.add_votable_from_url(name_catalog, url, colormap)
+    }
+
+    /// Add a catalog from the bytes of a CSV, TSV or whitespace separated ASCII table.
+    ///
+    /// The delimiter and the header are detected from the first lines.
+    /// The RA/Dec columns can be given in decimal degrees or in sexagesimal.
+    /// The rows that could not be read are reported in the console with their line number.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `data` - The bytes of the table
+    /// * `colormap` - The name of the colormap. Check out the list of possible colormaps names `getAvailableColormapList`.
+    #[wasm_bindgen(js_name = addDelimitedTable)]
+    pub fn add_delimited_table(
+        &mut self,
+        name_catalog: String,
+        data: Vec<u8>,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.app.add_delimited_table(name_catalog, data, colormap)
+    }
+
+    /// Add a catalog from the url of a CSV, TSV or whitespace separated ASCII table.
+    ///
+    /// The file is parsed as it is downloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `url` - The url of the table
+    /// * `colormap` - The name of the colormap. Check out the list of possible colormaps names `getAvailableColormapList`.
+    #[wasm_bindgen(js_name = addDelimitedTableFromURL)]
+    pub fn add_delimited_table_from_url(
+        &mut self,
+        name_catalog: String,
+        url: String,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.app.add_delimited_table_from_url(name_catalog, url, colormap)
+    }
+
+    /// Add a progressive (HiPS) catalog.
+    ///
+    /// The tiles covering the view are fetched and parsed in the backend.
+    /// They are evicted when they leave the view.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `url` - The root url of the HiPS catalog
+    /// * `min_order` - The order of the lowest tiles (`hips_order_min` property, usually 1)
+    /// * `max_order` - The order of the deepest tiles (`hips_order` property)
+    /// * `colormap` - The name of the colormap. Check out the list of possible colormaps names `getAvailableColormapList`.
+    #[wasm_bindgen(js_name = addProgressiveCatalog)]
+    pub fn add_progressive_catalog(
+        &mut self,
+        name_catalog: String,
+        url: String,
+        min_order: u8,
+        max_order: u8,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.app.add_progressive_catalog(name_catalog, url, min_order, max_order, colormap)
+    }
+
     /// Set the catalog heatmap colormap
     ///
     /// # Arguments
@@ -685,6 +843,290 @@ impl WebClient {
         Ok(())
     }
 
+    /// Set the maximum number of sources drawn for a catalog
+    ///
+    /// Beyond this budget, the densest HEALPix cells of the view are drawn through their
+    /// aggregate (their brightest source and the centroid of the others)
+    /// instead of their individual sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `budget` - The maximum number of sources drawn
+    #[wasm_bindgen(js_name = setCatalogLODBudget)]
+    pub fn set_catalog_lod_budget(&mut self, name_catalog: String, budget: usize) -> Result<(), JsValue> {
+        self.app.set_catalog_lod_budget(name_catalog, budget)
+    }
+
+    /// Set the kernel of a catalog heatmap
+    ///
+    /// The kernels other than the default one have an angular size and are normalized
+    /// so that the heatmap gives the surface density of the sources.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `kernel` - One of `default`, `gaussian`, `tophat` or `epanechnikov`
+    /// * `width` - The standard deviation of the gaussian kernel, the radius of the
+    ///   other ones, in arcseconds. Not used by the default kernel
+    #[wasm_bindgen(js_name = setCatalogKernel)]
+    pub fn set_catalog_kernel(&mut self, name_catalog: String, kernel: String, width: f64) -> Result<(), JsValue> {
+        self.app.set_catalog_kernel(name_catalog, kernel, width)
+    }
+
+    /// Set the intensity scaling of a catalog heatmap
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `max_density` - The surface density, in weight per square arcsecond, mapped to
+    ///   the top of the colormap. If not given, it is estimated from the sources in the view
+    #[wasm_bindgen(js_name = setCatalogIntensityScale)]
+    pub fn set_catalog_intensity_scale(&mut self, name_catalog: String, max_density: Option<f64>) -> Result<(), JsValue> {
+        self.app.set_catalog_scaling(name_catalog, max_density)
+    }
+
+    /// Weight the sources of a catalog heatmap
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `column` - The name of a numeric column giving the weights. The sources
+    ///   are equally weighted if not given
+    #[wasm_bindgen(js_name = setCatalogWeightColumn)]
+    pub fn set_catalog_weight_column(&mut self, name_catalog: String, column: Option<String>) -> Result<(), JsValue> {
+        self.app.set_catalog_weight_column(name_catalog, column)
+    }
+
+    /// Propagate the sources of the catalogs having proper motions to an epoch
+    ///
+    /// The proper motions, parallaxes and radial velocities are read from the
+    /// columns of the catalogs, found by their UCD or their usual names
+    ///
+    /// # Arguments
+    ///
+    /// * `epoch` - The target epoch in Julian years (e.g. 2016.0). The sources are moved
+    ///   back to their reference positions if not given
+    #[wasm_bindgen(js_name = setCatalogsEpoch)]
+    pub fn set_catalogs_epoch(&mut self, epoch: Option<f64>) -> Result<(), JsValue> {
+        self.app.set_catalogs_epoch(epoch)
+    }
+
+    /// Set the epoch of the positions of a catalog
+    ///
+    /// Used for the sources whose epoch is not given by a column (e.g. `ref_epoch`)
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `epoch` - The epoch in Julian years, J2000 if not given
+    #[wasm_bindgen(js_name = setCatalogReferenceEpoch)]
+    pub fn set_catalog_reference_epoch(&mut self, name_catalog: String, epoch: Option<f64>) -> Result<(), JsValue> {
+        self.app.set_catalog_reference_epoch(name_catalog, epoch)
+    }
+
+    /// Bin the sources of a catalog into the HEALPix cells of an order
+    ///
+    /// Returns a partial HEALPix map `{ order, cells, values }` giving the non empty cells
+    /// in the NESTED scheme along with their number of sources (or the sum of the weights
+    /// of their sources)
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `order` - The order of the HEALPix cells, lower than 25
+    /// * `weight_column` - The numeric column whose values are summed instead of counting the sources
+    #[wasm_bindgen(js_name = getCatalogDensityMap)]
+    pub fn get_catalog_density_map(
+        &self,
+        name_catalog: String,
+        order: u8,
+        weight_column: Option<String>,
+    ) -> Result<JsValue, JsValue> {
+        let map = self
+            .app
+            .get_catalog_density_map(&name_catalog, order, weight_column.as_deref())?;
+
+        Ok(serde_wasm_bindgen::to_value(&map)?)
+    }
+
+    /// Bin the sources of a catalog into the HEALPix cells of an order and
+    /// return the map as a HEALPix partial map FITS file
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `order` - The order of the HEALPix cells, lower than 25
+    /// * `weight_column` - The numeric column whose values are summed instead of counting the sources
+    #[wasm_bindgen(js_name = getCatalogDensityMapFITS)]
+    pub fn get_catalog_density_map_fits(
+        &self,
+        name_catalog: String,
+        order: u8,
+        weight_column: Option<String>,
+    ) -> Result<Box<[u8]>, JsValue> {
+        let map = self
+            .app
+            .get_catalog_density_map(&name_catalog, order, weight_column.as_deref())?;
+
+        Ok(map.to_fits().into_boxed_slice())
+    }
+
+    /// Bin the sources of a catalog into the HEALPix cells of an order
+    /// and render the map as a new catalog heatmap
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `order` - The order of the HEALPix cells, lower than 25
+    /// * `weight_column` - The numeric column whose values are summed instead of counting the sources
+    /// * `layer` - The name of the catalog rendering the map, it must not be used by another catalog
+    /// * `colormap` - The colormap of the map
+    #[wasm_bindgen(js_name = addCatalogDensityMap)]
+    pub fn add_catalog_density_map(
+        &mut self,
+        name_catalog: String,
+        order: u8,
+        weight_column: Option<String>,
+        layer: String,
+        colormap: String,
+    ) -> Result<(), JsValue> {
+        self.app
+            .add_catalog_density_map(name_catalog, order, weight_column, layer, colormap)
+    }
+
+    /// Add the MOC of the HEALPix cells of an order containing sources of a catalog
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `order` - The order of the HEALPix cells, lower than 25
+    /// * `params` - The parameters of the MOC layer
+    #[wasm_bindgen(js_name = addCatalogCoverage)]
+    pub fn add_catalog_coverage(
+        &mut self,
+        name_catalog: String,
+        order: u8,
+        params: &al_api::moc::MOC,
+    ) -> Result<(), JsValue> {
+        self.app.add_catalog_coverage(&name_catalog, order, params.clone())
+    }
+
+    /// Remove a catalog
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to remove
+    #[wasm_bindgen(js_name = removeCatalog)]
+    pub fn remove_catalog(&mut self, name_catalog: String) -> Result<(), JsValue> {
+        self.app.remove_catalog(name_catalog)
+    }
+
+    /// Rename a catalog
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The current name of the catalog
+    /// * `new_name` - Its new name. It must not be used by another catalog
+    #[wasm_bindgen(js_name = renameCatalog)]
+    pub fn rename_catalog(&mut self, name_catalog: String, new_name: String) -> Result<(), JsValue> {
+        self.app.rename_catalog(name_catalog, new_name)
+    }
+
+    /// Replace the sources of a catalog
+    ///
+    /// The sources are sorted asynchronously, the catalog is updated once they are ready.
+    /// The attributes of the previous sources are discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to update
+    /// * `radec` - The positions of the sources in ICRS given as successive (ra, dec) pairs in degrees
+    #[wasm_bindgen(js_name = setCatalogSources)]
+    pub fn set_catalog_sources(&mut self, name_catalog: String, radec: Vec<f64>) -> Result<(), JsValue> {
+        self.app.set_catalog_sources(name_catalog, radec)
+    }
+
+    /// Add new sources to a catalog
+    ///
+    /// The new sources are sorted asynchronously and merged with the ones of the catalog.
+    /// Can be called repeatedly to stream detections into a catalog.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to update
+    /// * `radec` - The positions of the new sources in ICRS given as successive (ra, dec) pairs in degrees
+    #[wasm_bindgen(js_name = appendCatalogSources)]
+    pub fn append_catalog_sources(&mut self, name_catalog: String, radec: Vec<f64>) -> Result<(), JsValue> {
+        self.app.append_catalog_sources(name_catalog, radec)
+    }
+
+    /// Select the sources of a catalog inside a polygon drawn on the screen (lasso selection)
+    ///
+    /// The polygon is deprojected into a spherical polygon, the smallest of the two areas
+    /// delimited by its vertices. The selected sources are kept for the catalog.
+    ///
+    /// Returns `{ sources: { fields, rows }, moc }` where `fields` are the names of the
+    /// columns of the `rows` (the positions `_RAJ2000` and `_DEJ2000` in degrees followed
+    /// by the attributes of the catalog) and `moc` is the coverage of the polygon in the
+    /// JSON format of Aladin
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `screen_vertices` - The vertices of the polygon given as successive (x, y) pairs in pixels
+    /// * `order` - The order of the MOC, lower than 25
+    #[wasm_bindgen(js_name = selectCatalogSourcesInPolygon)]
+    pub fn select_catalog_sources_in_polygon(
+        &mut self,
+        name_catalog: String,
+        screen_vertices: Vec<f64>,
+        order: u8,
+    ) -> Result<JsValue, JsValue> {
+        let screen_vertices = screen_vertices
+            .chunks_exact(2)
+            .map(|v| (v[0], v[1]))
+            .collect::<Vec<_>>();
+        let (rows, moc) = self
+            .app
+            .select_catalog_sources_in_polygon(&name_catalog, &screen_vertices, order)?;
+
+        let selection = js_sys::Object::new();
+        js_sys::Reflect::set(&selection, &"sources".into(), &serde_wasm_bindgen::to_value(&rows)?)?;
+        js_sys::Reflect::set(&selection, &"moc".into(), &js_sys::JSON::parse(&moc.to_json())?)?;
+
+        Ok(selection.into())
+    }
+
+    /// Serialize the sources of a catalog with all their attributes
+    ///
+    /// The positions of the sources, as drawn, are given by the first two columns
+    /// `_RAJ2000` and `_DEJ2000` in degrees
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `format` - The format of the table, "csv" or "votable"
+    /// * `subset` - The sources to export: "all", "view" for the ones in the cells
+    ///   covering the view or "selection" for the ones last selected
+    #[wasm_bindgen(js_name = exportCatalog)]
+    pub fn export_catalog(&mut self, name_catalog: String, format: String, subset: String) -> Result<Box<[u8]>, JsValue> {
+        self.app
+            .export_catalog(&name_catalog, &format, &subset)
+            .map(|bytes| bytes.into_boxed_slice())
+    }
+
+    /// Change the colormap of a catalog heatmap
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `colormap` - The name of the colormap
+    #[wasm_bindgen(js_name = setCatalogColormap)]
+    pub fn set_catalog_colormap(&mut self, name_catalog: String, colormap: String) -> Result<(), JsValue> {
+        self.app.set_catalog_colormap(name_catalog, colormap)
+    }
+
     /// Project a line to the screen
     ///
     /// # Returns
@@ -805,32 +1247,131 @@ impl WebClient {
     pub fn add_json_moc(&mut self, params: &al_api::moc::MOC, data: &JsValue) -> Result<(), JsValue> {
         let str: String = js_sys::JSON::stringify(data)?.into();
 
-        let moc = moclib::deser::json::from_json_aladin::<u64, Hpx<u64>>(&str)
-            .map_err(|e| JsValue::from(js_sys::Error::new(&e.to_string())))?
-            .into_cell_moc_iter()
-            .ranges()
-            .into_range_moc();
+        let moc = HEALPixCoverage::from_json(&str)
+            .map_err(|e| JsValue::from(js_sys::Error::new(&e)))?;
 
-        self.app.add_moc(params.clone(), HEALPixCoverage(moc))?;
+        self.app.add_moc(params.clone(), moc)?;
 
         Ok(())
     }
 
     #[wasm_bindgen(js_name = addFITSMoc)]
     pub fn add_fits_moc(&mut self, params: &al_api::moc::MOC, data: &[u8]) -> Result<(), JsValue> {
-        //let bytes = js_sys::Uint8Array::new(array_buffer).to_vec();
-        let moc = match fits::from_fits_ivoa_custom(Cursor::new(&data[..]), false).map_err(|e| JsValue::from_str(&e.to_string()))? {
-            MocIdxType::U16(MocQtyType::<u16, _>::Hpx(moc)) => Ok(crate::downloader::request::moc::from_fits_hpx(moc)),
-            MocIdxType::U32(MocQtyType::<u32, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-            MocIdxType::U64(MocQtyType::<u64, _>::Hpx(moc)) => Ok(from_fits_hpx(moc)),
-            _ => Err(JsValue::from_str("MOC not supported. Must be a HPX MOC"))
-        }?;
+        let moc = HEALPixCoverage::from_fits(data, false)?;
+
+        self.app.add_moc(params.clone(), moc)?;
+
+        Ok(())
+    }
+
+    /// Add a MOC serialized in the ASCII format of the MOC 2.0 standard
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    /// * `data` - The ASCII MOC, e.g. `3/1-5 4/42`
+    #[wasm_bindgen(js_name = addASCIIMoc)]
+    pub fn add_ascii_moc(&mut self, params: &al_api::moc::MOC, data: &str) -> Result<(), JsValue> {
+        let moc = HEALPixCoverage::from_ascii(data)?;
 
-        self.app.add_moc(params.clone(), HEALPixCoverage(moc))?;
+        self.app.add_moc(params.clone(), moc)?;
 
         Ok(())
     }
 
+    /// Add a MOC from its url
+    ///
+    /// The MOC is added once downloaded. Its serialization, FITS, JSON or ASCII,
+    /// is guessed from its content.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    /// * `url` - The url of the MOC
+    #[wasm_bindgen(js_name = addMocFromURL)]
+    pub fn add_moc_from_url(&mut self, params: &al_api::moc::MOC, url: String) -> Result<(), JsValue> {
+        self.app.add_moc_from_url(params.clone(), url)
+    }
+
+    /// Add a Space-Time MOC from a MOC 2.0 FITS file
+    ///
+    /// It is displayed as the sky area observed during the time window, see `setTimeWindow`.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    /// * `data` - The bytes of the FITS file
+    #[wasm_bindgen(js_name = addFITSSpaceTimeMoc)]
+    pub fn add_fits_space_time_moc(&mut self, params: &al_api::moc::MOC, data: &[u8]) -> Result<(), JsValue> {
+        let moc = SpaceTimeCoverage::from_fits(data)?;
+
+        self.app.add_space_time_moc(params.clone(), moc)
+    }
+
+    /// Get the first and the last instants observed by a Space-Time MOC
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the Space-Time MOC
+    ///
+    /// # Returns
+    ///
+    /// An array `[jd_min, jd_max]` in julian days, empty if nothing has been observed
+    #[wasm_bindgen(js_name = getSpaceTimeMocTimeRange)]
+    pub fn get_space_time_moc_time_range(&self, params: &al_api::moc::MOC) -> Result<Box<[f64]>, JsValue> {
+        let range = self.app.get_space_time_moc(params)?
+            .time_coverage()
+            .jd_range()
+            .map(|(jd_min, jd_max)| vec![jd_min, jd_max])
+            .unwrap_or_default();
+
+        Ok(range.into_boxed_slice())
+    }
+
+    /// Set the time window the Space-Time MOCs are projected on
+    ///
+    /// # Arguments
+    ///
+    /// * `jd_min` - The beginning of the window in julian days
+    /// * `jd_max` - The end of the window in julian days
+    #[wasm_bindgen(js_name = setTimeWindow)]
+    pub fn set_time_window(&mut self, jd_min: f64, jd_max: f64) -> Result<(), JsValue> {
+        if !jd_min.is_finite() || !jd_max.is_finite() || jd_min < 0.0 || jd_min >= jd_max {
+            return Err(JsValue::from_str("The time window must be a non empty interval of positive julian days"));
+        }
+
+        self.app.set_time_window(Some(TimeCoverage::from_jd_range(jd_min, jd_max)))
+    }
+
+    /// Set the time window the Space-Time MOCs are projected on from a Time MOC
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The bytes of the Time MOC 2.0 FITS file
+    ///
+    /// # Returns
+    ///
+    /// An array `[jd_min, jd_max]` in julian days of the first and the last instants of the Time MOC,
+    /// empty if the Time MOC is empty
+    #[wasm_bindgen(js_name = setTimeWindowFromFITSMoc)]
+    pub fn set_time_window_from_fits_moc(&mut self, data: &[u8]) -> Result<Box<[f64]>, JsValue> {
+        let time_window = TimeCoverage::from_fits(data)?;
+        let range = time_window
+            .jd_range()
+            .map(|(jd_min, jd_max)| vec![jd_min, jd_max])
+            .unwrap_or_default();
+
+        self.app.set_time_window(Some(time_window))?;
+
+        Ok(range.into_boxed_slice())
+    }
+
+    /// Remove the time window, the Space-Time MOCs are then displayed entirely
+    #[wasm_bindgen(js_name = removeTimeWindow)]
+    pub fn remove_time_window(&mut self) -> Result<(), JsValue> {
+        self.app.set_time_window(None)
+    }
+
     #[wasm_bindgen(js_name = removeMoc)]
     pub fn remove_moc(&mut self, params: &al_api::moc::MOC) -> Result<(), JsValue> {
         self.app.remove_moc(params)?;
@@ -847,7 +1388,7 @@ impl WebClient {
 
     #[wasm_bindgen(js_name = mocContains)]
     pub fn moc_contains(&mut self, params: &al_api::moc::MOC, lon: f64, lat: f64) -> Result<bool, JsValue> {
-        let moc = self.app.get_moc(params).ok_or_else(|| JsValue::from(js_sys::Error::new("MOC not found")))?;
+        let moc = self.app.get_moc_or_err(params)?;
         
         let location = LonLatT::new(ArcDeg(lon).into(), ArcDeg(lat).into());
 
@@ -856,8 +1397,354 @@ impl WebClient {
 
     #[wasm_bindgen(js_name = mocSkyFraction)]
     pub fn moc_sky_fraction(&mut self, params: &al_api::moc::MOC) -> Result<f32, JsValue> {
-        let moc = self.app.get_moc(params).ok_or_else(|| JsValue::from(js_sys::Error::new("MOC not found")))?;
+        let moc = self.app.get_moc_or_err(params)?;
 
         Ok(moc.coverage_percentage() as f32)
     }
+
+    /// Get the area of a loaded MOC in square degrees
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getMocArea)]
+    pub fn get_moc_area(&self, params: &al_api::moc::MOC) -> Result<f64, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        Ok(moc.area())
+    }
+
+    /// Get the number of cells of each order a loaded MOC is made of, from the order 0 to its maximum order
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getMocNumCellsPerOrder)]
+    pub fn get_moc_num_cells_per_order(&self, params: &al_api::moc::MOC) -> Result<Box<[f64]>, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        Ok(moc
+            .num_cells_per_depth()
+            .into_iter()
+            .map(|num_cells| num_cells as f64)
+            .collect())
+    }
+
+    /// Get a cone containing a loaded MOC
+    ///
+    /// The cone is returned as its center (ra, dec) and its radius in degrees, or undefined for an empty MOC
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getMocBoundingCone)]
+    pub fn get_moc_bounding_cone(&self, params: &al_api::moc::MOC) -> Result<Option<Box<[f64]>>, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        Ok(moc.bounding_cone().map(|(lon, lat, radius)| {
+            Box::new([lon.to_degrees(), lat.to_degrees(), radius.to_degrees()]) as Box<[f64]>
+        }))
+    }
+
+    /// Get the cells a loaded MOC is made of, given by their UNIQ index, i.e. `4 * 4^order + ipix`
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getMocUniqCells)]
+    pub fn get_moc_uniq_cells(&self, params: &al_api::moc::MOC) -> Result<Box<[u64]>, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        Ok(moc.uniq_cells().into_boxed_slice())
+    }
+
+    /// Get the ranges of cells of order 29 a loaded MOC is made of
+    ///
+    /// The ranges are flattened, i.e. `[start0, end0, start1, end1, ...]`, their ends being excluded
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getMocRanges)]
+    pub fn get_moc_ranges(&self, params: &al_api::moc::MOC) -> Result<Box<[u64]>, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        Ok(moc
+            .ranges()
+            .flat_map(|range| [range.start, range.end])
+            .collect())
+    }
+
+    /// Get the perimeter of a loaded MOC, i.e. its outer boundaries and the ones of its holes
+    ///
+    /// Each boundary is returned as a closed polyline of [lon, lat] vertices in degrees, the
+    /// first vertex not being repeated at its end.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getMocPerimeter)]
+    pub fn get_moc_perimeter(&self, params: &al_api::moc::MOC) -> Result<JsValue, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        let perimeter = moc
+            .perimeter()
+            .into_iter()
+            .map(|polyline| {
+                polyline
+                    .into_iter()
+                    .map(|(lon, lat)| [lon.to_degrees(), lat.to_degrees()])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        Ok(serde_wasm_bindgen::to_value(&perimeter)?)
+    }
+
+    /// Serialize a loaded MOC into a MOC 2.0 FITS file
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getFITSMoc)]
+    pub fn get_fits_moc(&self, params: &al_api::moc::MOC) -> Result<Box<[u8]>, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        Ok(moc.to_fits()?.into_boxed_slice())
+    }
+
+    /// Serialize a loaded MOC into the ASCII format of the MOC 2.0 standard, e.g. `3/1-5 4/42`
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getASCIIMoc)]
+    pub fn get_ascii_moc(&self, params: &al_api::moc::MOC) -> Result<String, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+
+        Ok(moc.to_ascii()?)
+    }
+
+    /// Serialize a loaded MOC into the JSON format of Aladin, i.e. `{"order": [cells]}`
+    ///
+    /// The cells of the orders greater than 24 cannot be represented by javascript
+    /// numbers, such MOCs must be serialized in FITS or ASCII
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The parameters of the MOC
+    #[wasm_bindgen(js_name = getJSONMoc)]
+    pub fn get_json_moc(&self, params: &al_api::moc::MOC) -> Result<JsValue, JsValue> {
+        let moc = self.app.get_moc_or_err(params)?;
+        if moc.depth_max() > 24 {
+            return Err(JsValue::from_str("The order of the MOC is greater than 24, serialize it in FITS or ASCII"));
+        }
+
+        js_sys::JSON::parse(&moc.to_json())
+    }
+
+    /// Add the union of two loaded MOCs as a new MOC layer
+    ///
+    /// # Arguments
+    ///
+    /// * `lhs` - The parameters of the first MOC
+    /// * `rhs` - The parameters of the second MOC
+    /// * `params` - The parameters of the resulting MOC layer
+    #[wasm_bindgen(js_name = mocUnion)]
+    pub fn moc_union(&mut self, lhs: &al_api::moc::MOC, rhs: &al_api::moc::MOC, params: &al_api::moc::MOC) -> Result<(), JsValue> {
+        self.app.union_mocs(lhs, rhs, params.clone())
+    }
+
+    /// Add the intersection of two loaded MOCs as a new MOC layer
+    ///
+    /// # Arguments
+    ///
+    /// * `lhs` - The parameters of the first MOC
+    /// * `rhs` - The parameters of the second MOC
+    /// * `params` - The parameters of the resulting MOC layer
+    #[wasm_bindgen(js_name = mocIntersection)]
+    pub fn moc_intersection(&mut self, lhs: &al_api::moc::MOC, rhs: &al_api::moc::MOC, params: &al_api::moc::MOC) -> Result<(), JsValue> {
+        self.app.intersect_mocs(lhs, rhs, params.clone())
+    }
+
+    /// Add the cells of a loaded MOC not in another one as a new MOC layer
+    ///
+    /// # Arguments
+    ///
+    /// * `lhs` - The parameters of the MOC
+    /// * `rhs` - The parameters of the MOC subtracted from `lhs`
+    /// * `params` - The parameters of the resulting MOC layer
+    #[wasm_bindgen(js_name = mocDifference)]
+    pub fn moc_difference(&mut self, lhs: &al_api::moc::MOC, rhs: &al_api::moc::MOC, params: &al_api::moc::MOC) -> Result<(), JsValue> {
+        self.app.subtract_mocs(lhs, rhs, params.clone())
+    }
+
+    /// Add the cells in exactly one of two loaded MOCs as a new MOC layer
+    ///
+    /// # Arguments
+    ///
+    /// * `lhs` - The parameters of the first MOC
+    /// * `rhs` - The parameters of the second MOC
+    /// * `params` - The parameters of the resulting MOC layer
+    #[wasm_bindgen(js_name = mocSymmetricDifference)]
+    pub fn moc_symmetric_difference(&mut self, lhs: &al_api::moc::MOC, rhs: &al_api::moc::MOC, params: &al_api::moc::MOC) -> Result<(), JsValue> {
+        self.app.symmetric_difference_mocs(lhs, rhs, params.clone())
+    }
+
+    /// Add the complement of a loaded MOC as a new MOC layer
+    ///
+    /// # Arguments
+    ///
+    /// * `moc` - The parameters of the MOC
+    /// * `params` - The parameters of the resulting MOC layer
+    #[wasm_bindgen(js_name = mocComplement)]
+    pub fn moc_complement(&mut self, moc: &al_api::moc::MOC, params: &al_api::moc::MOC) -> Result<(), JsValue> {
+        self.app.complement_moc(moc, params.clone())
+    }
+
+    /// Add a loaded MOC degraded to a lower order as a new MOC layer
+    ///
+    /// # Arguments
+    ///
+    /// * `moc` - The parameters of the MOC
+    /// * `order` - The new maximum order, lower than the one of the MOC
+    /// * `params` - The parameters of the resulting MOC layer
+    #[wasm_bindgen(js_name = mocDegrade)]
+    pub fn moc_degrade(&mut self, moc: &al_api::moc::MOC, order: u8, params: &al_api::moc::MOC) -> Result<(), JsValue> {
+        self.app.degrade_moc(moc, order, params.clone())
+    }
+
+    /// Build the MOC of a cone
+    ///
+    /// Returns the MOC in the JSON format of Aladin
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - The longitude of the center in ICRS, in degrees
+    /// * `lat` - The latitude of the center in ICRS, in degrees
+    /// * `radius` - The radius in degrees
+    /// * `order` - The maximum order of the MOC, lower than 25
+    /// * `params` - The parameters of the MOC layer. The MOC is not displayed if not given
+    #[wasm_bindgen(js_name = mocFromCone)]
+    pub fn moc_from_cone(
+        &mut self,
+        lon: f64,
+        lat: f64,
+        radius: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<JsValue, JsValue> {
+        let moc = self.app.moc_from_cone(lon, lat, radius, order, params)?;
+        js_sys::JSON::parse(&moc.to_json())
+    }
+
+    /// Build the MOC of an ellipse
+    ///
+    /// Returns the MOC in the JSON format of Aladin
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - The longitude of the center in ICRS, in degrees
+    /// * `lat` - The latitude of the center in ICRS, in degrees
+    /// * `a` - The first semi-axis in degrees, lower than 90
+    /// * `b` - The second semi-axis in degrees, lower than 90
+    /// * `pa` - The position angle of the first axis, east of north, in degrees
+    /// * `order` - The maximum order of the MOC, lower than 25
+    /// * `params` - The parameters of the MOC layer. The MOC is not displayed if not given
+    #[wasm_bindgen(js_name = mocFromEllipse)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn moc_from_ellipse(
+        &mut self,
+        lon: f64,
+        lat: f64,
+        a: f64,
+        b: f64,
+        pa: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<JsValue, JsValue> {
+        let moc = self.app.moc_from_ellipse((lon, lat), (a, b), pa, order, params)?;
+        js_sys::JSON::parse(&moc.to_json())
+    }
+
+    /// Build the MOC of a box whose sides are great circles
+    ///
+    /// Returns the MOC in the JSON format of Aladin
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - The longitude of the center in ICRS, in degrees
+    /// * `lat` - The latitude of the center in ICRS, in degrees
+    /// * `a` - The first half-width in degrees, lower than 90
+    /// * `b` - The second half-width in degrees, lower than 90
+    /// * `pa` - The position angle of the first axis, east of north, in degrees
+    /// * `order` - The maximum order of the MOC, lower than 25
+    /// * `params` - The parameters of the MOC layer. The MOC is not displayed if not given
+    #[wasm_bindgen(js_name = mocFromBox)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn moc_from_box(
+        &mut self,
+        lon: f64,
+        lat: f64,
+        a: f64,
+        b: f64,
+        pa: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<JsValue, JsValue> {
+        let moc = self.app.moc_from_box((lon, lat), (a, b), pa, order, params)?;
+        js_sys::JSON::parse(&moc.to_json())
+    }
+
+    /// Build the MOC of a spherical polygon
+    ///
+    /// The polygon is the smallest of the two areas delimited by its vertices.
+    /// Returns the MOC in the JSON format of Aladin
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The vertices in ICRS given as successive (lon, lat) pairs in degrees
+    /// * `order` - The maximum order of the MOC, lower than 25
+    /// * `params` - The parameters of the MOC layer. The MOC is not displayed if not given
+    #[wasm_bindgen(js_name = mocFromPolygon)]
+    pub fn moc_from_polygon(
+        &mut self,
+        vertices: Vec<f64>,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<JsValue, JsValue> {
+        let vertices = vertices
+            .chunks_exact(2)
+            .map(|v| (v[0], v[1]))
+            .collect::<Vec<_>>();
+        let moc = self.app.moc_from_polygon(&vertices, order, params)?;
+        js_sys::JSON::parse(&moc.to_json())
+    }
+
+    /// Build the MOC of a zone delimited by two meridians and two parallels
+    ///
+    /// Returns the MOC in the JSON format of Aladin
+    ///
+    /// # Arguments
+    ///
+    /// * `lon_min` - The minimum longitude in ICRS, in degrees. The zone crosses the
+    ///   primary meridian if it is greater than `lon_max`
+    /// * `lat_min` - The minimum latitude in ICRS, in degrees
+    /// * `lon_max` - The maximum longitude in ICRS, in degrees
+    /// * `lat_max` - The maximum latitude in ICRS, in degrees
+    /// * `order` - The maximum order of the MOC, lower than 25
+    /// * `params` - The parameters of the MOC layer. The MOC is not displayed if not given
+    #[wasm_bindgen(js_name = mocFromZone)]
+    pub fn moc_from_zone(
+        &mut self,
+        lon_min: f64,
+        lat_min: f64,
+        lon_max: f64,
+        lat_max: f64,
+        order: u8,
+        params: Option<al_api::moc::MOC>,
+    ) -> Result<JsValue, JsValue> {
+        let moc = self.app.moc_from_zone(lon_min, lat_min, lon_max, lat_max, order, params)?;
+        js_sys::JSON::parse(&moc.to_json())
+    }
 }
@@ -5,12 +5,24 @@ use crate::CameraViewPort;
 use cgmath::Zero;
 use cgmath::InnerSpace;
 use crate::math::angle::ToAngle;
+use crate::math::projection::coo_space::XYNDC;
+use al_api::coo_system::CooSystem;
+
+// Project a position given in a frame that can differ from the one of the view
+fn proj(lon: f64, lat: f64, frame: &CooSystem, camera: &CameraViewPort, projection: &ProjectionType) -> Option<XYNDC> {
+    let xyzw = LonLatT::new(lon.to_angle(), lat.to_angle()).vector();
+    let xyzw = crate::coosys::apply_coo_system(frame, camera.get_system(), &xyzw);
+
+    projection.model_to_normalized_device_space(&xyzw, camera)
+}
 
 pub fn project_along_longitudes_and_latitudes(
     mut start_lon: f64,
     mut start_lat: f64,
     mut end_lon: f64,
     mut end_lat: f64, 
+    // The frame in which the longitudes and latitudes are given
+    frame: &CooSystem,
     camera: &CameraViewPort,
     projection: &ProjectionType
 ) -> Vec<Vector2<f64>> {
@@ -43,13 +55,13 @@ pub fn project_along_longitudes_and_latitudes(
     for i in 0..num_point_max {
         let (lon, lat) = (start_lon + (i as f64) * delta_lon, start_lat + (i as f64) * delta_lat);
 
-        if let Some(p) = crate::math::lonlat::proj(LonLatT::new(lon.to_angle(), lat.to_angle()), projection, camera) {            
+        if let Some(p) = proj(lon, lat, frame, camera, projection) {
             if start {
                 prev = (lon, lat, p);
                 start = false;
             } else {
                 let cur = (lon, lat, p);
-                subdivide_along_longitude_and_latitudes(&mut s_vert, prev, cur, camera, projection, 0);
+                subdivide_along_longitude_and_latitudes(&mut s_vert, prev, cur, frame, camera, projection, 0);
 
                 prev = cur;
             }
@@ -69,6 +81,7 @@ pub fn subdivide_along_longitude_and_latitudes(
     vertices: &mut Vec<Vector2<f64>>,
     (lon_s, lat_s, p_s): (f64, f64, Vector2<f64>),
     (lon_e, lat_e, p_e): (f64, f64, Vector2<f64>),
+    frame: &CooSystem,
     camera: &CameraViewPort,
     projection: &ProjectionType,
     iter: usize,
@@ -83,7 +96,7 @@ pub fn subdivide_along_longitude_and_latitudes(
     let lon_m = (lon_s + lon_e)*0.5;
     let lat_m = (lat_s + lat_e)*0.5;
 
-    if let Some(p_m) = crate::math::lonlat::proj(LonLatT(lon_m.to_angle(), lat_m.to_angle()), projection, camera) {
+    if let Some(p_m) = proj(lon_m, lat_m, frame, camera, projection) {
         let ab = p_m - p_s;
         let bc = p_e - p_m;
         let ab_l = ab.magnitude2();
@@ -125,6 +138,7 @@ pub fn subdivide_along_longitude_and_latitudes(
                 vertices,
                 (lon_s, lat_s, p_s),
                 (lon_m, lat_m, p_m),
+                frame,
                 camera,
                 projection,
                 iter + 1
@@ -134,6 +148,7 @@ pub fn subdivide_along_longitude_and_latitudes(
                 vertices,
                 (lon_m, lat_m, p_m),
                 (lon_e, lat_e, p_e),
+                frame,
                 camera,
                 projection,
                 iter + 1
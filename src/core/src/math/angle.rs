@@ -262,6 +262,93 @@ impl FormatType for DMS {
     }
 }
 
+// The maximum number of decimals written for the labels of an angle
+const MAX_NUM_DECIMALS: usize = 6;
+
+/// The number of decimals needed to write the multiples of `step`
+pub fn num_decimals(step: f64) -> usize {
+    (0..MAX_NUM_DECIMALS)
+        .find(|&d| {
+            let x = step * 10_f64.powi(d as i32);
+            (x - x.round()).abs() < 1e-6 * x.max(1.0)
+        })
+        .unwrap_or(MAX_NUM_DECIMALS)
+}
+
+/// Write an angle given in radians as decimal degrees with `digits` decimals
+pub fn to_decimal(angle: f64, digits: usize) -> String {
+    format!("{:.*}°", digits, angle.to_degrees())
+}
+
+/// Write a galactic longitude or latitude given in radians as decimal degrees
+/// followed by `axis` instead of the degree sign, e.g. 120l or -30b
+pub fn to_galactic(angle: f64, digits: usize, axis: char) -> String {
+    format!("{:.*}{}", digits, angle.to_degrees(), axis)
+}
+
+/// Write a longitude given in radians as hours, minutes and seconds, e.g. 12h30m15s
+///
+/// Only the fields needed to tell apart the longitudes `step` radians apart are written
+pub fn to_hms(lon: f64, step: f64) -> String {
+    let hours = lon.rem_euclid(2.0 * PI).to_degrees() / 15.0;
+    sexagesimal(hours, step.to_degrees() / 15.0, ["h", "m", "s"], false, Some(24))
+}
+
+/// Write an angle given in radians as degrees, arcminutes and arcseconds, e.g. +30°15'20''
+///
+/// Only the fields needed to tell apart the angles `step` radians apart are written
+pub fn to_dms(angle: f64, step: f64, signed: bool) -> String {
+    sexagesimal(angle.to_degrees(), step.to_degrees(), ["°", "'", "''"], signed, None)
+}
+
+// Write a value and a step given in the unit of the first field.
+// The value is wrapped around `wrap` units of the first field if given
+fn sexagesimal(value: f64, step: f64, units: [&str; 3], signed: bool, wrap: Option<u64>) -> String {
+    const EPS: f64 = 1e-9;
+    // The number of fields written and of decimals of the last one
+    let (num_fields, num_decimals) = if step >= 1.0 - EPS {
+        (1, 0)
+    } else if step * 60.0 >= 1.0 - EPS {
+        (2, 0)
+    } else {
+        (3, num_decimals(step * 3600.0))
+    };
+
+    // The number of units of the last field in one unit of the first one
+    let scale = match num_fields {
+        1 => 1,
+        2 => 60,
+        _ => 3600 * 10_u64.pow(num_decimals as u32),
+    };
+    let mut n = (value.abs() * scale as f64).round() as u64;
+    if let Some(wrap) = wrap {
+        n %= wrap * scale;
+    }
+
+    let mut result = if !signed {
+        String::new()
+    } else if value < 0.0 && n > 0 {
+        "-".to_string()
+    } else {
+        "+".to_string()
+    };
+
+    result += &format!("{}{}", n / scale, units[0]);
+    if num_fields > 1 {
+        let per_minute = scale / 60;
+        let rem = n % scale;
+        result += &format!("{:02}{}", rem / per_minute, units[1]);
+
+        if num_fields > 2 {
+            let seconds = (rem % per_minute) as f64 / 10_f64.powi(num_decimals as i32);
+            let width = if num_decimals > 0 { num_decimals + 3 } else { 2 };
+            result += &format!("{:0width$.prec$}{}", seconds, units[2], width = width, prec = num_decimals);
+        }
+    }
+
+    result
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash)]
 #[repr(C)]
 pub struct Angle<S: BaseFloat>(pub S);
@@ -643,3 +730,31 @@ impl UniformType for Angle<f64> {
         gl.uniform1f(location, value.0 as f32);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{to_decimal, to_dms, to_galactic, to_hms};
+
+    #[test]
+    fn sexagesimal_labels() {
+        let hour = 15_f64.to_radians();
+        let minute = hour / 60.0;
+        let second = minute / 60.0;
+
+        assert_eq!(to_hms(12.5 * hour, hour), "13h");
+        assert_eq!(to_hms(12.5 * hour, 10.0 * minute), "12h30m");
+        assert_eq!(to_hms(12.0 * hour + 30.0 * minute + 15.0 * second, second), "12h30m15s");
+        assert_eq!(to_hms(12.0 * hour + 1.5 * second, 0.5 * second), "12h00m01.5s");
+        // Rounding up to 24h wraps to 0h
+        assert_eq!(to_hms(24.0 * hour - 0.1 * second, second), "0h00m00s");
+
+        let deg = 1_f64.to_radians();
+        assert_eq!(to_dms(-30.25 * deg, deg / 4.0, true), "-30°15'");
+        assert_eq!(to_dms(45.0 * deg, 15.0 * deg, true), "+45°");
+        assert_eq!(to_dms(-(1.0 / 7200.0) * deg, deg / 3600.0, false), "0°00'01''");
+
+        assert_eq!(to_decimal(12.3456 * deg, 2), "12.35°");
+        assert_eq!(to_galactic(120.0 * deg, 0, 'l'), "120l");
+        assert_eq!(to_galactic(-30.0 * deg, 0, 'b'), "-30b");
+    }
+}
@@ -34,7 +34,6 @@ pub enum PoleContained {
 }
 use crate::math::lonlat::LonLatT;
 //use cgmath::Vector2;
-use crate::CameraViewPort;
 impl FieldOfViewType {
     pub fn new_polygon(vertices: &[Vector4<f64>], control_point: &Vector4<f64>) -> FieldOfViewType {
         let (vertices, (lon, lat)): (Vec<_>, (Vec<_>, Vec<_>)) = vertices
@@ -86,7 +85,8 @@ impl FieldOfViewType {
     pub fn intersect_meridian<LonT: Into<Rad<f64>>>(
         &self,
         lon: LonT,
-        camera: &CameraViewPort,
+        // The center of the view, given in the frame of the field of view
+        center: &Vector4<f64>,
     ) -> Option<Vector3<f64>> {
         let Rad::<f64>(lon) = lon.into();
 
@@ -96,7 +96,6 @@ impl FieldOfViewType {
                 // We do an approx saying allsky fovs intersect all meridian
                 // but this is not true for example for the orthographic projection
                 // Some meridians may not be visible
-                let center = camera.get_center();
                 let pos: Vector3<f64> = LonLatT::new(Angle(lon), center.lat()).vector();
                 Some(pos)
             }
@@ -115,7 +114,6 @@ impl FieldOfViewType {
                     .or_else(|| {
                         // If no intersection has been found, e.g. because the
                         // great circle is fully contained in the bounding box
-                        let center = camera.get_center();
                         let pos: Vector3<f64> = LonLatT::new(Angle(lon), center.lat()).vector();
                         Some(pos)
                     })
@@ -126,13 +124,13 @@ impl FieldOfViewType {
     pub fn intersect_parallel<LatT: Into<Rad<f64>>>(
         &self,
         lat: LatT,
-        camera: &CameraViewPort,
+        // The center of the view, given in the frame of the field of view
+        center: &Vector4<f64>,
     ) -> Option<Vector3<f64>> {
         let Rad::<f64>(lat) = lat.into();
 
         match self {
             FieldOfViewType::Allsky => {
-                let center = camera.get_center();
                 let pos: Vector3<f64> = LonLatT::new(center.lon(), Angle(lat)).vector();
                 Some(pos)
             }
@@ -145,7 +143,6 @@ impl FieldOfViewType {
                         .or_else(|| {
                             // If no intersection has been found, e.g. because the
                             // great circle is fully contained in the bounding box
-                            let center = camera.get_center();
                             let pos: Vector3<f64> = LonLatT::new(center.lon(), Angle(lat)).vector();
                             Some(pos)
                         })
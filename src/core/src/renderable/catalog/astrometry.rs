@@ -0,0 +1,218 @@
+//! Propagation of the positions of the sources to another epoch
+//!
+//! The positions are propagated rigorously, i.e. along a straight line in space at
+//! constant velocity, following the Hipparcos and Gaia documentation (Vol. 1, §1.2.8).
+use super::source::Source;
+use crate::math::angle::Angle;
+use crate::table::{find_astrometry_columns, Attributes, Column, Field};
+
+use cgmath::{InnerSpace, Vector3};
+
+/// Default epoch of the positions, in Julian years
+pub const DEFAULT_REFERENCE_EPOCH: f64 = 2000.0;
+
+// Astronomical unit in km.year/s
+const AU_KM_YR_PER_S: f64 = 4.740470446;
+const MAS_TO_RAD: f64 = std::f64::consts::PI / (180.0 * 3600.0 * 1000.0);
+
+/// The astrometric parameters of the sources, in the order of the sources
+///
+/// Proper motions and parallaxes are in mas(/yr), radial velocities in km/s
+#[derive(Debug, Clone)]
+pub struct Astrometry {
+    pmra: Box<[f64]>,
+    pmdec: Box<[f64]>,
+    parallax: Option<Box<[f64]>>,
+    radial_velocity: Option<Box<[f64]>>,
+    ref_epoch: Option<Box<[f64]>>,
+}
+
+// The values of a numeric column converted to mas
+fn column_in_mas(attributes: &Attributes, idx: usize) -> Option<Box<[f64]>> {
+    let values = match &attributes.columns()[idx] {
+        Column::Numeric(values) => values,
+        _ => return None,
+    };
+
+    let scale = unit_to_mas(&attributes.fields()[idx]);
+    Some(values.iter().map(|v| v * scale).collect())
+}
+
+fn unit_to_mas(field: &Field) -> f64 {
+    let unit = field.unit.as_deref().unwrap_or("mas").trim().to_lowercase();
+    if unit.starts_with("mas") {
+        1.0
+    } else if unit.starts_with("arcsec") || unit.starts_with("\"") {
+        1e3
+    } else if unit.starts_with("deg") {
+        3.6e6
+    } else {
+        1.0
+    }
+}
+
+fn numeric_column(attributes: &Attributes, idx: usize) -> Option<Box<[f64]>> {
+    match &attributes.columns()[idx] {
+        Column::Numeric(values) => Some(values.clone().into_boxed_slice()),
+        _ => None,
+    }
+}
+
+impl Astrometry {
+    /// Get the astrometry from the columns of a table if it has proper motions
+    pub fn from_attributes(attributes: &Attributes, num_sources: usize) -> Option<Self> {
+        if attributes.num_rows() != num_sources {
+            return None;
+        }
+
+        let columns = find_astrometry_columns(attributes.fields())?;
+        Some(Self {
+            pmra: column_in_mas(attributes, columns.pmra)?,
+            pmdec: column_in_mas(attributes, columns.pmdec)?,
+            parallax: columns.parallax.and_then(|idx| column_in_mas(attributes, idx)),
+            radial_velocity: columns.radial_velocity.and_then(|idx| numeric_column(attributes, idx)),
+            ref_epoch: columns.ref_epoch.and_then(|idx| numeric_column(attributes, idx)),
+        })
+    }
+
+    /// Propagate the position of the i-th source
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Its position at its reference epoch
+    /// * `idx` - Its index
+    /// * `ref_epoch` - The reference epoch used if not given for the source
+    /// * `epoch` - The target epoch
+    pub fn propagate(&self, source: &Source, idx: usize, ref_epoch: f64, epoch: f64) -> Source {
+        let (pmra, pmdec) = (self.pmra[idx], self.pmdec[idx]);
+        if !pmra.is_finite() || !pmdec.is_finite() {
+            return source.clone();
+        }
+
+        let ref_epoch = self
+            .ref_epoch
+            .as_ref()
+            .map(|e| e[idx])
+            .filter(|e| e.is_finite())
+            .unwrap_or(ref_epoch);
+        let t = epoch - ref_epoch;
+
+        let parallax = self.parallax.as_ref().map(|p| p[idx]).filter(|p| p.is_finite()).unwrap_or(0.0);
+        let radial_velocity = self
+            .radial_velocity
+            .as_ref()
+            .map(|v| v[idx])
+            .filter(|v| v.is_finite())
+            .unwrap_or(0.0);
+
+        let (lon, lat) = source.lonlat();
+        let (lon, lat) = (lon as f64, lat as f64);
+        let r = Vector3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin());
+        // Local triad in the directions of increasing ra and dec
+        let p = Vector3::new(-lon.sin(), lon.cos(), 0.0);
+        let q = Vector3::new(-lat.sin() * lon.cos(), -lat.sin() * lon.sin(), lat.cos());
+
+        // Proper motion vector and radial proper motion, in rad/yr
+        let pm = (p * pmra + q * pmdec) * MAS_TO_RAD;
+        let pm_r = radial_velocity * parallax * MAS_TO_RAD / AU_KM_YR_PER_S;
+
+        let f = 1.0 / (1.0 + 2.0 * pm_r * t + (pm.magnitude2() + pm_r * pm_r) * t * t).sqrt();
+        let u = (r * (1.0 + pm_r * t) + pm * t) * f;
+
+        let u = u.normalize();
+        let (lon, lat) = (u.y.atan2(u.x), u.z.asin());
+        Source::new(Angle(lon as f32), Angle(lat as f32))
+    }
+}
+
+/// What is needed to move the sources of a catalog to a target epoch
+pub struct Propagation {
+    /// The positions of the sources at their reference epoch
+    pub reference: Vec<Source>,
+    astrometry: Astrometry,
+    ref_epoch: f64,
+    // None to go back to the reference positions
+    epoch: Option<f64>,
+    /// The state of the catalog the propagation has been computed from
+    pub generation: u32,
+}
+
+impl Propagation {
+    pub fn new(
+        reference: Vec<Source>,
+        astrometry: Astrometry,
+        ref_epoch: f64,
+        epoch: Option<f64>,
+        generation: u32,
+    ) -> Self {
+        Self {
+            reference,
+            astrometry,
+            ref_epoch,
+            epoch,
+            generation,
+        }
+    }
+
+    /// Propagate the sources of a range of indices
+    pub fn propagate(&self, range: std::ops::Range<usize>) -> impl Iterator<Item = Source> + '_ {
+        range.map(move |idx| match self.epoch {
+            Some(epoch) => self
+                .astrometry
+                .propagate(&self.reference[idx], idx, self.ref_epoch, epoch),
+            None => self.reference[idx].clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Astrometry;
+    use crate::table::{Field, TableBuilder, Value};
+
+    fn field(name: &str, ucd: &str, unit: &str) -> Field {
+        Field {
+            name: name.to_string(),
+            ucd: Some(ucd.to_string()),
+            unit: Some(unit.to_string()),
+            numeric: true,
+        }
+    }
+
+    #[test]
+    fn barnard_star() {
+        // Gaia DR3 astrometry of the Barnard's star
+        let fields = vec![
+            field("ra", "pos.eq.ra;meta.main", "deg"),
+            field("dec", "pos.eq.dec;meta.main", "deg"),
+            field("pmra", "pos.pm;pos.eq.ra", "mas/yr"),
+            field("pmdec", "pos.pm;pos.eq.dec", "mas/yr"),
+            field("parallax", "pos.parallax.trig", "mas"),
+        ];
+        let mut table = TableBuilder::with_position_columns(fields, 0, 1);
+        table
+            .push_row(
+                [269.44850252543836, 4.739420051112412, -801.551, 10362.394, 546.976]
+                    .iter()
+                    .map(|&v| Value::Numeric(v))
+                    .collect(),
+            )
+            .unwrap();
+        let (sources, attributes) = table.build();
+
+        let astrometry = Astrometry::from_attributes(&attributes, sources.len()).unwrap();
+        let propagated = astrometry.propagate(&sources[0], 0, 2016.0, 2026.0);
+        let (lon, lat) = propagated.lonlat();
+
+        // About 103.6 arcsec to the north and 8 arcsec to the west after 10 years
+        let (lon0, lat0) = sources[0].lonlat();
+        let dec_shift = (lat - lat0).to_degrees() as f64 * 3600.0;
+        let ra_shift = (lon - lon0).to_degrees() as f64 * 3600.0 * (lat0 as f64).cos();
+        assert!((dec_shift - 103.6).abs() < 0.5, "{}", dec_shift);
+        assert!((ra_shift + 8.0).abs() < 0.5, "{}", ra_shift);
+
+        // Back to the reference epoch
+        let (lon, lat) = astrometry.propagate(&propagated, 0, 2026.0, 2016.0).lonlat();
+        assert!((lon - lon0).abs() < 1e-6 && (lat - lat0).abs() < 1e-6);
+    }
+}
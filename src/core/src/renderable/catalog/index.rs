@@ -95,3 +95,23 @@ impl SourceIndices {
         &sources[idx_f32]
     }
 }
+
+/// Sort the sources by the HEALPix cell of depth 7 containing them
+///
+/// Returns the permutation to apply to the sources (and to their attributes)
+/// so that a `SourceIndices` can be built from them. The sources sharing a
+/// same cell keep their relative order.
+pub fn sort_by_hpx_cell(sources: &[Source]) -> Vec<u32> {
+    let hashes = sources
+        .iter()
+        .map(|s| {
+            let (lon, lat) = s.lonlat();
+            cdshealpix::nested::hash(7, lon as f64, lat as f64)
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows = (0..(sources.len() as u32)).collect::<Vec<_>>();
+    rows.sort_by_key(|&r| hashes[r as usize]);
+
+    rows
+}
@@ -0,0 +1,133 @@
+//! Kernels of the catalog heatmaps
+use crate::math::angle::{Angle, ArcSec};
+
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KernelType {
+    // The kernel texture, of constant size on the screen
+    Default,
+    Gaussian,
+    TopHat,
+    Epanechnikov,
+}
+
+impl TryFrom<&str> for KernelType {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name.to_lowercase().as_str() {
+            "default" => Ok(KernelType::Default),
+            "gaussian" => Ok(KernelType::Gaussian),
+            "tophat" | "top-hat" => Ok(KernelType::TopHat),
+            "epanechnikov" => Ok(KernelType::Epanechnikov),
+            _ => Err(format!("{} kernel not found. Expected: default, gaussian, tophat or epanechnikov", name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Kernel {
+    pub kind: KernelType,
+    /// The standard deviation of a gaussian kernel, the radius of the other ones
+    pub width: Angle<f64>,
+}
+
+impl Default for Kernel {
+    fn default() -> Self {
+        Self {
+            kind: KernelType::Default,
+            width: ArcSec(0.0).into(),
+        }
+    }
+}
+
+impl Kernel {
+    pub fn new(kind: KernelType, width: Angle<f64>) -> Result<Self, String> {
+        if kind != KernelType::Default && (width.0.is_nan() || width.0 <= 0.0) {
+            return Err("The width of the kernel must be positive".to_string());
+        }
+
+        Ok(Self { kind, width })
+    }
+
+    /// Identifier of the kernel in the catalog shaders
+    pub fn shader_id(&self) -> i32 {
+        match self.kind {
+            KernelType::Default => 0,
+            KernelType::Gaussian => 1,
+            KernelType::TopHat => 2,
+            KernelType::Epanechnikov => 3,
+        }
+    }
+
+    /// Radius of the billboard drawn for a source
+    pub fn support(&self) -> Angle<f64> {
+        match self.kind {
+            // The gaussian is truncated at 3 sigmas
+            KernelType::Gaussian => self.width * 3.0,
+            _ => self.width,
+        }
+    }
+
+    /// Width of the kernel in arcseconds
+    pub fn width_arcsec(&self) -> f64 {
+        self.width.0.to_degrees() * 3600.0
+    }
+
+    /// Value at its center of the kernel normalized over the sky, per square arcsecond
+    pub fn peak(&self) -> f64 {
+        let w2 = self.width_arcsec() * self.width_arcsec();
+        match self.kind {
+            KernelType::Default => 1.0,
+            // Normalized over its truncated support
+            KernelType::Gaussian => 1.0 / (2.0 * std::f64::consts::PI * w2 * (1.0 - (-4.5_f64).exp())),
+            KernelType::TopHat => 1.0 / (std::f64::consts::PI * w2),
+            KernelType::Epanechnikov => 2.0 / (std::f64::consts::PI * w2),
+        }
+    }
+}
+
+/// How the density is mapped to the colormap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scaling {
+    /// The maximum density is estimated from the sources in the view
+    Auto,
+    /// The density (in weight per square arcsecond) mapped to the top of the colormap
+    Fixed(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Kernel, KernelType};
+    use crate::math::angle::ArcSec;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn kernels_are_normalized() {
+        // Integrate the kernels over the plane, in square arcseconds
+        for kind in ["gaussian", "tophat", "epanechnikov"] {
+            let kernel = Kernel::new(KernelType::try_from(kind).unwrap(), ArcSec(2.0).into()).unwrap();
+            let support = kernel.support().0.to_degrees() * 3600.0;
+
+            let n = 400;
+            let dr = support / n as f64;
+            let integral = (0..n)
+                .map(|i| {
+                    let r = (i as f64 + 0.5) * dr;
+                    let u = r / support;
+                    let k = match kernel.kind {
+                        KernelType::Gaussian => (-0.5 * (3.0 * u) * (3.0 * u)).exp(),
+                        KernelType::TopHat => 1.0,
+                        _ => 1.0 - u * u,
+                    };
+                    kernel.peak() * k * 2.0 * std::f64::consts::PI * r * dr
+                })
+                .sum::<f64>();
+
+            assert!((integral - 1.0).abs() < 1e-2, "{}: {}", kind, integral);
+        }
+
+        assert!(Kernel::new(KernelType::Gaussian, ArcSec(0.0).into()).is_err());
+    }
+}
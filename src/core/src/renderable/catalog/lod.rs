@@ -0,0 +1,315 @@
+//! Level of detail of the catalogs
+//!
+//! The sources are aggregated in each HEALPix cell up to the depth 7 of the
+//! `SourceIndices`. When there are too many sources in the view, the sparsest cells are
+//! refined first so that the densest cells are the ones drawn through their aggregate
+//! (i.e. the centroid weighted by the number of sources they contain, plus their
+//! brightest member) instead of their individual sources.
+use super::index::SourceIndices;
+use super::source::Source;
+use crate::healpix::cell::HEALPixCell;
+
+use cgmath::{InnerSpace, Vector3};
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::Range;
+
+/// Default maximum number of instances drawn for a catalog
+pub const DEFAULT_LOD_BUDGET: usize = 50000;
+
+const MAX_DEPTH: u8 = 7;
+
+#[derive(Debug, Clone)]
+pub struct Aggregate {
+    /// Number of sources in the cell
+    pub count: u32,
+    /// Sum of the weights of the sources
+    pub weight: f64,
+    /// Index of the brightest source of the cell
+    pub brightest: u32,
+    // Magnitude of the brightest source, NaN if not known
+    brightest_mag: f64,
+    brightest_weight: f64,
+    // Sum of the weighted positions of the sources
+    sum: Vector3<f64>,
+}
+
+impl Aggregate {
+    /// Weighted centroid of the sources
+    pub fn centroid(&self) -> Source {
+        let c = if self.sum.magnitude2() > 0.0 {
+            self.sum.normalize()
+        } else {
+            self.sum
+        };
+
+        Source {
+            x: c.x as f32,
+            y: c.y as f32,
+            z: c.z as f32,
+        }
+    }
+
+    pub fn brightest_weight(&self) -> f64 {
+        self.brightest_weight
+    }
+
+    /// Sum of the weights of the sources other than the brightest one
+    pub fn others_weight(&self) -> f64 {
+        self.weight - self.brightest_weight
+    }
+
+    // Number of instances drawn for the aggregate:
+    // the brightest source and the centroid of the others
+    fn num_instances(&self) -> usize {
+        self.count.min(2) as usize
+    }
+
+    fn merge(&mut self, other: &Aggregate) {
+        self.count += other.count;
+        self.weight += other.weight;
+        self.sum += other.sum;
+
+        if self.brightest_mag.is_nan() || other.brightest_mag < self.brightest_mag {
+            self.brightest = other.brightest;
+            self.brightest_mag = other.brightest_mag;
+            self.brightest_weight = other.brightest_weight;
+        }
+    }
+}
+
+/// Number of cells at depth 7
+pub const NUM_CELLS: u64 = 12 << (2 * MAX_DEPTH as u64);
+
+/// Compute the aggregates of the cells at depth 7, a range of cells at a time
+#[derive(Default)]
+pub struct AggregatesBuilder(HashMap<u64, Aggregate>);
+
+impl AggregatesBuilder {
+    /// Add the aggregates of a range of cells at depth 7
+    ///
+    /// # Arguments
+    ///
+    /// * `cells` - The indices of the cells, lower than `NUM_CELLS`
+    /// * `weights` - The weight of each source, 1 if not given
+    /// * `magnitudes` - The magnitude of each source used to find the brightest ones
+    pub fn add_cells(
+        &mut self,
+        cells: Range<u64>,
+        sources: &[Source],
+        indices: &SourceIndices,
+        weights: Option<&[f64]>,
+        magnitudes: Option<&[f64]>,
+    ) {
+        for idx in cells {
+            let range = indices.get_source_indices(&HEALPixCell(MAX_DEPTH, idx));
+            if range.is_empty() {
+                continue;
+            }
+
+            let mut aggregate = Aggregate {
+                count: range.end - range.start,
+                weight: 0.0,
+                brightest: range.start,
+                brightest_mag: f64::NAN,
+                brightest_weight: 0.0,
+                sum: Vector3::new(0.0, 0.0, 0.0),
+            };
+            for i in range {
+                let s = &sources[i as usize];
+                let w = weights.map(|w| w[i as usize]).filter(|w| w.is_finite()).unwrap_or(1.0);
+
+                aggregate.weight += w;
+                aggregate.sum += Vector3::new(s.x as f64, s.y as f64, s.z as f64) * w;
+
+                let mag = magnitudes.map(|m| m[i as usize]).unwrap_or(f64::NAN);
+                if aggregate.brightest_mag.is_nan() || mag < aggregate.brightest_mag {
+                    aggregate.brightest = i;
+                    aggregate.brightest_mag = mag;
+                    aggregate.brightest_weight = w;
+                }
+            }
+
+            self.0.insert(idx, aggregate);
+        }
+    }
+
+    /// Merge the aggregates of the cells up to the depth 0
+    pub fn build(self) -> Aggregates {
+        let mut depths = vec![HashMap::new(); MAX_DEPTH as usize + 1];
+        depths[MAX_DEPTH as usize] = self.0;
+
+        // The aggregates of a cell are the merge of the ones of its children
+        for depth in (0..MAX_DEPTH as usize).rev() {
+            let mut parents: HashMap<u64, Aggregate> = HashMap::new();
+            for (idx, aggregate) in depths[depth + 1].iter() {
+                parents
+                    .entry(idx >> 2)
+                    .and_modify(|p| p.merge(aggregate))
+                    .or_insert_with(|| aggregate.clone());
+            }
+
+            depths[depth] = parents;
+        }
+
+        Aggregates(depths.into_boxed_slice())
+    }
+}
+
+/// The aggregates of the non empty cells from the depth 0 to 7
+pub struct Aggregates(Box<[HashMap<u64, Aggregate>]>);
+
+impl Aggregates {
+    /// Compute the aggregates of sources sorted as for `SourceIndices`
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - The weight of each source, 1 if not given
+    /// * `magnitudes` - The magnitude of each source used to find the brightest ones
+    pub fn new(
+        sources: &[Source],
+        indices: &SourceIndices,
+        weights: Option<&[f64]>,
+        magnitudes: Option<&[f64]>,
+    ) -> Self {
+        let mut builder = AggregatesBuilder::default();
+        builder.add_cells(0..NUM_CELLS, sources, indices, weights, magnitudes);
+
+        builder.build()
+    }
+
+    /// Get the aggregate of a cell of depth <= 7
+    pub fn get(&self, cell: &HEALPixCell) -> Option<&Aggregate> {
+        self.0.get(cell.depth() as usize)?.get(&cell.idx())
+    }
+
+    /// Select the level of detail of the cells in the view
+    ///
+    /// The cells are refined, the sparsest first, as long as the number of instances
+    /// to draw remains lower than the budget, so that the densest cells are the ones
+    /// left aggregated.
+    /// Returns the cells to draw through their aggregate and the cells
+    /// of depth 7 whose sources are all drawn.
+    pub fn select(&self, cells: &[HEALPixCell], budget: usize) -> (Vec<HEALPixCell>, Vec<HEALPixCell>) {
+        let mut heap = BinaryHeap::new();
+        let mut num_instances = 0;
+        for cell in cells {
+            if let Some(aggregate) = self.get(cell) {
+                heap.push((Reverse(aggregate.count), *cell));
+                num_instances += aggregate.num_instances();
+            }
+        }
+
+        let mut aggregated = vec![];
+        let mut full = vec![];
+        while let Some((Reverse(count), cell)) = heap.pop() {
+            let aggregate_instances = self.get(&cell).map(|a| a.num_instances()).unwrap_or(0);
+
+            if cell.depth() >= MAX_DEPTH {
+                if num_instances - aggregate_instances + count as usize <= budget {
+                    num_instances = num_instances - aggregate_instances + count as usize;
+                    full.push(cell);
+                } else {
+                    aggregated.push(cell);
+                }
+            } else {
+                let children = cell
+                    .get_children_cells(1)
+                    .filter_map(|c| self.get(&c).map(|a| (a.count, c, a.num_instances())))
+                    .collect::<Vec<_>>();
+                let children_instances = children.iter().map(|(_, _, n)| n).sum::<usize>();
+
+                if num_instances - aggregate_instances + children_instances <= budget {
+                    num_instances = num_instances - aggregate_instances + children_instances;
+                    heap.extend(children.into_iter().map(|(count, c, _)| (Reverse(count), c)));
+                } else {
+                    aggregated.push(cell);
+                }
+            }
+        }
+
+        (aggregated, full)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Aggregates;
+    use crate::healpix::cell::HEALPixCell;
+    use crate::math::angle::ArcDeg;
+    use crate::renderable::catalog::{index::SourceIndices, Source};
+
+    fn sources(lonlat: &[(f32, f32)]) -> Vec<Source> {
+        let sources = lonlat
+            .iter()
+            .map(|&(lon, lat)| Source::new(ArcDeg(lon).into(), ArcDeg(lat).into()))
+            .collect::<Vec<_>>();
+        let rows = super::super::index::sort_by_hpx_cell(&sources);
+
+        rows.iter().map(|&r| sources[r as usize].clone()).collect()
+    }
+
+    #[test]
+    fn aggregates_follow_the_budget() {
+        let sources = sources(&[(10.0, 10.0), (10.01, 10.01), (10.02, 10.0), (200.0, -40.0)]);
+        let indices = SourceIndices::new(&sources);
+        // The brightest source of the dense cell is at (10.01, 10.01)
+        let magnitudes = sources
+            .iter()
+            .map(|s| if (s.lonlat().1.to_degrees() - 10.01).abs() < 1e-3 { 9.0 } else { 12.0 })
+            .collect::<Vec<_>>();
+        let aggregates = Aggregates::new(&sources, &indices, None, Some(&magnitudes));
+
+        let allsky = (0..12).map(|idx| HEALPixCell(0, idx)).collect::<Vec<_>>();
+        let total = allsky
+            .iter()
+            .filter_map(|c| aggregates.get(c))
+            .map(|a| a.count)
+            .sum::<u32>();
+        assert_eq!(total, 4);
+
+        // All the sources fit in the budget
+        let (aggregated, full) = aggregates.select(&allsky, 10);
+        assert!(aggregated.is_empty());
+        assert_eq!(full.len(), 2);
+
+        // The dense cell is aggregated
+        let (aggregated, full) = aggregates.select(&allsky, 3);
+        assert_eq!(aggregated.len(), 1);
+        assert_eq!(full.len(), 1);
+        let dense = aggregates.get(&aggregated[0]).unwrap();
+        assert_eq!(dense.count, 3);
+        assert_eq!(magnitudes[dense.brightest as usize], 9.0);
+
+        let (lon, lat) = dense.centroid().lonlat();
+        assert!((lon.to_degrees() - 10.01).abs() < 1e-2 && (lat.to_degrees() - 10.0).abs() < 1e-2);
+
+        // 4 sources in two cells of depth 7 and 3 sources in a single one
+        let clusters = self::sources(&[
+            (10.0, 10.0),
+            (10.01, 10.01),
+            (11.0, 10.0),
+            (11.01, 10.0),
+            (200.0, -40.0),
+            (200.01, -40.0),
+            (200.02, -40.01),
+        ]);
+        let indices = SourceIndices::new(&clusters);
+        let aggregates = Aggregates::new(&clusters, &indices, None, None);
+
+        // Both clusters are drawn through their aggregate (2 instances each).
+        // Refining the dense one costs 2 more instances, the sparse one only 1,
+        // the budget allows only one of them: the densest cell stays aggregated
+        let (aggregated, full) = aggregates.select(&allsky, 6);
+
+        let counts = |cells: &[HEALPixCell]| {
+            cells
+                .iter()
+                .map(|c| aggregates.get(c).unwrap().count)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(counts(&aggregated), vec![4]);
+        assert_eq!(counts(&full), vec![3]);
+    }
+}
@@ -3,14 +3,15 @@ use crate::ShaderManager;
 
 use al_api::resources::Resources;
 
+use al_core::image::format::RGBA16F;
 use al_core::FrameBufferObject;
 use al_core::{
     Texture2D, VecData, VertexArrayObject, WebGlContext,
 };
 use al_core::Colormaps;
-use al_core::colormap::Colormap;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
 use std::iter::FromIterator;
 use web_sys::WebGl2RenderingContext;
 use crate::ProjectionType;
@@ -18,12 +19,16 @@ use crate::ProjectionType;
 #[derive(Debug)]
 pub enum Error {
     CatalogNotPresent { message: String },
+    CatalogAlreadyPresent { message: String },
+    InvalidColumn { message: String },
 }
 use wasm_bindgen::JsValue;
 impl From<Error> for JsValue {
     fn from(err: Error) -> Self {
         match err {
             Error::CatalogNotPresent { message } => message.into(),
+            Error::CatalogAlreadyPresent { message } => message.into(),
+            Error::InvalidColumn { message } => message.into(),
         }
     }
 }
@@ -39,6 +44,16 @@ pub struct Manager {
 
     catalogs: HashMap<String, Catalog>,
     kernel_size: Vector2<f32>,
+
+    // The cells in the field of view used to select the sources to render
+    view: HEALPixCellsInView,
+
+    // Catalogs whose sources are given by HiPS catalog tiles
+    progressive: HashMap<String, ProgressiveCatalog>,
+    // Epoch the catalogs are propagated to, their reference one if None
+    epoch: Option<f64>,
+    // Number of merges of appended sources spawned, giving their ids
+    num_merges: u64,
 }
 
 impl Manager {
@@ -139,7 +154,15 @@ impl Manager {
         let catalogs = HashMap::new();
         let kernel_size = Vector2::new(0.0, 0.0);
 
-        let fbo = FrameBufferObject::new(gl, 768, 768).unwrap_abort();
+        // The kernels are summed into a float target so that the contributions
+        // lower than 1/255 of the sparse sources are not rounded to 0
+        let fbo = FrameBufferObject::with_format::<RGBA16F>(gl, 768, 768)
+            .or_else(|_| FrameBufferObject::new(gl, 768, 768))
+            .unwrap_abort();
+        let view = HEALPixCellsInView::new();
+        let progressive = HashMap::new();
+        let epoch = None;
+        let num_merges = 0;
 
         let gl = gl.clone();
         let mut manager = Manager {
@@ -152,6 +175,10 @@ impl Manager {
 
             catalogs,
             kernel_size,
+            view,
+            progressive,
+            epoch,
+            num_merges,
         };
 
         manager.set_kernel_size(camera);
@@ -164,14 +191,18 @@ impl Manager {
         &mut self,
         name: String,
         sources: Box<[Source]>,
-        colormap: Colormap,
+        attributes: Attributes,
+        colormap: String,
         _shaders: &mut ShaderManager,
-        _camera: &CameraViewPort,
-        _view: &HEALPixCellsInView,
+        camera: &CameraViewPort,
     ) {
         // Create the HashMap storing the source indices with respect to the
         // HEALPix cell at depth 7 in which they are contained
-        let catalog = Catalog::new::<P>(&self.gl, colormap, sources);
+        let mut catalog = Catalog::new::<P>(&self.gl, colormap, sources, attributes);
+
+        // Select the sources in the current view
+        self.view.refresh(camera.get_tile_depth().min(7), CooSystem::ICRSJ2000, camera);
+        catalog.update(&Self::cells_in_view(camera, &self.view));
 
         // Update the number of sources loaded
         //self.num_sources += num_instances_in_catalog as usize;
@@ -185,6 +216,259 @@ impl Manager {
         // at depth 7
     }
 
+    // Add a catalog whose sources are loaded from the tiles of a HiPS catalog
+    pub fn add_progressive_catalog<P: Projection>(
+        &mut self,
+        name: String,
+        catalog: ProgressiveCatalog,
+        colormap: String,
+        shaders: &mut ShaderManager,
+        camera: &CameraViewPort,
+    ) {
+        // The catalog is empty until its first tiles are received
+        self.add_catalog::<P>(
+            name.clone(),
+            vec![].into_boxed_slice(),
+            Attributes::default(),
+            colormap,
+            shaders,
+            camera,
+        );
+        self.progressive.insert(name, catalog);
+    }
+
+    /// Get the queries of the HiPS catalog tiles needed for the current view
+    pub fn get_catalog_tiles_to_fetch(&mut self, camera: &CameraViewPort) -> Vec<query::CatalogTile> {
+        self.progressive
+            .values_mut()
+            .flat_map(|catalog| catalog.refresh(camera))
+            .collect()
+    }
+
+    pub fn add_catalog_tile(&mut self, tile: CatalogTile) {
+        let table = tile.table.lock().unwrap_abort();
+
+        for catalog in self.progressive.values_mut() {
+            if catalog.get_hips_url() == tile.get_hips_url() {
+                catalog.add_tile(tile.cell, table.clone());
+            }
+        }
+    }
+
+    /// Update the sources of the progressive catalogs whose tiles have changed
+    ///
+    /// The sources of the new tiles are queued to be merged with the ones of the catalog
+    ///
+    /// Returns the names of the catalogs whose sources have been replaced and the
+    /// names of the ones to which sources have been appended
+    pub fn refresh_progressive_catalogs(&mut self, camera: &CameraViewPort) -> (Vec<String>, Vec<String>) {
+        let mut replaced = vec![];
+        let mut appended = vec![];
+        let cells = Self::cells_in_view(camera, &self.view);
+
+        for (name, progressive) in self.progressive.iter_mut() {
+            if let (Some(update), Some(catalog)) = (progressive.take_update(), self.catalogs.get_mut(name)) {
+                match update {
+                    TilesUpdate::Replace(sources, attributes) => {
+                        catalog.set_sources(sources.into_boxed_slice(), attributes, &cells);
+                        replaced.push(name.clone());
+                    }
+                    TilesUpdate::Append(sources, attributes) => {
+                        catalog.append_sources(sources.into_boxed_slice(), attributes);
+                        appended.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        (replaced, appended)
+    }
+
+    /// Remove a catalog
+    ///
+    /// Its GPU buffers are released once the catalog is dropped
+    pub fn remove_catalog(&mut self, name: &str) -> Result<(), Error> {
+        self.progressive.remove(name);
+        self.catalogs
+            .remove(name)
+            .map(|_| ())
+            .ok_or(Error::CatalogNotPresent {
+                message: format!("{} catalog is not present!", name),
+            })
+    }
+
+    /// Check no catalog is named `name` before adding one
+    pub fn check_catalog_absent(&self, name: &str) -> Result<(), Error> {
+        if self.catalogs.contains_key(name) {
+            Err(Error::CatalogAlreadyPresent {
+                message: format!("{} catalog is already present!", name),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn rename_catalog(&mut self, name: &str, new_name: String) -> Result<(), Error> {
+        self.check_catalog_absent(&new_name)?;
+
+        let mut catalog = self.catalogs.remove(name).ok_or(Error::CatalogNotPresent {
+            message: format!("{} catalog is not present!", name),
+        })?;
+        // The merge in progress is given by the former name, it is done again
+        catalog.merging = None;
+        self.catalogs.insert(new_name.clone(), catalog);
+
+        if let Some(progressive) = self.progressive.remove(name) {
+            self.progressive.insert(new_name, progressive);
+        }
+
+        Ok(())
+    }
+
+    /// Replace the sources of a catalog
+    ///
+    /// The sources must be sorted by their HEALPix cell at depth 7 (see `BuildCatalogIndex`)
+    pub fn set_catalog_sources(
+        &mut self,
+        name: &str,
+        sources: Box<[Source]>,
+        attributes: Attributes,
+        camera: &CameraViewPort,
+    ) -> Result<(), Error> {
+        let cells = self.refresh_cells_in_view(camera);
+        self.get_mut_catalog(name)?.set_sources(sources, attributes, &cells);
+
+        Ok(())
+    }
+
+    /// Add new sources to a catalog
+    ///
+    /// The sources must be sorted by their HEALPix cell at depth 7 (see `BuildCatalogIndex`).
+    /// They are merged with the ones of the catalog by the merges given by `next_catalog_merge`.
+    pub fn append_catalog_sources(
+        &mut self,
+        name: &str,
+        sources: Box<[Source]>,
+        attributes: Attributes,
+    ) -> Result<(), Error> {
+        self.get_mut_catalog(name)?.append_sources(sources, attributes);
+
+        Ok(())
+    }
+
+    /// Get the next merge of appended sources to compute for a catalog
+    ///
+    /// Returns `None` if a merge is already in progress or if there is nothing to merge
+    pub fn next_catalog_merge(&mut self, name: &str) -> Option<Merge> {
+        let id = self.num_merges;
+        let merge = self.catalogs.get_mut(name)?.next_merge(id);
+        if merge.is_some() {
+            self.num_merges += 1;
+        }
+
+        merge
+    }
+
+    /// Replace the sources of a catalog by their merge with appended sources
+    ///
+    /// Returns false if the catalog has changed since the merge has been spawned
+    pub fn set_catalog_merged_sources(
+        &mut self,
+        name: &str,
+        merge: Merge,
+        camera: &CameraViewPort,
+    ) -> Result<bool, Error> {
+        let cells = self.refresh_cells_in_view(camera);
+        Ok(self.get_mut_catalog(name)?.set_merged_sources(merge, &cells))
+    }
+
+    /// Replace the sources of a catalog by their propagated positions
+    ///
+    /// Returns false if the catalog has changed since the propagation has been spawned
+    pub fn set_catalog_propagated_sources(
+        &mut self,
+        name: &str,
+        reference: Box<[Source]>,
+        sources: Box<[Source]>,
+        rows: &[u32],
+        generation: u32,
+        camera: &CameraViewPort,
+    ) -> Result<bool, Error> {
+        let cells = self.refresh_cells_in_view(camera);
+        Ok(self
+            .get_mut_catalog(name)?
+            .set_propagated_sources(reference, sources, rows, generation, &cells))
+    }
+
+    /// Set the epoch the sources of the catalogs are propagated to
+    pub fn set_epoch(&mut self, epoch: Option<f64>) {
+        self.epoch = epoch;
+    }
+
+    pub fn get_epoch(&self) -> Option<f64> {
+        self.epoch
+    }
+
+    /// Get a subset of the sources of a catalog along with their attributes
+    pub fn get_catalog_subset(
+        &mut self,
+        name: &str,
+        subset: Subset,
+        camera: &CameraViewPort,
+    ) -> Result<(Vec<Source>, Attributes), Error> {
+        self.refresh_cells_in_view(camera);
+        let view = self.view.get_coverage();
+
+        Ok(self.get_catalog(name)?.get_subset(subset, view))
+    }
+
+    pub fn get_catalog_names(&self) -> Vec<String> {
+        self.catalogs.keys().cloned().collect()
+    }
+
+    pub fn set_catalog_lod_budget(&mut self, name: &str, budget: usize, camera: &CameraViewPort) -> Result<(), Error> {
+        let cells = self.refresh_cells_in_view(camera);
+        self.get_mut_catalog(name)?.set_lod_budget(budget, &cells);
+
+        Ok(())
+    }
+
+    pub fn get_catalog(&self, name: &str) -> Result<&Catalog, Error> {
+        self.catalogs.get(name).ok_or(Error::CatalogNotPresent {
+            message: format!("{} catalog is not present!", name),
+        })
+    }
+
+    pub fn set_catalog_kernel(&mut self, name: &str, kernel: Kernel, camera: &CameraViewPort) -> Result<(), Error> {
+        let cells = self.refresh_cells_in_view(camera);
+        self.get_mut_catalog(name)?.set_kernel(kernel, &cells);
+
+        Ok(())
+    }
+
+    pub fn set_catalog_scaling(&mut self, name: &str, scaling: Scaling, camera: &CameraViewPort) -> Result<(), Error> {
+        let cells = self.refresh_cells_in_view(camera);
+        self.get_mut_catalog(name)?.set_scaling(scaling, &cells);
+
+        Ok(())
+    }
+
+    pub fn set_catalog_weight_column(
+        &mut self,
+        name: &str,
+        column: Option<String>,
+        camera: &CameraViewPort,
+    ) -> Result<(), Error> {
+        let cells = self.refresh_cells_in_view(camera);
+        self.get_mut_catalog(name)?.set_weight_column(column, &cells)
+    }
+
+    // The view is not refreshed by `update` while there is no catalog
+    fn refresh_cells_in_view(&mut self, camera: &CameraViewPort) -> Vec<HEALPixCell> {
+        self.view.refresh(camera.get_tile_depth().min(7), CooSystem::ICRSJ2000, camera);
+        Self::cells_in_view(camera, &self.view)
+    }
+
     pub fn set_kernel_size(&mut self, camera: &CameraViewPort) {
         let size = camera.get_screen_size();
         self.kernel_size = Vector2::new(32.0 / size.x, 32.0 / size.y);
@@ -196,20 +480,36 @@ impl Manager {
         })
     }
 
-    pub fn update(&mut self, camera: &CameraViewPort, view: &HEALPixCellsInView) {
+    pub fn is_empty(&self) -> bool {
+        self.catalogs.is_empty()
+    }
+
+    pub fn update(&mut self, camera: &CameraViewPort) {
+        if self.is_empty() {
+            return;
+        }
+
+        self.view.refresh(camera.get_tile_depth().min(7), CooSystem::ICRSJ2000, camera);
+        if !self.view.has_view_changed() {
+            return;
+        }
+
+        let cells = Self::cells_in_view(camera, &self.view);
+        for catalog in self.catalogs.values_mut() {
+            catalog.update(&cells);
+        }
+    }
+
+    fn cells_in_view(camera: &CameraViewPort, view: &HEALPixCellsInView) -> Vec<HEALPixCell> {
         // Render only the sources in the current field of view
         // Cells that are of depth > 7 are not handled by the hashmap (limited to depth 7)
         // For these cells, we draw all the sources lying in the ancestor cell of depth 7 containing
         // this cell
         //if camera.get_aperture() > P::RASTER_THRESHOLD_ANGLE {
         if camera.get_field_of_view().is_allsky() {
-            let cells = crate::healpix::cell::ALLSKY_HPX_CELLS_D0;
-
-            for catalog in self.catalogs.values_mut() {
-                catalog.update(cells);
-            }
+            crate::healpix::cell::ALLSKY_HPX_CELLS_D0.to_vec()
         } else {
-            let cells = Vec::from_iter(
+            Vec::from_iter(
                 view.get_cells()
                     .map(|&cell| {
                         let d = cell.depth();
@@ -221,11 +521,7 @@ impl Manager {
                     })
                     // This will delete the doublons if there is
                     .collect::<HashSet<_>>(),
-            );
-
-            for catalog in self.catalogs.values_mut() {
-                catalog.update(&cells);
-            }
+            )
         }
     }
 
@@ -249,15 +545,107 @@ impl Manager {
 }
 
 use super::index::SourceIndices;
+use super::merge::Merge;
+use super::kernel::{Kernel, KernelType, Scaling};
+use super::lod::{Aggregates, DEFAULT_LOD_BUDGET};
+use super::astrometry::{Astrometry, Propagation, DEFAULT_REFERENCE_EPOCH};
+use crate::healpix::coverage::HEALPixCoverage;
+use cdshealpix::sph_geom::{coo3d::Coo3D, Polygon};
+use crate::healpix::map::HEALPixMap;
+
+// The values of a numeric column, if it has a value for each source
+fn numeric_values<'a>(attributes: &'a Attributes, name: &str, num_sources: usize) -> Option<&'a [f64]> {
+    match attributes.get_column(name) {
+        Some(Column::Numeric(values)) if values.len() == num_sources => Some(values),
+        _ => None,
+    }
+}
+
+// The rows of the sources in the cells of a coverage
+//
+// The cells deeper than the depth 7 of the index give all the sources of their ancestor of depth 7
+fn rows_in_coverage(indices: &SourceIndices, coverage: &HEALPixCoverage) -> Vec<u32> {
+    let depth = coverage.depth().min(7);
+    coverage
+        .degraded(depth)
+        .flatten_to_fixed_depth_cells()
+        .flat_map(|idx| indices.get_source_indices(&HEALPixCell(depth, idx)))
+        .collect()
+}
+
+// The weights and the magnitudes of the sources used for their aggregates
+pub(super) fn aggregated_columns<'a>(
+    attributes: &'a Attributes,
+    weight_column: Option<&str>,
+    num_sources: usize,
+) -> (Option<&'a [f64]>, Option<&'a [f64]>) {
+    let magnitudes = find_magnitude_column(attributes.fields())
+        .and_then(|idx| numeric_values(attributes, &attributes.fields()[idx].name, num_sources));
+    let weights = weight_column.and_then(|name| numeric_values(attributes, name, num_sources));
+
+    (weights, magnitudes)
+}
+
+/// A subset of the sources of a catalog
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subset {
+    All,
+    /// The sources in the cells of the view
+    View,
+    /// The sources selected e.g. with a lasso
+    Selection,
+}
+
+impl TryFrom<&str> for Subset {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name.to_lowercase().as_str() {
+            "all" => Ok(Subset::All),
+            "view" => Ok(Subset::View),
+            "selection" => Ok(Subset::Selection),
+            _ => Err(format!("{} subset not found. Expected: all, view or selection", name)),
+        }
+    }
+}
 
 pub struct Catalog {
-    colormap: Colormap,
+    // Label of the colormap
+    colormap: String,
     num_instances: i32,
     indices: SourceIndices,
     alpha: f32,
     strength: f32,
+    // The instances drawn: the position of a source or of the centroid
+    // of an aggregate followed by the number of sources it represents
     current_sources: Vec<f32>,
     sources: Box<[f32]>,
+    aggregates: Aggregates,
+    // Maximum number of instances drawn
+    lod_budget: usize,
+
+    kernel: Kernel,
+    scaling: Scaling,
+    // Maximum density in the view used by the auto scaling
+    auto_max_density: f64,
+    // The numeric column giving the weight of the sources
+    weight_column: Option<String>,
+    // The columns of the table, in the same order as the sources
+    attributes: Attributes,
+    // The positions of the sources at their reference epoch once they have
+    // been propagated to another epoch, in the same order as the sources
+    reference: Option<Box<[Source]>>,
+    // Epoch of the positions given by the user, if not given by a column
+    reference_epoch: Option<f64>,
+    // Incremented each time the order of the sources changes so that
+    // outdated propagated sources are discarded
+    generation: u32,
+    // Indices of the selected sources, in increasing order
+    selection: Vec<u32>,
+    // Sources appended and waiting to be merged, the first ones being merged if `merging`
+    appends: VecDeque<(Box<[Source]>, Attributes)>,
+    // Id of the merge in progress
+    merging: Option<u64>,
     vertex_array_object_catalog: VertexArrayObject,
 }
 use crate::healpix::cell::HEALPixCell;
@@ -265,22 +653,44 @@ use crate::{camera::CameraViewPort, math::projection::Projection, utils};
 use al_core::SliceData;
 use cgmath::Vector2;
 use std::collections::HashSet;
-const MAX_SOURCES_PER_CATALOG: f32 = 50000.0;
 
 use crate::survey::view::HEALPixCellsInView;
+use crate::table::{find_magnitude_column, Attributes, Column};
+use crate::downloader::{query, request::catalog::CatalogTile};
+use super::progressive::TilesUpdate;
+use super::ProgressiveCatalog;
 use crate::Abort;
+use al_api::coo_system::CooSystem;
+
+// The ids of the vertex and fragment shaders, registered on the JS side, drawing a catalog in a projection
+fn catalog_shader(projection: &ProjectionType) -> Option<(&'static str, &'static str)> {
+    match projection {
+        ProjectionType::Sin(_) => Some(("CatalogOrthoVS", "CatalogOrthoFS")),
+        ProjectionType::Ait(_) => Some(("CatalogAitoffVS", "CatalogFS")),
+        ProjectionType::Mer(_) => Some(("CatalogMercatVS", "CatalogFS")),
+        ProjectionType::Mol(_) => Some(("CatalogMollVS", "CatalogFS")),
+        ProjectionType::Arc(_) => Some(("CatalogArcVS", "CatalogFS")),
+        ProjectionType::Tan(_) => Some(("CatalogTanVS", "CatalogFS")),
+        ProjectionType::Hpx(_) => Some(("CatalogHEALPixVS", "CatalogFS")),
+        _ => None,
+    }
+}
+
 impl Catalog {
     fn new<P: Projection>(
         gl: &WebGlContext,
-        colormap: Colormap,
+        colormap: String,
         sources: Box<[Source]>,
+        attributes: Attributes,
     ) -> Catalog {
         let alpha = 1_f32;
         let strength = 1_f32;
         let indices = SourceIndices::new(&sources);
-        let num_instances = sources.len() as i32;
+        let aggregates = Self::compute_aggregates(&sources, &indices, &attributes, None);
+        let num_instances = 0;
 
         let sources = unsafe { utils::transmute_boxed_slice(sources) };
+        let current_sources = vec![];
 
         let vertex_array_object_catalog = {
             #[cfg(feature = "webgl2")]
@@ -316,11 +726,11 @@ impl Catalog {
                 // Store the cartesian position of the center of the source in the a instanced VBO
                 .add_instanced_array_buffer(
                     "center",
-                    3 * std::mem::size_of::<f32>(),
-                    &[3],
+                    4 * std::mem::size_of::<f32>(),
+                    &[4],
                     &[0],
                     WebGl2RenderingContext::DYNAMIC_DRAW,
-                    SliceData(sources.as_ref()),
+                    VecData(&current_sources),
                 )
                 // Set the element buffer
                 .add_element_buffer(
@@ -332,10 +742,10 @@ impl Catalog {
             #[cfg(feature = "webgl1")]
             vao.bind_for_update()
                 .add_instanced_array_buffer(
-                    3,
+                    4,
                     "center",
                     WebGl2RenderingContext::DYNAMIC_DRAW,
-                    SliceData(sources.as_ref()),
+                    VecData(&current_sources),
                 )
                 // Store the UV and the offsets of the billboard in a VBO
                 .add_array_buffer(
@@ -361,7 +771,15 @@ impl Catalog {
 
             vao
         };
-        let current_sources = vec![];
+        let lod_budget = DEFAULT_LOD_BUDGET;
+        let kernel = Kernel::default();
+        let scaling = Scaling::Auto;
+        let auto_max_density = 1.0;
+        let weight_column = None;
+        let reference = None;
+        let reference_epoch = None;
+        let generation = 0;
+        let selection = vec![];
         Self {
             alpha,
             strength,
@@ -370,63 +788,414 @@ impl Catalog {
             indices,
             current_sources,
             sources,
+            aggregates,
+            lod_budget,
+            kernel,
+            scaling,
+            auto_max_density,
+            weight_column,
+            attributes,
+            reference,
+            reference_epoch,
+            generation,
+            selection,
+            appends: VecDeque::new(),
+            merging: None,
 
             vertex_array_object_catalog,
         }
     }
 
+    // Replace the sources of the catalog keeping its GPU buffers
+    fn set_sources(&mut self, sources: Box<[Source]>, attributes: Attributes, cells: &[HEALPixCell]) {
+        self.indices = SourceIndices::new(&sources);
+        self.aggregates =
+            Self::compute_aggregates(&sources, &self.indices, &attributes, self.weight_column.as_deref());
+        self.sources = unsafe { utils::transmute_boxed_slice(sources) };
+        self.attributes = attributes;
+        self.reference = None;
+        self.generation += 1;
+        self.selection.clear();
+        // The sources appended before are replaced too
+        self.appends.clear();
+        self.merging = None;
+
+        self.update(cells);
+    }
+
+    // Queue new sources to be merged with the ones of the catalog
+    fn append_sources(&mut self, sources: Box<[Source]>, attributes: Attributes) {
+        self.appends.push_back((sources, attributes));
+    }
+
+    // Start merging the first appended sources if no merge is in progress
+    fn next_merge(&mut self, id: u64) -> Option<Merge> {
+        if self.merging.is_some() {
+            return None;
+        }
+
+        let (sources, attributes) = self.appends.front()?;
+        let merge = Merge::new(
+            id,
+            self.generation,
+            self.get_sources(),
+            sources,
+            self.attributes.clone(),
+            attributes,
+            self.reference.as_deref(),
+            self.weight_column.clone(),
+        );
+        self.merging = Some(id);
+
+        Some(merge)
+    }
+
+    // Swap in the merge of the first appended sources keeping the GPU buffers
+    //
+    // Returns false if the merge is outdated. If only the order of the sources
+    // has changed, the appended sources are kept to be merged again.
+    fn set_merged_sources(&mut self, merge: Merge, cells: &[HEALPixCell]) -> bool {
+        if self.merging != Some(merge.id) {
+            return false;
+        }
+        self.merging = None;
+
+        if merge.generation != self.generation {
+            return false;
+        }
+        self.appends.pop_front();
+
+        // The merge is done
+        self.indices = merge.indices.unwrap_abort();
+        self.aggregates = merge.aggregates.unwrap_abort();
+        self.sources = unsafe { utils::transmute_boxed_slice(merge.merged_sources.into_boxed_slice()) };
+        self.attributes = merge.attributes;
+        self.reference = merge.reference.map(|reference| reference.into_boxed_slice());
+        self.generation += 1;
+        self.reorder_selection(&merge.rows);
+
+        self.update(cells);
+
+        true
+    }
+
+    // The brightest sources are given by the main magnitude column if there is one
+    fn compute_aggregates(
+        sources: &[Source],
+        indices: &SourceIndices,
+        attributes: &Attributes,
+        weight_column: Option<&str>,
+    ) -> Aggregates {
+        let (weights, magnitudes) = aggregated_columns(attributes, weight_column, sources.len());
+
+        Aggregates::new(sources, indices, weights, magnitudes)
+    }
+
+    fn get_sources(&self) -> &[Source] {
+        // Source is a packed struct of f32
+        unsafe {
+            std::slice::from_raw_parts(
+                self.sources.as_ptr() as *const Source,
+                self.sources.len() / Source::num_f32(),
+            )
+        }
+    }
+
+    /// Get what is needed to propagate the sources to an epoch, or to move them
+    /// back to their reference positions if `None`
+    ///
+    /// Returns `None` if the catalog has no proper motions or if its sources are
+    /// already at their reference positions
+    pub fn propagation(&self, epoch: Option<f64>) -> Option<Propagation> {
+        if epoch.is_none() && self.reference.is_none() {
+            return None;
+        }
+
+        let reference = match &self.reference {
+            Some(reference) => reference.to_vec(),
+            None => self.get_sources().to_vec(),
+        };
+        let astrometry = Astrometry::from_attributes(&self.attributes, reference.len())?;
+        let ref_epoch = self.reference_epoch.unwrap_or(DEFAULT_REFERENCE_EPOCH);
+
+        Some(Propagation::new(reference, astrometry, ref_epoch, epoch, self.generation))
+    }
+
+    /// Replace the sources by their propagated positions
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - The reference positions reordered as the propagated sources
+    /// * `sources` - The propagated sources sorted by their HEALPix cell at depth 7
+    /// * `rows` - The permutation of the sources given to the propagation
+    /// * `generation` - The generation of the catalog the propagation has been computed from
+    ///
+    /// Returns false if the catalog has changed since, the sources being left unchanged
+    fn set_propagated_sources(
+        &mut self,
+        reference: Box<[Source]>,
+        sources: Box<[Source]>,
+        rows: &[u32],
+        generation: u32,
+        cells: &[HEALPixCell],
+    ) -> bool {
+        if generation != self.generation {
+            return false;
+        }
+
+        if self.attributes.num_rows() == rows.len() {
+            self.attributes.permute(rows);
+        }
+        self.indices = SourceIndices::new(&sources);
+        self.aggregates =
+            Self::compute_aggregates(&sources, &self.indices, &self.attributes, self.weight_column.as_deref());
+        self.sources = unsafe { utils::transmute_boxed_slice(sources) };
+        self.reference = Some(reference);
+        self.generation += 1;
+        self.reorder_selection(rows);
+
+        self.update(cells);
+
+        true
+    }
+
+    // Keep the same sources selected once the sources have been reordered
+    fn reorder_selection(&mut self, rows: &[u32]) {
+        let mut new_indices = vec![0; rows.len()];
+        for (idx, &row) in rows.iter().enumerate() {
+            new_indices[row as usize] = idx as u32;
+        }
+
+        for idx in self.selection.iter_mut() {
+            *idx = new_indices[*idx as usize];
+        }
+        self.selection.sort_unstable();
+    }
+
+    /// Select the sources inside a spherical polygon
+    ///
+    /// # Arguments
+    ///
+    /// * `polygon` - The polygon in the ICRS frame
+    /// * `candidates` - The coverage of the polygon at depth 7, whose cells give
+    ///   the sources to test
+    pub fn select_in_polygon(&mut self, polygon: &Polygon, candidates: &HEALPixCoverage) {
+        let sources = self.get_sources();
+        let selection = rows_in_coverage(&self.indices, candidates)
+            .into_iter()
+            .filter(|&idx| {
+                let (lon, lat) = sources[idx as usize].lonlat();
+                polygon.contains(&Coo3D::from_sph_coo(lon as f64, lat as f64))
+            })
+            .collect::<Vec<_>>();
+
+        self.selection = selection;
+    }
+
+    /// The selected sources along with their attributes
+    pub fn get_selection(&self) -> (Vec<Source>, Attributes) {
+        self.get_rows(&self.selection)
+    }
+
+    /// Get a subset of the sources along with their attributes
+    pub fn get_subset(&self, subset: Subset, view: &HEALPixCoverage) -> (Vec<Source>, Attributes) {
+        match subset {
+            Subset::All => {
+                let rows = (0..self.get_sources().len() as u32).collect::<Vec<_>>();
+                self.get_rows(&rows)
+            }
+            Subset::View => {
+                let rows = rows_in_coverage(&self.indices, view);
+                self.get_rows(&rows)
+            }
+            Subset::Selection => self.get_selection(),
+        }
+    }
+
+    fn get_rows(&self, rows: &[u32]) -> (Vec<Source>, Attributes) {
+        let sources = self.get_sources();
+        let selected = rows.iter().map(|&idx| sources[idx as usize].clone()).collect();
+
+        let attributes = if self.attributes.num_rows() == sources.len() {
+            self.attributes.select(rows)
+        } else {
+            Attributes::default()
+        };
+
+        (selected, attributes)
+    }
+
+    /// Set the epoch of the positions used when no epoch column is found,
+    /// J2000 if `None`
+    pub fn set_reference_epoch(&mut self, epoch: Option<f64>) {
+        self.reference_epoch = epoch;
+    }
+
+    pub fn set_kernel(&mut self, kernel: Kernel, cells: &[HEALPixCell]) {
+        self.kernel = kernel;
+        // The auto scaling depends on the width of the kernel
+        self.update(cells);
+    }
+
+    pub fn set_scaling(&mut self, scaling: Scaling, cells: &[HEALPixCell]) {
+        self.scaling = scaling;
+        self.update(cells);
+    }
+
+    /// Weight the sources by a numeric column, or equally if `None`
+    pub fn set_weight_column(&mut self, column: Option<String>, cells: &[HEALPixCell]) -> Result<(), Error> {
+        if let Some(name) = &column {
+            if numeric_values(&self.attributes, name, self.get_sources().len()).is_none() {
+                return Err(Error::InvalidColumn {
+                    message: format!("{} is not a numeric column of the catalog", name),
+                });
+            }
+        }
+
+        self.weight_column = column;
+        self.aggregates = Self::compute_aggregates(
+            self.get_sources(),
+            &self.indices,
+            &self.attributes,
+            self.weight_column.as_deref(),
+        );
+        self.update(cells);
+
+        Ok(())
+    }
+
+    /// Bin the sources into the HEALPix cells of an order
+    ///
+    /// The cells give the number of sources they contain, or the sum of their
+    /// weights if a weight column is given
+    pub fn density_map(&self, order: u8, weight_column: Option<&str>) -> Result<HEALPixMap, Error> {
+        let sources = self.get_sources();
+        let weights = match weight_column {
+            Some(name) => Some(numeric_values(&self.attributes, name, sources.len()).ok_or_else(|| {
+                Error::InvalidColumn {
+                    message: format!("{} is not a numeric column of the catalog", name),
+                }
+            })?),
+            None => None,
+        };
+
+        let positions = sources.iter().enumerate().map(|(idx, s)| {
+            let (lon, lat) = s.lonlat();
+            let weight = weights.map(|w| w[idx]).filter(|w| w.is_finite()).unwrap_or(1.0);
+
+            (lon as f64, lat as f64, weight)
+        });
+
+        Ok(HEALPixMap::from_positions(order, positions))
+    }
+
+    // Estimate the maximum density in the view, in weight per square arcsecond, from
+    // the aggregates of the cells whose size is close to the one of the kernel
+    fn estimate_max_density(&self, cells: &[HEALPixCell]) -> f64 {
+        const SQ_ARCSEC_PER_SR: f64 = 4.254517029615221e10;
+
+        let cell_area = |depth: u8| 4.0 * std::f64::consts::PI / (12 << (2 * depth as u64)) as f64;
+        let support = self.kernel.support().0;
+        let depth = (0..=7)
+            .rev()
+            .find(|&d| cell_area(d).sqrt() >= 2.0 * support)
+            .unwrap_or(0);
+        let area = cell_area(depth) * SQ_ARCSEC_PER_SR;
+
+        let max_weight = cells
+            .iter()
+            .flat_map(|cell| {
+                let d = cell.depth();
+                if d > depth {
+                    vec![cell.ancestor(d - depth)]
+                } else {
+                    cell.get_children_cells(depth - d).collect()
+                }
+            })
+            .filter_map(|cell| self.aggregates.get(&cell))
+            .map(|aggregate| aggregate.weight)
+            .fold(0.0, f64::max);
+
+        // An isolated source is not saturated
+        (max_weight / area).max(self.kernel.peak())
+    }
+
     pub fn set_strength(&mut self, strength: f32) {
         self.strength = strength;
     }
 
-    pub fn set_colormap(&mut self, colormap: Colormap) {
+    pub fn set_colormap(&mut self, colormap: String) {
         self.colormap = colormap;
     }
 
+    pub fn get_attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
     pub fn set_alpha(&mut self, alpha: f32) {
         self.alpha = alpha;
     }
 
-    fn get_total_num_sources_in_fov(&self, cells: &[HEALPixCell]) -> usize {
-        let mut total_sources = 0;
-
-        for cell in cells {
-            let sources_idx = self.indices.get_source_indices(cell);
-            total_sources += (sources_idx.end - sources_idx.start) as usize;
-        }
-
-        total_sources
+    /// Set the maximum number of instances drawn, the sources of the densest
+    /// cells being aggregated beyond it
+    pub fn set_lod_budget(&mut self, budget: usize, cells: &[HEALPixCell]) {
+        self.lod_budget = budget;
+        self.update(cells);
     }
 
     // Cells are of depth <= 7
     fn update(&mut self, cells: &[HEALPixCell]) {
-        let num_sources_in_fov = self.get_total_num_sources_in_fov(cells) as f32;
+        if self.kernel.kind != KernelType::Default {
+            self.auto_max_density = self.estimate_max_density(cells);
+        }
+
         // reset the sources in the frame
         self.current_sources.clear();
-        // depth < 7
-        for cell in cells {
-            let delta_depth = (7_i8 - cell.depth() as i8).max(0);
-
-            for c in cell.get_children_cells(delta_depth as u8) {
-                // Define the total number of sources being in this kernel depth tile
-                let sources_in_cell = self.indices.get_source_indices(&c);
-                let num_sources_in_kernel_cell =
-                    (sources_in_cell.end - sources_in_cell.start) as usize;
-                if num_sources_in_kernel_cell > 0 {
-                    let num_sources = ((num_sources_in_kernel_cell as f32) / num_sources_in_fov)
-                        * MAX_SOURCES_PER_CATALOG;
-
-                    let sources =
-                        self.indices
-                            .get_k_sources(&self.sources, &c, num_sources as usize, 0);
-                    self.current_sources.extend(sources);
+
+        let (aggregated, full) = self.aggregates.select(cells, self.lod_budget);
+        for cell in aggregated {
+            if let Some(aggregate) = self.aggregates.get(&cell) {
+                // The brightest source is drawn apart from the centroid of the others
+                let idx = aggregate.brightest as usize * Source::num_f32();
+                self.current_sources
+                    .extend_from_slice(&self.sources[idx..(idx + Source::num_f32())]);
+                self.current_sources.push(aggregate.brightest_weight() as f32);
+
+                if aggregate.count > 1 {
+                    let centroid = aggregate.centroid();
+                    self.current_sources.extend([
+                        centroid.x,
+                        centroid.y,
+                        centroid.z,
+                        aggregate.others_weight() as f32,
+                    ]);
                 }
             }
         }
+
+        let num_sources = self.sources.len() / Source::num_f32();
+        let attributes = &self.attributes;
+        let weights = self
+            .weight_column
+            .as_deref()
+            .and_then(|name| numeric_values(attributes, name, num_sources));
+        for cell in full {
+            let range = self.indices.get_source_indices(&cell);
+            let sources = self.indices.get_k_sources(&self.sources, &cell, range.len(), 0);
+            for (s, idx) in sources.chunks_exact(Source::num_f32()).zip(range) {
+                let weight = weights
+                    .map(|w| w[idx as usize])
+                    .filter(|w| w.is_finite())
+                    .unwrap_or(1.0);
+
+                self.current_sources.extend_from_slice(s);
+                self.current_sources.push(weight as f32);
+            }
+        }
         //self.current_sources.shrink_to_fit();
 
         // Update the vertex buffer
-        self.num_instances = (self.current_sources.len() / Source::num_f32()) as i32;
+        self.num_instances = (self.current_sources.len() / (Source::num_f32() + 1)) as i32;
         #[cfg(feature = "webgl1")]
         self.vertex_array_object_catalog
             .bind_for_update()
@@ -448,6 +1217,30 @@ impl Catalog {
         fbo: Option<&FrameBufferObject>,
         projection: &ProjectionType,
     ) -> Result<(), JsValue> {
+        let (vs, fs) = match catalog_shader(projection) {
+            Some(ids) => ids,
+            // There is no catalog shader for the other projections
+            None => return Ok(()),
+        };
+
+        // The analytic kernels have an angular size
+        let kernel_size = if self.kernel.kind == KernelType::Default {
+            manager.kernel_size
+        } else {
+            let size = camera.get_screen_size();
+            // The screen width spans 2 in NDC
+            let width = (4.0 * self.kernel.support().0 / camera.get_aperture().0) as f32;
+            // Keep the kernel at least 2 pixels wide
+            let width = width.max(4.0 / size.x);
+
+            Vector2::new(width, width * size.x / size.y)
+        };
+        let max_density = match self.scaling {
+            Scaling::Auto => self.auto_max_density,
+            Scaling::Fixed(max_density) => max_density,
+        };
+        let kernel_scale = (self.kernel.peak() * self.strength as f64 / max_density) as f32;
+
         // If the catalog is transparent, simply discard the draw
         if self.alpha > 0_f32 {
             // Render to the FRAMEBUFFER
@@ -457,16 +1250,7 @@ impl Catalog {
                     gl.clear_color(0.0, 0.0, 0.0, 1.0);
                     gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
 
-                    let shader = match projection {
-                        ProjectionType::Sin(_) => crate::shader::get_shader(gl, shaders, "CatalogOrtVS", "CatalogOrtFS"),
-                        ProjectionType::Ait(_) => crate::shader::get_shader(gl, shaders, "CatalogAitVS", "CatalogFS"),
-                        ProjectionType::Mer(_) => crate::shader::get_shader(gl, shaders, "CatalogMerVS", "CatalogFS"),
-                        ProjectionType::Mol(_) => crate::shader::get_shader(gl, shaders, "CatalogMolVS", "CatalogFS"),
-                        ProjectionType::Arc(_) => crate::shader::get_shader(gl, shaders, "CatalogArcVS", "CatalogFS"),
-                        ProjectionType::Tan(_) => crate::shader::get_shader(gl, shaders, "CatalogTanVS", "CatalogFS"),
-                        ProjectionType::Hpx(_) => crate::shader::get_shader(gl, shaders, "CatalogHpxVS", "CatalogFS"),
-                        _ => todo!()
-                    }?;
+                    let shader = crate::shader::get_shader(gl, shaders, vs, fs)?;
                     let shader_bound = shader.bind(gl);
 
                     shader_bound
@@ -475,7 +1259,9 @@ impl Catalog {
                         .attach_uniform("kernel_texture", &manager.kernel_texture) // Gaussian kernel texture
                         .attach_uniform("strength", &self.strength) // Strengh of the kernel
                         .attach_uniform("current_time", &utils::get_current_time())
-                        .attach_uniform("kernel_size", &manager.kernel_size)
+                        .attach_uniform("kernel_size", &kernel_size)
+                        .attach_uniform("kernel_type", &self.kernel.shader_id())
+                        .attach_uniform("kernel_scale", &kernel_scale)
                         .bind_vertex_array_object_ref(&self.vertex_array_object_catalog)
                         .draw_elements_instanced_with_i32(
                             WebGl2RenderingContext::TRIANGLES,
@@ -499,7 +1285,7 @@ impl Catalog {
                 shaderbound
                     .attach_uniform("texture_fbo", &manager.fbo.texture) // FBO density texture computed just above
                     .attach_uniform("alpha", &self.alpha) // Alpha channel
-                    .attach_uniforms_with_params_from(&self.colormap, colormaps)
+                    .attach_uniforms_with_params_from(colormaps.get(&self.colormap), colormaps)
                     .attach_uniforms_from(colormaps)
                     .attach_uniform("reversed", &0.0_f32)
                     .bind_vertex_array_object_ref(&manager.vertex_array_object_screen)
@@ -516,3 +1302,61 @@ impl Catalog {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::{catalog_shader, rows_in_coverage};
+    use crate::healpix::coverage::HEALPixCoverage;
+    use crate::math::angle::ArcDeg;
+    use crate::renderable::catalog::{index::SourceIndices, sort_by_hpx_cell, Source};
+    use crate::ProjectionType;
+
+    #[test]
+    fn catalog_shaders_are_registered() {
+        let registered = include_str!("../../../../js/ShadersWebGL2.js");
+        let projections = [
+            ProjectionType::Sin(mapproj::zenithal::sin::Sin),
+            ProjectionType::Ait(mapproj::pseudocyl::ait::Ait),
+            ProjectionType::Mer(mapproj::cylindrical::mer::Mer),
+            ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new()),
+            ProjectionType::Arc(mapproj::zenithal::arc::Arc),
+            ProjectionType::Tan(mapproj::zenithal::tan::Tan),
+            ProjectionType::Hpx(mapproj::hybrid::hpx::Hpx),
+        ];
+        for projection in projections.iter() {
+            let (vs, fs) = catalog_shader(projection).unwrap();
+            for id in [vs, fs] {
+                assert!(registered.contains(&format!("id: \"{}\"", id)), "{} is not registered", id);
+            }
+        }
+    }
+
+    #[test]
+    fn sources_in_a_coverage_shallower_than_the_index() {
+        let sources = [(10.0, 10.0), (10.5, 10.2), (200.0, -40.0), (80.0, 60.0)]
+            .iter()
+            .map(|&(lon, lat)| Source::new(ArcDeg(lon).into(), ArcDeg(lat).into()))
+            .collect::<Vec<_>>();
+        let rows = sort_by_hpx_cell(&sources);
+        let sources = rows.iter().map(|&r| sources[r as usize].clone()).collect::<Vec<_>>();
+        let indices = SourceIndices::new(&sources);
+
+        // A coverage of depth 3 made of the cell of the first source
+        let cell = |s: &Source| {
+            let (lon, lat) = s.lonlat();
+            cdshealpix::nested::hash(3, lon as f64, lat as f64)
+        };
+        let first = sources
+            .iter()
+            .find(|s| (s.lonlat().0.to_degrees() - 10.0).abs() < 1e-3)
+            .unwrap();
+        let view = HEALPixCoverage::from_hpx_cells(3, std::iter::once(cell(first)), Some(1));
+        assert_eq!(view.depth(), 3);
+
+        let expected = (0..sources.len() as u32)
+            .filter(|&r| cell(&sources[r as usize]) == cell(first))
+            .collect::<Vec<_>>();
+        assert_eq!(expected.len(), 2);
+        assert_eq!(rows_in_coverage(&indices, &view), expected);
+    }
+}
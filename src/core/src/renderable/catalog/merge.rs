@@ -0,0 +1,226 @@
+//! Merge of the sources appended to a catalog
+//!
+//! The sources of the catalog and the appended ones are both sorted by their HEALPix
+//! cell at depth 7 so they only have to be merged. The merge, the permutation of the
+//! attributes and the computation of the index and of the aggregates are done a chunk
+//! at a time (see `MergeCatalogSources`), the catalog being left untouched until the
+//! merge is swapped in.
+use super::index::SourceIndices;
+use super::lod::{Aggregates, AggregatesBuilder, NUM_CELLS};
+use super::manager::aggregated_columns;
+use super::source::Source;
+use crate::table::Attributes;
+
+const CHUNK_OF_SOURCES_TO_MERGE: usize = 20000;
+const CHUNK_OF_CELLS_TO_AGGREGATE: u64 = 16384;
+
+enum Step {
+    Merge,
+    // Index of the next column of attributes to permute
+    Attributes(usize),
+    Sources,
+    Indices,
+    // Index of the next cell at depth 7 to aggregate
+    Aggregates(u64),
+    Done,
+}
+
+pub struct Merge {
+    /// Identifies the merge among the ones spawned by the manager
+    pub id: u64,
+    /// The generation of the catalog the merge has been computed from
+    pub generation: u32,
+
+    step: Step,
+
+    // The sources of the catalog followed by the appended ones
+    sources: Vec<Source>,
+    num_old_sources: usize,
+    // Next source of each of the two runs to merge
+    i: usize,
+    j: usize,
+
+    pub(super) rows: Vec<u32>,
+    pub(super) attributes: Attributes,
+    permute_attributes: bool,
+    pub(super) reference: Option<Vec<Source>>,
+    weight_column: Option<String>,
+
+    pub(super) merged_sources: Vec<Source>,
+    pub(super) indices: Option<SourceIndices>,
+    builder: AggregatesBuilder,
+    pub(super) aggregates: Option<Aggregates>,
+}
+
+impl Merge {
+    /// # Arguments
+    ///
+    /// * `old_sources` - The sources of the catalog, sorted by their cell at depth 7
+    /// * `new_sources` - The appended sources, sorted the same way and given at the reference epoch
+    /// * `attributes` - The attributes of the catalog
+    /// * `reference` - The positions of the sources of the catalog at the reference epoch, if propagated
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: u64,
+        generation: u32,
+        old_sources: &[Source],
+        new_sources: &[Source],
+        mut attributes: Attributes,
+        new_attributes: &Attributes,
+        reference: Option<&[Source]>,
+        weight_column: Option<String>,
+    ) -> Self {
+        let num_old_sources = old_sources.len();
+        let mut sources = Vec::with_capacity(num_old_sources + new_sources.len());
+        sources.extend_from_slice(old_sources);
+        sources.extend_from_slice(new_sources);
+
+        // Attributes of sources given by their position only are left empty
+        let permute_attributes = !attributes.fields().is_empty() || num_old_sources == 0;
+        if permute_attributes {
+            attributes.append(new_attributes, new_sources.len());
+        }
+
+        let reference = reference.map(|reference| {
+            let mut reference = reference.to_vec();
+            reference.extend_from_slice(new_sources);
+            reference
+        });
+
+        Self {
+            id,
+            generation,
+            step: Step::Merge,
+            rows: Vec::with_capacity(sources.len()),
+            sources,
+            num_old_sources,
+            i: 0,
+            j: num_old_sources,
+            attributes,
+            permute_attributes,
+            reference,
+            weight_column,
+            merged_sources: vec![],
+            indices: None,
+            builder: AggregatesBuilder::default(),
+            aggregates: None,
+        }
+    }
+
+    /// Do the next chunk of the merge
+    ///
+    /// Returns false once the merge is done
+    pub fn step(&mut self) -> bool {
+        match self.step {
+            Step::Merge => {
+                let hash = |s: &Source| {
+                    let (lon, lat) = s.lonlat();
+                    cdshealpix::nested::hash(7, lon as f64, lat as f64)
+                };
+
+                let end = (self.rows.len() + CHUNK_OF_SOURCES_TO_MERGE).min(self.sources.len());
+                while self.rows.len() < end {
+                    // The sources of the catalog come first in a same cell
+                    let take_old = self.j == self.sources.len()
+                        || (self.i < self.num_old_sources
+                            && hash(&self.sources[self.i]) <= hash(&self.sources[self.j]));
+
+                    if take_old {
+                        self.rows.push(self.i as u32);
+                        self.i += 1;
+                    } else {
+                        self.rows.push(self.j as u32);
+                        self.j += 1;
+                    }
+                }
+
+                if self.rows.len() == self.sources.len() {
+                    self.step = Step::Attributes(0);
+                }
+            }
+            Step::Attributes(idx) => {
+                if self.permute_attributes && idx < self.attributes.columns().len() {
+                    self.attributes.permute_column(idx, &self.rows);
+                    self.step = Step::Attributes(idx + 1);
+                } else {
+                    self.step = Step::Sources;
+                }
+            }
+            Step::Sources => {
+                let rows = &self.rows;
+                self.merged_sources = rows.iter().map(|&r| self.sources[r as usize].clone()).collect();
+                self.reference = self
+                    .reference
+                    .take()
+                    .map(|reference| rows.iter().map(|&r| reference[r as usize].clone()).collect());
+                self.sources = vec![];
+
+                self.step = Step::Indices;
+            }
+            Step::Indices => {
+                self.indices = Some(SourceIndices::new(&self.merged_sources));
+                self.step = Step::Aggregates(0);
+            }
+            Step::Aggregates(start) => {
+                let end = (start + CHUNK_OF_CELLS_TO_AGGREGATE).min(NUM_CELLS);
+                if let Some(indices) = &self.indices {
+                    let (weights, magnitudes) = aggregated_columns(
+                        &self.attributes,
+                        self.weight_column.as_deref(),
+                        self.merged_sources.len(),
+                    );
+                    self.builder
+                        .add_cells(start..end, &self.merged_sources, indices, weights, magnitudes);
+                }
+
+                if end == NUM_CELLS {
+                    let builder = std::mem::take(&mut self.builder);
+                    self.aggregates = Some(builder.build());
+                    self.step = Step::Done;
+                } else {
+                    self.step = Step::Aggregates(end);
+                }
+            }
+            Step::Done => return false,
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Merge;
+    use crate::healpix::cell::HEALPixCell;
+    use crate::math::angle::ArcDeg;
+    use crate::renderable::catalog::{index::sort_by_hpx_cell, Source};
+
+    fn sources(lonlat: &[(f32, f32)]) -> Vec<Source> {
+        let sources = lonlat
+            .iter()
+            .map(|&(lon, lat)| Source::new(ArcDeg(lon).into(), ArcDeg(lat).into()))
+            .collect::<Vec<_>>();
+        let rows = sort_by_hpx_cell(&sources);
+
+        rows.iter().map(|&r| sources[r as usize].clone()).collect()
+    }
+
+    #[test]
+    fn merge_is_a_stable_sort() {
+        let old = sources(&[(10.0, 5.0), (200.0, -40.0), (80.0, 60.0), (10.0, 5.0)]);
+        let new = sources(&[(300.0, 20.0), (10.0, 5.0), (150.0, -80.0)]);
+
+        let mut merge = Merge::new(0, 0, &old, &new, Default::default(), &Default::default(), None, None);
+        while merge.step() {}
+
+        let all = old.iter().chain(new.iter()).cloned().collect::<Vec<_>>();
+        assert_eq!(merge.rows, sort_by_hpx_cell(&all));
+
+        let aggregates = merge.aggregates.unwrap();
+        let total = (0..12)
+            .filter_map(|idx| aggregates.get(&HEALPixCell(0, idx)))
+            .map(|a| a.count)
+            .sum::<u32>();
+        assert_eq!(total, 7);
+    }
+}
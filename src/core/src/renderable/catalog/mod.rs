@@ -1,5 +1,15 @@
 mod manager;
-pub use manager::{Catalog, Manager};
+pub use manager::{Catalog, Manager, Subset};
+mod progressive;
+pub use progressive::ProgressiveCatalog;
 mod source;
 pub use source::Source;
 mod index;
+pub use index::sort_by_hpx_cell;
+mod kernel;
+pub use kernel::{Kernel, KernelType, Scaling};
+mod lod;
+mod astrometry;
+pub use astrometry::Propagation;
+mod merge;
+pub use merge::Merge;
@@ -0,0 +1,153 @@
+//! Progressive (HiPS) catalogs
+//!
+//! The sources of a HiPS catalog are split into tiles `Norder/DirD/NpixN.tsv`,
+//! each order adding new sources to the ones of the lower orders. The tiles covering
+//! the view are fetched from the minimum order down to the order of the view and are
+//! evicted as soon as they leave it. The sources of the new tiles are appended to
+//! the catalog, which is only rebuilt once tiles have been evicted.
+use super::index::sort_by_hpx_cell;
+use super::source::Source;
+use crate::camera::CameraViewPort;
+use crate::downloader::query;
+use crate::healpix::cell::HEALPixCell;
+use crate::renderable::Url;
+use crate::survey::view::HEALPixCellsInView;
+use crate::table::{Attributes, Field};
+
+use al_api::coo_system::CooSystem;
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+pub struct ProgressiveCatalog {
+    hips_url: Url,
+    min_order: u8,
+    max_order: u8,
+    // The columns described in the Metadata.xml
+    fields: Rc<[Field]>,
+
+    view: HEALPixCellsInView,
+    // The tiles needed for the current view
+    cells: HashSet<HEALPixCell>,
+
+    tiles: HashMap<HEALPixCell, (Vec<Source>, Attributes)>,
+    // Tiles that do not exist or that could not be parsed
+    missing: HashSet<HEALPixCell>,
+    // Tiles received since the sources have been gathered
+    new_tiles: Vec<HEALPixCell>,
+    // Whether tiles have been evicted since the sources have been gathered
+    has_evicted: bool,
+}
+
+/// The change of the sources of a progressive catalog
+pub enum TilesUpdate {
+    /// Tiles have been evicted, all the sources are gathered again
+    Replace(Vec<Source>, Attributes),
+    /// The sources of the new tiles only
+    Append(Vec<Source>, Attributes),
+}
+
+impl ProgressiveCatalog {
+    pub fn new(hips_url: Url, min_order: u8, max_order: u8, fields: Vec<Field>) -> Self {
+        Self {
+            hips_url,
+            min_order,
+            max_order: max_order.max(min_order),
+            fields: fields.into(),
+
+            view: HEALPixCellsInView::new(),
+            cells: HashSet::new(),
+
+            tiles: HashMap::new(),
+            missing: HashSet::new(),
+            new_tiles: vec![],
+            has_evicted: false,
+        }
+    }
+
+    pub fn get_hips_url(&self) -> &Url {
+        &self.hips_url
+    }
+
+    /// Update the tiles covering the view
+    ///
+    /// The tiles that left the view are evicted and the queries for the
+    /// new ones are returned.
+    pub fn refresh(&mut self, camera: &CameraViewPort) -> Vec<query::CatalogTile> {
+        let depth = camera
+            .get_tile_depth()
+            .clamp(self.min_order, self.max_order);
+        self.view.refresh(depth, CooSystem::ICRSJ2000, camera);
+
+        let min_order = self.min_order;
+        self.cells = self
+            .view
+            .get_cells()
+            .flat_map(|cell| (min_order..=depth).map(move |d| cell.ancestor(depth - d)))
+            .collect();
+
+        let num_tiles = self.tiles.len();
+        let cells = &self.cells;
+        self.tiles.retain(|cell, _| cells.contains(cell));
+        self.has_evicted |= num_tiles != self.tiles.len();
+
+        self.cells
+            .iter()
+            .filter(|cell| !self.tiles.contains_key(cell) && !self.missing.contains(cell))
+            .map(|cell| query::CatalogTile::new(cell, &self.hips_url, self.fields.clone()))
+            .collect()
+    }
+
+    pub fn add_tile(&mut self, cell: HEALPixCell, table: Option<(Vec<Source>, Attributes)>) {
+        // The view may have changed since the tile has been requested
+        if !self.cells.contains(&cell) {
+            return;
+        }
+
+        if let Some(table) = table {
+            // A tile received twice replaces the sources it added before
+            self.has_evicted |= self.tiles.insert(cell, table).is_some();
+            self.new_tiles.push(cell);
+        } else {
+            self.missing.insert(cell);
+        }
+    }
+
+    /// Gather the sources of the tiles if they have changed
+    ///
+    /// The sources are returned sorted as needed by the catalog renderable
+    pub fn take_update(&mut self) -> Option<TilesUpdate> {
+        if self.has_evicted {
+            self.has_evicted = false;
+            self.new_tiles.clear();
+
+            let (sources, attributes) = sorted_sources(self.tiles.values());
+            Some(TilesUpdate::Replace(sources, attributes))
+        } else if !self.new_tiles.is_empty() {
+            let tiles = &self.tiles;
+            let new_tiles = self.new_tiles.drain(..).filter_map(|cell| tiles.get(&cell));
+
+            let (sources, attributes) = sorted_sources(new_tiles);
+            Some(TilesUpdate::Append(sources, attributes))
+        } else {
+            None
+        }
+    }
+}
+
+fn sorted_sources<'a>(
+    tiles: impl Iterator<Item = &'a (Vec<Source>, Attributes)>,
+) -> (Vec<Source>, Attributes) {
+    let mut sources = vec![];
+    let mut attributes = Attributes::default();
+    for (tile_sources, tile_attributes) in tiles {
+        sources.extend_from_slice(tile_sources);
+        attributes.append(tile_attributes, tile_sources.len());
+    }
+
+    let rows = sort_by_hpx_cell(&sources);
+    let sources = rows.iter().map(|&r| sources[r as usize].clone()).collect();
+    attributes.permute(&rows);
+
+    (sources, attributes)
+}
@@ -1,14 +1,13 @@
 
-use web_sys::WebGl2RenderingContext;
-
 use crate::math::angle;
 use cgmath::Vector4;
 
 use crate::camera::CameraViewPort;
 use crate::ProjectionType;
 
-use al_api::grid::GridCfg;
-use al_core::VertexArrayObject;
+use al_api::coo_system::CooSystem;
+use al_api::grid::{GridBorderLabels, GridCfg, GridLabelFormat};
+use crate::coosys::apply_coo_system;
 use al_api::color::ColorRGB;
 use crate::Abort;
 pub struct ProjetedGrid {
@@ -18,30 +17,39 @@ pub struct ProjetedGrid {
     pub show_labels: bool,
     pub enabled: bool,
     pub label_scale: f32,
-
-    // The vertex array object of the screen in NDC
-    vao: VertexArrayObject,
+    // The frame of the meridians and parallels, the one of the view if not given
+    pub frame: Option<CooSystem>,
+    // The step between the lines in radians, adapting to the field of view if not given
+    pub step: Option<f64>,
+    pub label_format: GridLabelFormat,
+    // The number of decimals of the labels in decimal degrees, deduced from the step if not given
+    pub label_digits: Option<u8>,
+    // Label the lines on the border of the view instead of along them
+    pub border_axes: bool,
+    // The length of the ticks of the border axes in pixels
+    pub tick_length: f32,
+    pub border_labels: GridBorderLabels,
+    // in pixels
+    pub line_width: f32,
+    // The lengths of the dashes and of the gaps between them in pixels, solid if empty
+    pub line_dash: Vec<f32>,
+
+    // The meridians, parallels and ticks rasterized as thick lines
+    lines: RasterizedLinesRenderManager,
 
     labels: Vec<Option<Label>>,
-    sizes: Vec<usize>,
-    offsets: Vec<usize>,
-
-    num_vertices: usize,
-
-    gl: WebGlContext,
 
     // Render Text Manager
     text_renderer: TextRenderManager,
 }
 
-use crate::shader::ShaderManager;
-use al_core::VecData;
 use al_core::WebGlContext;
 use wasm_bindgen::JsValue;
 
 use super::labels::RenderManager;
 
 use super::TextRenderManager;
+use super::lines::{self, LineStyle, RasterizedLinesRenderManager};
 
 use al_api::resources::Resources;
 impl ProjetedGrid {
@@ -51,38 +59,10 @@ impl ProjetedGrid {
         resources: &Resources,
         projection: &ProjectionType
     ) -> Result<ProjetedGrid, JsValue> {
-        let vao = {
-            let mut vao = VertexArrayObject::new(gl);
-            let vertices = vec![];
-            // layout (location = 0) in vec2 ndc_pos;
-            #[cfg(feature = "webgl2")]
-            vao.bind_for_update().add_array_buffer(
-                "ndc_pos",
-                2 * std::mem::size_of::<f32>(),
-                &[2],
-                &[0],
-                WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<f32>(&vertices),
-            );
-            #[cfg(feature = "webgl1")]
-            vao.bind_for_update().add_array_buffer(
-                2,
-                "ndc_pos",
-                WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<f32>(&vertices),
-            );
-
-            vao
-        };
-
-        let num_vertices = 0;
+        let lines = RasterizedLinesRenderManager::new(gl)?;
 
         let labels = vec![];
 
-        let gl = gl.clone();
-        let sizes = vec![];
-        let offsets = vec![];
-
         let text_renderer = TextRenderManager::new(gl.clone(), &resources)?;
 
         let color = ColorRGB { r: 0.0, g: 1.0, b: 0.0 };
@@ -90,6 +70,15 @@ impl ProjetedGrid {
         let show_labels = true;
         let enabled = false;
         let label_scale = 1.0;
+        let frame = None;
+        let step = None;
+        let label_format = GridLabelFormat::Dms;
+        let label_digits = None;
+        let border_axes = false;
+        let tick_length = 8.0;
+        let border_labels = GridBorderLabels::Outside;
+        let line_width = 1.0;
+        let line_dash = vec![];
 
         let mut grid = ProjetedGrid {
             color,
@@ -97,16 +86,18 @@ impl ProjetedGrid {
             show_labels,
             enabled,
             label_scale,
-
-            vao,
-            //vbo,
+            frame,
+            step,
+            label_format,
+            label_digits,
+            border_axes,
+            tick_length,
+            border_labels,
+            line_width,
+            line_dash,
+
+            lines,
             labels,
-            num_vertices,
-
-            sizes,
-            offsets,
-
-            gl,
 
             text_renderer,
         };
@@ -117,14 +108,31 @@ impl ProjetedGrid {
     }
 
     pub fn set_cfg(&mut self, new_cfg: GridCfg, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(), JsValue> {
+        // Nothing is changed if the configuration cannot be applied
+        if let Some(line_dash) = &new_cfg.line_dash {
+            lines::check_dash(line_dash)?;
+        }
+
         let GridCfg {
             color,
             opacity,
             show_labels,
             label_size,
             enabled,
+            frame,
+            step,
+            label_format,
+            label_digits,
+            border_axes,
+            tick_length,
+            border_labels,
+            line_width,
+            line_dash,
         } = new_cfg;
 
+        // The style of the lines is given when they are rasterized
+        let style_changed = color.is_some() || opacity.is_some() || line_width.is_some() || line_dash.is_some();
+
         if let Some(color) = color {
             self.color = color;
         }
@@ -137,19 +145,63 @@ impl ProjetedGrid {
             self.show_labels = show_labels;
         }
 
+        if let Some(frame) = frame {
+            self.frame = frame.coo_system();
+        }
+
+        if let Some(step) = step {
+            self.step = if step > 0.0 { Some(step.to_radians()) } else { None };
+        }
+
+        if let Some(label_format) = label_format {
+            self.label_format = label_format;
+        }
+
+        if let Some(label_digits) = label_digits {
+            self.label_digits = if label_digits >= 0 { Some(label_digits as u8) } else { None };
+        }
+
+        if let Some(border_axes) = border_axes {
+            self.border_axes = border_axes;
+        }
+
+        if let Some(tick_length) = tick_length {
+            self.tick_length = tick_length;
+        }
+
+        if let Some(border_labels) = border_labels {
+            self.border_labels = border_labels;
+        }
+
+        if let Some(line_width) = line_width {
+            self.line_width = line_width;
+        }
+
+        if let Some(line_dash) = line_dash {
+            self.line_dash = line_dash;
+        }
+
         if let Some(enabled) = enabled {
             self.enabled = enabled;
-            if enabled {
-                self.force_update(camera, projection);
-            }
         }
 
+        let labels_changed = label_format.is_some()
+            || label_digits.is_some()
+            || border_axes.is_some()
+            || tick_length.is_some()
+            || border_labels.is_some()
+            || (self.border_axes && label_size.is_some());
+        // The labels of the border axes are spaced given their size
         if let Some(label_size) = label_size {
             self.label_scale = label_size;
 
             //self.text_renderer.set_text_size(label_size)?;
         }
 
+        if self.enabled && (enabled == Some(true) || frame.is_some() || step.is_some() || labels_changed || style_changed) {
+            self.force_update(camera, projection);
+        }
+
         self.text_renderer.begin_frame();
         for label in self.labels.iter().flatten() {
             self.text_renderer.add_label(
@@ -166,25 +218,41 @@ impl ProjetedGrid {
     fn force_update(&mut self, camera: &CameraViewPort, projection: &ProjectionType) {
         self.text_renderer.begin_frame();
         //let text_height = text_renderer.text_size();
-        let lines = lines(camera, &self.text_renderer, projection);
-
-        self.offsets.clear();
-        self.sizes.clear();
-        let (vertices, labels): (Vec<Vec<Vector2<f64>>>, Vec<Option<Label>>) = lines
-            .into_iter()
-            .map(|line| {
-                if self.sizes.is_empty() {
-                    self.offsets.push(0);
-                } else {
-                    let last_offset = *self.offsets.last().unwrap_abort();
-                    self.offsets.push(last_offset + self.sizes.last().unwrap_abort());
-                }
-                self.sizes.push(line.vertices.len());
+        let view = GridView::new(camera, projection, self.frame);
+        let mut lines = lines(&view, &self.text_renderer, self.step, self.label_format, self.label_digits);
+        let (ticks, border_labels) = if self.border_axes {
+            let axes = BorderAxes {
+                tick_length: self.tick_length as f64,
+                labels: self.border_labels,
+                label_scale: self.label_scale as f64,
+            };
+
+            axes.place(&mut lines, camera, &self.text_renderer)
+        } else {
+            (vec![], vec![])
+        };
+
+        let mut style = LineStyle {
+            width: self.line_width,
+            dash: self.line_dash.clone(),
+            color: self.color,
+            opacity: self.opacity,
+        };
+        self.lines.begin_frame();
+        for line in lines.iter() {
+            for path in paths(&line.vertices) {
+                self.lines.add_path(&path, false, &style, camera);
+            }
+        }
+        // The ticks and the frame of the border axes are solid
+        style.dash.clear();
+        for path in paths(&ticks) {
+            self.lines.add_path(&path, false, &style, camera);
+        }
+        self.lines.end_frame();
 
-                (line.vertices, line.label)
-            })
-            .unzip();
-        self.labels = labels;
+        self.labels = lines.into_iter().map(|line| line.label).collect();
+        self.labels.extend(border_labels.into_iter().map(Some));
 
         for label in self.labels.iter().flatten() {
             self.text_renderer.add_label(
@@ -194,38 +262,6 @@ impl ProjetedGrid {
             );
         }
 
-        let vertices = vertices
-            .into_iter()
-            .flatten()
-            .flat_map(|v| [v.x as f32, v.y as f32])
-            .collect::<Vec<_>>();
-        //self.lines = lines;
-        self.num_vertices = vertices.len() >> 1;
-
-        /*let vertices = unsafe {
-            let len = vertices.len() << 1;
-            let cap = len;
-
-            Vec::from_raw_parts(vertices.as_mut_ptr() as *mut f32, len, cap)
-        };*/
-        /*let vertices = unsafe {
-            vertices.set_len(self.num_vertices << 1);
-            std::mem::transmute::<_, Vec<f32>>(vertices)
-        };*/
-
-        #[cfg(feature = "webgl2")]
-        self.vao.bind_for_update().update_array(
-            "ndc_pos",
-            WebGl2RenderingContext::DYNAMIC_DRAW,
-            VecData(&vertices),
-        );
-        #[cfg(feature = "webgl1")]
-        self.vao.bind_for_update().update_array(
-            "ndc_pos",
-            WebGl2RenderingContext::DYNAMIC_DRAW,
-            VecData(&vertices),
-        );
-
         self.text_renderer.end_frame();
     }
 
@@ -238,58 +274,118 @@ impl ProjetedGrid {
         self.force_update(camera, projection);
     }
 
-    fn draw_lines_cpu(&self, camera: &CameraViewPort, shaders: &mut ShaderManager) {
-        self.gl.blend_func_separate(
-            WebGl2RenderingContext::SRC_ALPHA,
-            WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
-            WebGl2RenderingContext::ONE,
-            WebGl2RenderingContext::ONE,
-        );
+    pub fn draw(&mut self, camera: &CameraViewPort) -> Result<(), JsValue> {
+        if self.enabled {
+            self.lines.draw();
 
-        let shader = shaders
-            .get(
-                &self.gl,
-                &ShaderId(Cow::Borrowed("GridVS_CPU"), Cow::Borrowed("GridFS_CPU")),
-            )
-            .unwrap_abort();
-        let shader = shader.bind(&self.gl);
-        shader
-            .attach_uniforms_from(camera)
-            .attach_uniform("opacity", &self.opacity)
-            .attach_uniform("color", &self.color);
-
-        // The raster vao is bound at the lib.rs level
-        let drawer = shader.bind_vertex_array_object_ref(&self.vao);
-        for (offset, size) in self.offsets.iter().zip(self.sizes.iter()) {
-            if *size > 0 {
-                drawer.draw_arrays(WebGl2RenderingContext::LINES, *offset as i32, *size as i32);
+            if self.show_labels {
+                self.text_renderer.draw(camera, &self.color, self.opacity, self.label_scale)?;
             }
         }
+
+        Ok(())
     }
+}
+
+/// The id of the grid created with the view
+pub(crate) const DEFAULT_GRID: &str = "default";
+
+/// A collection of coordinate grids drawn at the same time
+///
+/// Each grid is identified by a name and has its own frame, color, labels and step.
+/// The grids are drawn in the order they have been added.
+pub struct ProjetedGrids {
+    grids: HashMap<String, ProjetedGrid>,
+    // The ids of the grids in their drawing order
+    ids: Vec<String>,
+
+    gl: WebGlContext,
+    resources: Resources,
+}
 
-    pub fn draw(
-        &mut self,
+impl ProjetedGrids {
+    pub fn new(
+        gl: &WebGlContext,
         camera: &CameraViewPort,
-        shaders: &mut ShaderManager,
-    ) -> Result<(), JsValue> {
-        if self.enabled {
-            self.gl.enable(WebGl2RenderingContext::BLEND);
-            self.draw_lines_cpu(camera, shaders);
+        resources: &Resources,
+        projection: &ProjectionType
+    ) -> Result<ProjetedGrids, JsValue> {
+        let mut grids = HashMap::new();
+        grids.insert(
+            DEFAULT_GRID.to_string(),
+            ProjetedGrid::new(gl, camera, resources, projection)?
+        );
+        let ids = vec![DEFAULT_GRID.to_string()];
 
-            self.gl.disable(WebGl2RenderingContext::BLEND);
+        Ok(ProjetedGrids {
+            grids,
+            ids,
 
-            if self.show_labels {
-                self.text_renderer.draw(camera, &self.color, self.opacity, self.label_scale)?;
-            }
+            gl: gl.clone(),
+            resources: resources.clone(),
+        })
+    }
+
+    /// Add a new grid, shown unless its configuration tells otherwise
+    pub fn add(&mut self, id: String, cfg: GridCfg, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(), JsValue> {
+        if self.grids.contains_key(&id) {
+            return Err(JsValue::from_str(&format!("A grid with the id {} already exists", id)));
         }
 
+        let mut grid = ProjetedGrid::new(&self.gl, camera, &self.resources, projection)?;
+        let cfg = GridCfg {
+            enabled: cfg.enabled.or(Some(true)),
+            ..cfg
+        };
+        grid.set_cfg(cfg, camera, projection)?;
+
+        self.grids.insert(id.clone(), grid);
+        self.ids.push(id);
+
         Ok(())
     }
-}
 
-use crate::shader::ShaderId;
+    /// Remove a grid. The default one can only be hidden
+    pub fn remove(&mut self, id: &str) -> Result<(), JsValue> {
+        if id == DEFAULT_GRID {
+            return Err(JsValue::from_str("The default grid cannot be removed"));
+        }
 
-use std::borrow::Cow;
+        self.grids
+            .remove(id)
+            .ok_or_else(|| JsValue::from_str(&format!("Grid {} not found", id)))?;
+        self.ids.retain(|cur| cur != id);
+
+        Ok(())
+    }
+
+    pub fn set_cfg(&mut self, id: &str, cfg: GridCfg, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(), JsValue> {
+        self.grids
+            .get_mut(id)
+            .ok_or_else(|| JsValue::from_str(&format!("Grid {} not found", id)))?
+            .set_cfg(cfg, camera, projection)
+    }
+
+    // Update the grids whenever the camera moved
+    pub fn update(&mut self, camera: &CameraViewPort, projection: &ProjectionType) {
+        for grid in self.grids.values_mut() {
+            grid.update(camera, projection);
+        }
+    }
+
+    pub fn draw(&mut self, camera: &CameraViewPort) -> Result<(), JsValue> {
+        for id in self.ids.iter() {
+            self.grids
+                .get_mut(id)
+                .unwrap_abort()
+                .draw(camera)?;
+        }
+
+        Ok(())
+    }
+}
+
+use std::collections::HashMap;
 
 use crate::math::{
     angle::Angle,
@@ -299,6 +395,57 @@ use cgmath::InnerSpace;
 use cgmath::Vector2;
 use core::ops::Range;
 
+// The view seen from the frame of the grid
+struct GridView<'a> {
+    camera: &'a CameraViewPort,
+    projection: &'a ProjectionType,
+    frame: CooSystem,
+
+    // The field of view and its center given in the frame of the grid.
+    // The ones of the camera are used if the grid is in the frame of the view
+    fov: Option<FieldOfViewType>,
+    center: Vector4<f64>,
+}
+
+impl<'a> GridView<'a> {
+    fn new(camera: &'a CameraViewPort, projection: &'a ProjectionType, frame: Option<CooSystem>) -> Self {
+        let view_frame = camera.get_system();
+        let frame = frame.unwrap_or(*view_frame);
+
+        let center = apply_coo_system(view_frame, &frame, camera.get_center());
+        let fov = if &frame == view_frame {
+            None
+        } else if let Some(vertices) = camera.get_vertices() {
+            let vertices = vertices
+                .iter()
+                .map(|v| apply_coo_system(view_frame, &frame, v))
+                .collect::<Vec<_>>();
+
+            Some(FieldOfViewType::new_polygon(&vertices, &center))
+        } else {
+            Some(FieldOfViewType::Allsky)
+        };
+
+        Self {
+            camera,
+            projection,
+            frame,
+            fov,
+            center,
+        }
+    }
+
+    fn fov(&self) -> &FieldOfViewType {
+        self.fov.as_ref().unwrap_or_else(|| self.camera.get_field_of_view())
+    }
+
+    // Project a position given in the frame of the grid
+    fn to_screen_space(&self, v: &Vector4<f64>) -> Option<Vector2<f64>> {
+        let v = apply_coo_system(&self.frame, self.camera.get_system(), v);
+        self.projection.model_to_screen_space(&v, self.camera)
+    }
+}
+
 #[derive(Debug)]
 struct Label {
     position: Vector2<f64>,
@@ -307,15 +454,15 @@ struct Label {
 }
 impl Label {
     fn meridian(
-        fov: &FieldOfViewType,
-        lon: f64,
+        view: &GridView,
         m1: &Vector3<f64>,
-        camera: &CameraViewPort,
         sp: Option<&Vector2<f64>>,
+        content: String,
         text_renderer: &TextRenderManager,
-        projection: &ProjectionType,
     ) -> Option<Self> {
-        let LonLatT(.., lat) = camera.get_center().lonlat();
+        let fov = view.fov();
+        let camera = view.camera;
+        let LonLatT(.., lat) = view.center.lonlat();
         // Do not plot meridian labels when the center of fov
         // is above 80deg
         if fov.is_allsky() {
@@ -339,7 +486,7 @@ impl Label {
         let m2 = ((m1 + d * 1e-3).normalize()).extend(1.0);
 
         //let s1 = projection.model_to_screen_space(&(system.to_icrs_j2000::<f64>() * m1), camera, reversed_longitude)?;
-        let s1 = projection.model_to_screen_space(&m1.extend(1.0), camera)?;
+        let s1 = view.to_screen_space(&m1.extend(1.0))?;
 
         if !fov.is_allsky() && fov.contains_pole() {
             // If a pole is contained in the view
@@ -363,13 +510,12 @@ impl Label {
             }
         }
 
-        let s2 = projection.model_to_screen_space(&m2, camera)?;
+        let s2 = view.to_screen_space(&m2)?;
 
         //let s2 = projection.model_to_screen_space(&(system.to_icrs_j2000::<f64>() * m2), camera, reversed_longitude)?;
 
         let ds = (s2 - s1).normalize();
 
-        let content = Angle(lon).to_string::<angle::DMS>();
         let position = if !fov.is_allsky() {
             //let dim = ctx2d.measure_text(&content).unwrap_abort();
             let dim = text_renderer.get_width_pixel_size(&content);
@@ -408,17 +554,15 @@ impl Label {
     }
 
     fn parallel(
-        fov: &FieldOfViewType,
-        lat: f64,
+        view: &GridView,
         m1: &Vector3<f64>,
-        camera: &CameraViewPort,
+        content: String,
         // in pixels
         text_renderer: &TextRenderManager,
-        projection: &ProjectionType
     ) -> Option<Self> {
+        let fov = view.fov();
         let mut d = Vector3::new(-m1.z, 0.0, m1.x).normalize();
-        let _system = camera.get_system();
-        let center = camera.get_center().truncate();
+        let center = view.center.truncate();
         //let center = (system.to_gal::<f64>() * camera.get_center()).truncate();
         if center.dot(d) < 0.0 {
             d = -d;
@@ -427,14 +571,13 @@ impl Label {
 
         let s1 =
             //projection.model_to_screen_space(&(system.to_icrs_j2000::<f64>() * m1.extend(1.0)), camera, reversed_longitude)?;
-            projection.model_to_screen_space(&m1.extend(1.0), camera)?;
+            view.to_screen_space(&m1.extend(1.0))?;
         let s2 =
             //projection.model_to_screen_space(&(system.to_icrs_j2000::<f64>() * m2.extend(1.0)), camera, reversed_longitude)?;
-            projection.model_to_screen_space(&m2.extend(1.0), camera)?;
+            view.to_screen_space(&m2.extend(1.0))?;
 
         let ds = (s2 - s1).normalize();
 
-        let content = Angle(lat).to_string::<angle::DMS>();
         let position = if !fov.is_allsky() && !fov.contains_pole() {
             let dim = text_renderer.get_width_pixel_size(&content);
             let k = ds * (dim * 0.5 + 10.0);
@@ -493,11 +636,15 @@ impl Label {
 struct GridLine {
     vertices: Vec<Vector2<f64>>,
     label: Option<Label>,
+    // The text of the label, also written on the border axes
+    content: String,
+    meridian: bool,
 }
 use cgmath::{Rad, Vector3};
 use math::angle::SerializeToString;
 const PI: f64 = std::f64::consts::PI;
 const HALF_PI: f64 = 0.5 * PI;
+use crate::math::projection::{ndc_to_screen_space, screen_to_ndc_space};
 use crate::math::{
     self,
     angle::ArcDeg,
@@ -509,23 +656,24 @@ impl GridLine {
         lon: f64,
         lat: &Range<f64>,
         sp: Option<&Vector2<f64>>,
-        camera: &CameraViewPort,
+        view: &GridView,
         //text_height: f64,
+        labels: &LabelFormatter,
         text_renderer: &TextRenderManager,
-        projection: &ProjectionType
     ) -> Option<Self> {
-        let fov = camera.get_field_of_view();
-        if let Some(p) = fov.intersect_meridian(Rad(lon), camera) {
+        if let Some(p) = view.fov().intersect_meridian(Rad(lon), &view.center) {
             let vertices = crate::line::project_along_longitudes_and_latitudes(
                 lon, lat.start,
                 lon, lat.end,
-                camera,
-                projection,
+                &view.frame,
+                view.camera,
+                view.projection,
             );
 
-            let label = Label::meridian(fov, lon, &p, camera, sp, text_renderer, projection);
+            let content = labels.lon(lon);
+            let label = Label::meridian(view, &p, sp, content.clone(), text_renderer);
 
-            Some(GridLine { vertices, label })
+            Some(GridLine { vertices, label, content, meridian: true })
         } else {
             None
         }
@@ -534,23 +682,23 @@ impl GridLine {
     fn parallel(
         lon: &Range<f64>,
         lat: f64,
-        camera: &CameraViewPort,
+        view: &GridView,
+        labels: &LabelFormatter,
         text_renderer: &TextRenderManager,
-        projection: &ProjectionType
     ) -> Option<Self> {
-        let fov = camera.get_field_of_view();
-
-        if let Some(p) = fov.intersect_parallel(Rad(lat), camera) {
+        if let Some(p) = view.fov().intersect_parallel(Rad(lat), &view.center) {
             let vertices = crate::line::project_along_longitudes_and_latitudes(
                 lon.start, lat,
                 lon.end, lat,
-                camera,
-                projection,
+                &view.frame,
+                view.camera,
+                view.projection,
             );
 
-            let label = Label::parallel(fov, lat, &p, camera, text_renderer, projection);
+            let content = labels.lat(lat);
+            let label = Label::parallel(view, &p, content.clone(), text_renderer);
 
-            Some(GridLine { vertices, label })
+            Some(GridLine { vertices, label, content, meridian: false })
         } else {
             None
         }
@@ -595,39 +743,301 @@ const GRID_STEPS: &[f64] = &[
     0.34906584,
     std::f64::consts::FRAC_PI_4,
 ];
+// The space between the labels of the border axes and the ticks or the frame, in pixels
+const BORDER_LABEL_PADDING: f64 = 4.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    // The normal of the side pointing towards the inside of the frame, in screen space
+    fn inward(&self) -> Vector2<f64> {
+        match self {
+            Side::Left => Vector2::new(1.0, 0.0),
+            Side::Right => Vector2::new(-1.0, 0.0),
+            Side::Top => Vector2::new(0.0, 1.0),
+            Side::Bottom => Vector2::new(0.0, -1.0),
+        }
+    }
+}
+
+// The meridians and parallels labeled where they cross the border of the view, as WCSAxes does
+struct BorderAxes {
+    // in pixels
+    tick_length: f64,
+    labels: GridBorderLabels,
+    label_scale: f64,
+}
+
+impl BorderAxes {
+    // Remove the labels along the lines and give the ticks to draw and the labels placed on the frame
+    fn place(
+        &self,
+        lines: &mut [GridLine],
+        camera: &CameraViewPort,
+        text_renderer: &TextRenderManager,
+    ) -> (Vec<Vector2<f64>>, Vec<Label>) {
+        let size = ndc_to_screen_space(&Vector2::new(1.0, -1.0), camera);
+        let label_size = |content: &str| {
+            Vector2::new(
+                text_renderer.get_width_pixel_size(content),
+                text_renderer.get_height_pixel_size(content),
+            ) * self.label_scale
+        };
+
+        // The frame is shrunk to leave room for the labels written outside of it
+        let margin = match self.labels {
+            GridBorderLabels::Outside => {
+                let max_size = lines.iter().fold(Vector2::new(0.0, 0.0), |max: Vector2<f64>, line| {
+                    let s = label_size(&line.content);
+                    Vector2::new(max.x.max(s.x), max.y.max(s.y))
+                });
+
+                max_size + Vector2::new(2.0, 2.0) * BORDER_LABEL_PADDING
+            }
+            GridBorderLabels::Inside => Vector2::new(0.0, 0.0),
+        };
+        let (min, max) = (margin, size - margin);
+
+        let mut ticks = vec![];
+        let mut crossings = vec![];
+        for (idx, line) in lines.iter_mut().enumerate() {
+            line.label = None;
+
+            let vertices = line
+                .vertices
+                .chunks_exact(2)
+                .map(|s| (ndc_to_screen_space(&s[0], camera), ndc_to_screen_space(&s[1], camera)))
+                .collect::<Vec<_>>();
+
+            for (a, b) in vertices.iter() {
+                for (side, p) in border_crossings(a, b, &min, &max) {
+                    ticks.push(p);
+                    ticks.push(p + side.inward() * self.tick_length);
+
+                    // The meridians are preferably labeled at the top and bottom of the view
+                    // and the parallels on its left and right sides
+                    let preferred = line.meridian == matches!(side, Side::Top | Side::Bottom);
+                    crossings.push((!preferred, idx, side, p));
+                }
+            }
+
+            // The lines do not go over the labels written outside of the frame
+            if self.labels == GridBorderLabels::Outside {
+                line.vertices = vertices
+                    .iter()
+                    .filter_map(|(a, b)| clip_segment(a, b, &min, &max))
+                    .flat_map(|(a, b)| [screen_to_ndc_space(&a, camera), screen_to_ndc_space(&b, camera)])
+                    .collect();
+            }
+        }
+
+        if self.labels == GridBorderLabels::Outside {
+            let corners = [min, Vector2::new(max.x, min.y), max, Vector2::new(min.x, max.y)];
+            for i in 0..4 {
+                ticks.push(corners[i]);
+                ticks.push(corners[(i + 1) % 4]);
+            }
+        }
+
+        crossings.sort_by_key(|(not_preferred, ..)| *not_preferred);
+
+        // Labels overlapping the ones already placed are dropped
+        let mut placed: Vec<(Vector2<f64>, Vector2<f64>)> = vec![];
+        let mut labels = vec![];
+        for (_, idx, side, p) in crossings {
+            let content = &lines[idx].content;
+            let dim = label_size(content);
+            let extent = match side {
+                Side::Left | Side::Right => dim.x,
+                Side::Top | Side::Bottom => dim.y,
+            };
+            let dist = match self.labels {
+                GridBorderLabels::Outside => -(BORDER_LABEL_PADDING + extent * 0.5),
+                GridBorderLabels::Inside => self.tick_length + BORDER_LABEL_PADDING + extent * 0.5,
+            };
+            let position = p + side.inward() * dist;
+
+            let half = dim * 0.5 + Vector2::new(1.0, 1.0) * (BORDER_LABEL_PADDING * 0.5);
+            let (lo, hi) = (position - half, position + half);
+            let inside_view = lo.x >= 0.0 && lo.y >= 0.0 && hi.x <= size.x && hi.y <= size.y;
+            let overlaps = placed
+                .iter()
+                .any(|(l, h)| lo.x < h.x && l.x < hi.x && lo.y < h.y && l.y < hi.y);
+
+            if inside_view && !overlaps {
+                placed.push((lo, hi));
+                labels.push(Label {
+                    position,
+                    content: content.clone(),
+                    rot: 0.0,
+                });
+            }
+        }
+
+        let ticks = ticks.iter().map(|p| screen_to_ndc_space(p, camera)).collect();
+
+        (ticks, labels)
+    }
+}
+
+// The lines are given as pairs of vertices, the consecutive segments sharing a vertex being joined into paths
+fn paths(vertices: &[Vector2<f64>]) -> Vec<Vec<Vector2<f64>>> {
+    let mut paths: Vec<Vec<Vector2<f64>>> = vec![];
+    for segment in vertices.chunks_exact(2) {
+        match paths.last_mut() {
+            Some(path) if path.last().is_some_and(|last| (last - segment[0]).magnitude2() < 1e-12) => {
+                path.push(segment[1]);
+            }
+            _ => paths.push(segment.to_vec()),
+        }
+    }
+
+    paths
+}
+
+// The points where a segment crosses the sides of a frame
+fn border_crossings(a: &Vector2<f64>, b: &Vector2<f64>, min: &Vector2<f64>, max: &Vector2<f64>) -> Vec<(Side, Vector2<f64>)> {
+    let mut crossings = vec![];
+    let sides = [
+        (Side::Left, 0, min.x),
+        (Side::Right, 0, max.x),
+        (Side::Top, 1, min.y),
+        (Side::Bottom, 1, max.y),
+    ];
+    for (side, axis, value) in sides {
+        let (da, db) = (a[axis] - value, b[axis] - value);
+        if da * db < 0.0 {
+            let p = a + (b - a) * (da / (da - db));
+            let other = 1 - axis;
+            if p[other] >= min[other] && p[other] <= max[other] {
+                crossings.push((side, p));
+            }
+        }
+    }
+
+    crossings
+}
+
+// Clip a segment to a frame
+fn clip_segment(a: &Vector2<f64>, b: &Vector2<f64>, min: &Vector2<f64>, max: &Vector2<f64>) -> Option<(Vector2<f64>, Vector2<f64>)> {
+    let d = b - a;
+    let (mut t0, mut t1) = (0.0_f64, 1.0_f64);
+    for axis in 0..2 {
+        for (p, q) in [(-d[axis], a[axis] - min[axis]), (d[axis], max[axis] - a[axis])] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    t0 = t0.max(t);
+                } else {
+                    t1 = t1.min(t);
+                }
+            }
+        }
+    }
+
+    if t0 < t1 {
+        Some((a + d * t0, a + d * t1))
+    } else {
+        None
+    }
+}
+
+// The steps between the lines labeled in decimal degrees, in degrees
+const DECIMAL_STEPS: &[f64] = &[
+    0.00001, 0.00002, 0.00005,
+    0.0001, 0.0002, 0.0005,
+    0.001, 0.002, 0.005,
+    0.01, 0.02, 0.05,
+    0.1, 0.2, 0.5,
+    1.0, 2.0, 5.0,
+    10.0, 20.0, 30.0, 45.0,
+];
+// The steps between the meridians labeled in hours, minutes and seconds, in seconds of time
+const HMS_STEPS: &[f64] = &[
+    0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5,
+    1.0, 2.0, 5.0, 10.0, 15.0, 30.0,
+    60.0, 120.0, 300.0, 600.0, 900.0, 1800.0,
+    3600.0, 7200.0, 10800.0,
+];
+// The steps between the parallels labeled in degrees, arcminutes and arcseconds, in arcseconds
+const DMS_STEPS: &[f64] = &[
+    0.01, 0.02, 0.05, 0.1, 0.2, 0.5,
+    1.0, 2.0, 5.0, 10.0, 15.0, 30.0,
+    60.0, 120.0, 300.0, 600.0, 900.0, 1800.0,
+    3600.0, 7200.0, 18000.0, 36000.0, 54000.0, 108000.0, 162000.0,
+];
+
+// Writes the labels of the meridians and parallels given the steps between them
+struct LabelFormatter {
+    format: GridLabelFormat,
+    digits: Option<u8>,
+    step_lon: f64,
+    step_lat: f64,
+}
+
+impl LabelFormatter {
+    fn lon(&self, lon: f64) -> String {
+        match self.format {
+            GridLabelFormat::Dms => Angle(lon).to_string::<angle::DMS>(),
+            GridLabelFormat::Decimal => angle::to_decimal(lon.rem_euclid(2.0 * PI), self.digits(self.step_lon)),
+            GridLabelFormat::Sexagesimal => angle::to_hms(lon, self.step_lon),
+            GridLabelFormat::Galactic => angle::to_galactic(lon.rem_euclid(2.0 * PI), self.digits(self.step_lon), 'l'),
+        }
+    }
+
+    fn lat(&self, lat: f64) -> String {
+        match self.format {
+            GridLabelFormat::Dms => Angle(lat).to_string::<angle::DMS>(),
+            GridLabelFormat::Decimal => angle::to_decimal(lat, self.digits(self.step_lat)),
+            GridLabelFormat::Sexagesimal => angle::to_dms(lat, self.step_lat, true),
+            GridLabelFormat::Galactic => angle::to_galactic(lat, self.digits(self.step_lat), 'b'),
+        }
+    }
+
+    // The number of decimals of the labels in decimal degrees
+    fn digits(&self, step: f64) -> usize {
+        self.digits
+            .map_or_else(|| angle::num_decimals(step.to_degrees()), |digits| digits as usize)
+    }
+}
+
 fn lines(
-    camera: &CameraViewPort,
+    view: &GridView,
     //text_height: f64,
     text_renderer: &TextRenderManager,
-    projection: &ProjectionType
+    fixed_step: Option<f64>,
+    label_format: GridLabelFormat,
+    label_digits: Option<u8>,
 ) -> Vec<GridLine> {
+    let camera = view.camera;
     // Get the screen position of the nearest pole
-    let _system = camera.get_system();
-    let fov = camera.get_field_of_view();
+    let fov = view.fov();
     let sp = if fov.contains_pole() {
         if fov.contains_north_pole() {
             // Project the pole into the screen
             // This is an information needed
             // for plotting labels
             // screen north pole
-            projection.view_to_screen_space(
-                //&(system.to_icrs_j2000::<f64>() * Vector4::new(0.0, 1.0, 0.0, 1.0)),
-                &Vector4::new(0.0, 1.0, 0.0, 1.0),
-                camera,
-            )
+            view.to_screen_space(&Vector4::new(0.0, 1.0, 0.0, 1.0))
         } else {
             // screen south pole
-            projection.view_to_screen_space(
-                //&(system.to_icrs_j2000::<f64>() * Vector4::new(0.0, -1.0, 0.0, 1.0)),
-                &Vector4::new(0.0, -1.0, 0.0, 1.0),
-                camera,
-            )
+            view.to_screen_space(&Vector4::new(0.0, -1.0, 0.0, 1.0))
         }
     } else {
         None
     };
 
-    let bbox = camera.get_bounding_box();
+    let bbox = fov.get_bounding_box();
 
     /*let step_lon = select_grid_step(
         bbox,
@@ -643,9 +1053,35 @@ fn lines(
     let step_lon_precised = (bbox.get_lon_size() as f64) * step_line_px / (camera.get_width() as f64);
     let step_lat_precised = (bbox.get_lat_size() as f64) * step_line_px / (camera.get_height() as f64);
 
-    // Select the good step with a binary search
-    let step_lon = select_fixed_step(step_lon_precised);
-    let step_lat = select_fixed_step(step_lat_precised);
+    let (step_lon, step_lat) = if let Some(step) = fixed_step {
+        (
+            limit_fixed_step(step, bbox.get_lon_size()),
+            limit_fixed_step(step, bbox.get_lat_size()),
+        )
+    } else {
+        // Select the good step with a binary search among the ones
+        // giving round labels in the format chosen
+        match label_format {
+            GridLabelFormat::Dms => (
+                select_fixed_step(GRID_STEPS, step_lon_precised),
+                select_fixed_step(GRID_STEPS, step_lat_precised),
+            ),
+            GridLabelFormat::Decimal | GridLabelFormat::Galactic => (
+                select_fixed_step(DECIMAL_STEPS, step_lon_precised.to_degrees()).to_radians(),
+                select_fixed_step(DECIMAL_STEPS, step_lat_precised.to_degrees()).to_radians(),
+            ),
+            GridLabelFormat::Sexagesimal => (
+                (select_fixed_step(HMS_STEPS, step_lon_precised.to_degrees() * 240.0) / 240.0).to_radians(),
+                (select_fixed_step(DMS_STEPS, step_lat_precised.to_degrees() * 3600.0) / 3600.0).to_radians(),
+            ),
+        }
+    };
+    let labels = LabelFormatter {
+        format: label_format,
+        digits: label_digits,
+        step_lon,
+        step_lat,
+    };
 
     let mut lines = vec![];
     // Add meridians
@@ -657,7 +1093,7 @@ fn lines(
 
     while theta < stop_theta {
         if let Some(line) =
-            GridLine::meridian(theta, &bbox.get_lat(), sp.as_ref(), camera, text_renderer, projection)
+            GridLine::meridian(theta, &bbox.get_lat(), sp.as_ref(), view, &labels, text_renderer)
         {
             lines.push(line);
         }
@@ -676,7 +1112,7 @@ fn lines(
         stop_alpha -= 1e-3;
     }*/
     while alpha < stop_alpha {
-        if let Some(line) = GridLine::parallel(&bbox.get_lon(), alpha, camera, text_renderer, projection) {
+        if let Some(line) = GridLine::parallel(&bbox.get_lon(), alpha, view, &labels, text_renderer) {
             lines.push(line);
         }
         alpha += step_lat;
@@ -707,19 +1143,31 @@ fn lines(
     step
 }*/
 
-fn select_fixed_step(fov: f64) -> f64 {
-    match GRID_STEPS.binary_search_by(|v| {
+// The maximum number of meridians or parallels drawn with a step given by the user
+const MAX_NUM_LINES_FIXED_STEP: f64 = 64.0;
+
+// A step given by the user is doubled as long as it gives too many lines in the field of view
+fn limit_fixed_step(mut step: f64, fov: f64) -> f64 {
+    while fov / step > MAX_NUM_LINES_FIXED_STEP {
+        step *= 2.0;
+    }
+
+    step
+}
+
+fn select_fixed_step(steps: &[f64], fov: f64) -> f64 {
+    match steps.binary_search_by(|v| {
         v.partial_cmp(&fov).expect("Couldn't compare values, maybe because the fov given is NaN")
     }) {
-        Ok(idx) => GRID_STEPS[idx],
+        Ok(idx) => steps[idx],
         Err(idx) => {
             if idx == 0 {
-                GRID_STEPS[0]
-            } else if idx == GRID_STEPS.len() {
-                GRID_STEPS[idx - 1]
+                steps[0]
+            } else if idx == steps.len() {
+                steps[idx - 1]
             } else {
-                let a = GRID_STEPS[idx];
-                let b = GRID_STEPS[idx - 1];
+                let a = steps[idx];
+                let b = steps[idx - 1];
 
                 if a - fov > fov - b {
                     b
@@ -730,3 +1178,24 @@ fn select_fixed_step(fov: f64) -> f64 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{border_crossings, clip_segment};
+    use cgmath::Vector2;
+
+    #[test]
+    fn border_axes_geometry() {
+        let (min, max) = (Vector2::new(10.0, 10.0), Vector2::new(110.0, 60.0));
+
+        // A segment going through the frame from its left side to its bottom one
+        let (a, b) = (Vector2::new(0.0, 20.0), Vector2::new(100.0, 70.0));
+        let crossings = border_crossings(&a, &b, &min, &max);
+        assert_eq!(crossings.len(), 2);
+        assert_eq!(crossings[0].1, Vector2::new(10.0, 25.0));
+        assert_eq!(crossings[1].1, Vector2::new(80.0, 60.0));
+
+        assert_eq!(clip_segment(&a, &b, &min, &max), Some((Vector2::new(10.0, 25.0), Vector2::new(80.0, 60.0))));
+        assert_eq!(clip_segment(&Vector2::new(0.0, 0.0), &Vector2::new(5.0, 100.0), &min, &max), None);
+    }
+}
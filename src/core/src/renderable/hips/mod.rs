@@ -35,6 +35,9 @@ use crate::time::Time;
 use crate::survey::buffer::ImageSurveyTextures;
 use crate::survey::texture::Texture;
 use crate::survey::view::HEALPixCellsInView;
+use crate::renderable::moc::RangesTexture;
+use al_api::coo_system::CooSystem;
+use al_core::shader::{SendUniforms, ShaderBound};
 use raytracing::RayTracer;
 use uv::{TileCorner, TileUVW};
 
@@ -44,14 +47,53 @@ use std::fmt::Debug;
 use wasm_bindgen::JsValue;
 
 // Identity matrix
-const ID: &Matrix4<f64> = &Matrix4::new(
+pub(crate) const ID: &Matrix4<f64> = &Matrix4::new(
     1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
 );
 // Longitude reversed identity matrix
-const ID_R: &Matrix4<f64> = &Matrix4::new(
+pub(crate) const ID_R: &Matrix4<f64> = &Matrix4::new(
     -1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
 );
 
+/// A MOC masking the display of a HiPS layer
+pub struct MocMask {
+    ranges: RangesTexture,
+    // 0: no mask, 1: the pixels outside the MOC are discarded, 2: the ones inside
+    mode: i32,
+}
+
+impl MocMask {
+    /// Mask a layer with a MOC given in ICRS
+    ///
+    /// # Arguments
+    ///
+    /// * `moc` - The MOC. The layer is shown only over it
+    /// * `inverted` - Show the layer only outside of the MOC instead
+    pub fn new(gl: &WebGlContext, moc: &HEALPixCoverage, inverted: bool) -> Result<Self, JsValue> {
+        let ranges = RangesTexture::new(gl, moc)?;
+        let mode = if inverted { 2 } else { 1 };
+
+        Ok(Self { ranges, mode })
+    }
+
+    /// A mask letting all the pixels of a layer through
+    ///
+    /// The HiPS shaders need a texture of ranges to be bound even if they are not masked
+    pub fn none(gl: &WebGlContext) -> Result<Self, JsValue> {
+        let ranges = RangesTexture::new(gl, &HEALPixCoverage::empty())?;
+
+        Ok(Self { ranges, mode: 0 })
+    }
+}
+
+impl SendUniforms for MocMask {
+    fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
+        shader
+            .attach_uniforms_from(&self.ranges)
+            .attach_uniform("mask_mode", &self.mode)
+    }
+}
+
 const M: f64 = 280.0*280.0;
 const N: f64 = 150.0*150.0;
 const RAP: f64 = 0.7;
@@ -352,6 +394,8 @@ pub struct HiPS {
     //#[cfg(feature = "webgl1")]
     // layout (location = 5) in float m1;
     m1: Vec<f32>,
+    // layout (location = 6) in vec3 hips_pos;
+    hips_pos: Vec<f32>,
 
     idx_vertices: Vec<u16>,
 
@@ -392,6 +436,7 @@ impl HiPS {
         let time_tile_received = vec![];
         let m0 = vec![];
         let m1 = vec![];
+        let hips_pos = vec![];
         let idx_vertices = vec![];
 
         #[cfg(feature = "webgl2")]
@@ -432,6 +477,12 @@ impl HiPS {
                 WebGl2RenderingContext::DYNAMIC_DRAW,
                 VecData::<f32>(&m1),
             )
+            .add_array_buffer_single(
+                3,
+                "hips_pos",
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData::<f32>(&hips_pos),
+            )
             // Set the element buffer
             .add_element_buffer(
                 WebGl2RenderingContext::DYNAMIC_DRAW,
@@ -476,6 +527,12 @@ impl HiPS {
                 WebGl2RenderingContext::DYNAMIC_DRAW,
                 VecData::<f32>(&m1),
             )
+            .add_array_buffer(
+                3,
+                "hips_pos",
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData::<f32>(&hips_pos),
+            )
             // Set the element buffer
             .add_element_buffer(
                 WebGl2RenderingContext::DYNAMIC_DRAW,
@@ -513,6 +570,7 @@ impl HiPS {
             time_tile_received,
             m0,
             m1,
+            hips_pos,
 
             idx_vertices,
             min_depth_tile,
@@ -606,6 +664,7 @@ impl HiPS {
         self.time_tile_received.clear();
         self.m0.clear();
         self.m1.clear();
+        self.hips_pos.clear();
         self.idx_vertices.clear();
 
         let cfg = self.textures.config();
@@ -698,7 +757,8 @@ impl HiPS {
                     for i in 0..n_vertices_per_segment {
                         for j in 0..n_vertices_per_segment {
                             let id_vertex_0 = (j + i * n_vertices_per_segment) as usize;
-                            let world_pos: Vector4<f64> = v2w * ll[id_vertex_0].vector::<Vector4<f64>>();
+                            let hips_pos = ll[id_vertex_0].vector::<Vector4<f64>>();
+                            let world_pos: Vector4<f64> = v2w * hips_pos;
 
                             let ndc_pos = if let Some(ndc_pos) = projection.world_to_normalized_device_space(&world_pos, camera) {
                                 ndc_pos
@@ -728,6 +788,7 @@ impl HiPS {
                                 self.time_tile_received.push(start_time);
                                 self.m0.push(miss_0);
                                 self.m1.push(miss_1);
+                                self.hips_pos.extend([hips_pos.x as f32, hips_pos.y as f32, hips_pos.z as f32]);
     
                                 // push to idx_vertices
                                 if i > 0 && j > 0 {
@@ -808,6 +869,11 @@ impl HiPS {
             WebGl2RenderingContext::DYNAMIC_DRAW,
             VecData(&self.m1),
         )
+        .update_array(
+            "hips_pos",
+            WebGl2RenderingContext::DYNAMIC_DRAW,
+            VecData(&self.hips_pos),
+        )
         .update_element_array(
             WebGl2RenderingContext::DYNAMIC_DRAW,
             VecData(&self.idx_vertices),
@@ -905,6 +971,7 @@ impl HiPS {
         camera: &CameraViewPort,
         raytracer: &RayTracer,
         cfg: &ImageMetadata,
+        mask: &MocMask,
     ) -> Result<(), JsValue> {
         // Get the coo system transformation matrix
         let selected_frame = camera.get_system();
@@ -919,6 +986,8 @@ impl HiPS {
         // Retrieve the model and inverse model matrix
         let w2v = c * (*camera.get_w2m()) * rl;
         let v2w = w2v.transpose();
+        // The masks are given in ICRS
+        let mask_model = hips_frame.to::<f64>(&CooSystem::ICRSJ2000);
 
         let raytracing = raytracer.is_rendering(camera);
         let longitude_reversed = camera.get_longitude_reversed();
@@ -962,7 +1031,9 @@ impl HiPS {
                     .attach_uniform("inv_model", &v2w)
                     .attach_uniform("current_time", &utils::get_current_time())
                     .attach_uniform("opacity", &opacity)
-                    .attach_uniforms_from(colormaps);
+                    .attach_uniforms_from(colormaps)
+                    .attach_uniforms_from(mask)
+                    .attach_uniform("mask_model", mask_model);
     
                 raytracer.draw(&shader);
             } else {
@@ -1007,6 +1078,8 @@ impl HiPS {
                     .attach_uniform("current_time", &utils::get_current_time())
                     .attach_uniform("opacity", &opacity)
                     .attach_uniforms_from(colormaps)
+                    .attach_uniforms_from(mask)
+                    .attach_uniform("mask_model", mask_model)
                     .bind_vertex_array_object_ref(&self.vao)
                     .draw_elements_with_i32(
                         WebGl2RenderingContext::TRIANGLES,
@@ -262,6 +262,17 @@ impl TextRenderManager {
 
         w as f64
     }
+
+    pub fn get_height_pixel_size(&self, content: &str) -> f64 {
+        let mut h = 0;
+        for c in content.chars() {
+            if let Some(l) = self.letters.get(&c) {
+                h = std::cmp::max(h, l.h);
+            }
+        }
+
+        h as f64
+    }
 }
 
 impl RenderManager for TextRenderManager {
@@ -1,173 +1,281 @@
-use al_core::webgl_ctx::WebGl2Context;
-use al_core::VertexArrayObject;
 use al_core::shader::Shader;
+use al_core::webgl_ctx::WebGlContext;
+use al_core::VertexArrayObject;
 
+use al_api::color::ColorRGB;
+use al_core::VecData;
+use cgmath::{InnerSpace, Vector2};
+use wasm_bindgen::JsValue;
+use web_sys::WebGl2RenderingContext;
+
+use crate::camera::CameraViewPort;
+use crate::math::projection::{ndc_to_screen_space, screen_to_ndc_space};
 
-use super::RenderManager;
+// The maximum number of dashes and gaps of a dash pattern
+const MAX_NUM_DASH: usize = 8;
+// The miter of the joins is limited to that factor of the line width
+const MITER_LIMIT: f64 = 2.0;
+// The lines are widened by that many pixels on each side to fade their edges out
+const AA_FEATHER: f64 = 1.0;
+
+/// Check a dash pattern fits in the ones given to the shader, a pattern
+/// of odd length counting twice as it is repeated
+pub fn check_dash(dash: &[f32]) -> Result<(), JsValue> {
+    let len = if dash.len() % 2 == 1 { 2 * dash.len() } else { dash.len() };
+    if len > MAX_NUM_DASH {
+        Err(JsValue::from_str(&format!(
+            "The dash pattern {:?} is too long, it can have at most {} lengths or {} if their number is odd",
+            dash,
+            MAX_NUM_DASH,
+            MAX_NUM_DASH / 2
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+pub struct LineStyle {
+    /// The width of the line in pixels
+    pub width: f32,
+    /// The lengths of the dashes and of the gaps between them in pixels, alternatively.
+    /// The line is solid if empty
+    pub dash: Vec<f32>,
+    pub color: ColorRGB,
+    pub opacity: f32,
+}
 
 struct LineMeta {
-    color: Color,
-    thickness: f32,
+    color: ColorRGB,
+    opacity: f32,
+    half_width: f32,
+    dash: [f32; MAX_NUM_DASH],
+    num_dash: i32,
     off_idx: usize,
     num_idx: usize,
 }
 
 pub struct RasterizedLinesRenderManager {
-    gl: WebGl2Context,
+    gl: WebGlContext,
     shader: Shader,
     vao: VertexArrayObject,
 
     vertices: Vec<f32>,
-    indices: Vec<u16>,
+    indices: Vec<u32>,
     meta: Vec<LineMeta>,
 }
-use wasm_bindgen::JsValue;
-use cgmath::Vector2;
-use al_core::VecData;
-use web_sys::WebGl2RenderingContext;
-use crate::Color;
-use crate::camera::CameraViewPort;
-
-
-use lyon::math::point;
-use lyon::path::Path;
-use lyon::tessellation::*;
 
 impl RasterizedLinesRenderManager {
     /// Init the buffers, VAO and shader
-    pub fn new(gl: WebGl2Context, camera: &CameraViewPort) -> Result<Self, JsValue> {
-        // Create the VAO for the screen
-        #[cfg(feature = "webgl1")]
+    pub fn new(gl: &WebGlContext) -> Result<Self, JsValue> {
         let shader = Shader::new(
-            &gl,
-            include_str!("../shaders/webgl1/line/line_vertex.glsl"),
-            include_str!("../shaders/webgl1/line/line_frag.glsl")
+            gl,
+            include_str!("../../../glsl/webgl2/line/line_vertex.glsl"),
+            include_str!("../../../glsl/webgl2/line/line_frag.glsl"),
         )?;
-        #[cfg(feature = "webgl2")]
-        let shader = Shader::new(
-            &gl,
-            include_str!("../shaders/webgl2/line/line_vertex.glsl"),
-            include_str!("../shaders/webgl2/line/line_frag.glsl")
-        )?;
-        let mut vao = VertexArrayObject::new(&gl);
-
-        shader
-            .bind(&gl)
-                .bind_vertex_array_object(&mut vao)
-                    .add_array_buffer(
-                        2 * std::mem::size_of::<f32>(),
-                        &[2],
-                        &[0],
-                        WebGl2RenderingContext::STREAM_DRAW,
-                        VecData::<f32>(&vec![]),
-                    )
-                    // Set the element buffer
-                    .add_element_buffer(
-                        WebGl2RenderingContext::STREAM_DRAW,
-                        VecData::<u16>(&vec![]),
-                    )
-                // Unbind the buffer
-                .unbind();
-        let meta = vec![];
-        Ok(
-            Self {
-                gl,
-                shader,
-                vao,
-                meta,
-                vertices: vec![],
-                indices: vec![],
-            }
-        )
+
+        let vertices = vec![];
+        let indices = vec![];
+        let mut vao = VertexArrayObject::new(gl);
+        vao.bind_for_update()
+            .add_array_buffer(
+                "vertices",
+                4 * std::mem::size_of::<f32>(),
+                &[2, 1, 1],
+                &[0, 2 * std::mem::size_of::<f32>(), 3 * std::mem::size_of::<f32>()],
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData::<f32>(&vertices),
+            )
+            // Set the element buffer
+            .add_element_buffer(
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData::<u32>(&indices),
+            )
+            .unbind();
+
+        Ok(Self {
+            gl: gl.clone(),
+            shader,
+            vao,
+            vertices,
+            indices,
+            meta: vec![],
+        })
     }
 
-    pub fn add_path(&mut self, path: &[Vector2<f32>], thickness: f32, color: &Color) {
-        let mut builder = Path::builder();
-        if path.is_empty() {
+    /// Add a path to draw
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The vertices of the path in normalized device coordinates
+    /// * `closed` - Join the last vertex to the first one
+    /// * `style` - The width, dashes and color of the line
+    pub fn add_path(&mut self, path: &[Vector2<f64>], closed: bool, style: &LineStyle, camera: &CameraViewPort) {
+        // The line is tessellated in screen space so that its width is given in pixels
+        let mut points: Vec<Vector2<f64>> = Vec::with_capacity(path.len() + 1);
+        for p in path {
+            let p = ndc_to_screen_space(p, camera);
+            if points.last().is_none_or(|last| (p - last).magnitude2() > 1e-12) {
+                points.push(p);
+            }
+        }
+        if closed && points.len() > 2 {
+            points.push(points[0]);
+        }
+        if points.len() < 2 {
             return;
         }
 
-        builder.begin(point(path[0].x, path[0].y));
+        // The edges of the line are anti-aliased in the fragment shader
+        let half_width = 0.5 * style.width as f64 + AA_FEATHER;
+        let normal = |a: &Vector2<f64>, b: &Vector2<f64>| {
+            let d = (b - a).normalize();
+            Vector2::new(-d.y, d.x)
+        };
+        let n = points.len();
+
+        let first_vertex = (self.vertices.len() / 4) as u32;
+        let mut l = 0.0;
+        for i in 0..n {
+            // The segments before and after the vertex. The ones of the ends of a closed path join
+            let prev = if i > 0 {
+                Some(normal(&points[i - 1], &points[i]))
+            } else if closed && n > 2 {
+                Some(normal(&points[n - 2], &points[0]))
+            } else {
+                None
+            };
+            let next = if i < n - 1 {
+                Some(normal(&points[i], &points[i + 1]))
+            } else if closed && n > 2 {
+                Some(normal(&points[0], &points[1]))
+            } else {
+                None
+            };
+
+            // Miter join
+            let offset = match (prev, next) {
+                (Some(prev), Some(next)) => {
+                    let miter = prev + next;
+                    if miter.magnitude2() < 1e-12 {
+                        next * half_width
+                    } else {
+                        let miter = miter.normalize();
+                        let scale = (1.0 / miter.dot(next)).min(MITER_LIMIT);
+                        miter * (half_width * scale)
+                    }
+                }
+                (Some(n), None) | (None, Some(n)) => n * half_width,
+                (None, None) => unreachable!(),
+            };
+
+            if i > 0 {
+                l += (points[i] - points[i - 1]).magnitude();
+            }
 
-        for p in path.iter().skip(1) {
-            builder.line_to(point(p.x, p.y));
+            // The distance to the middle of the line is given across it
+            for (p, d) in [(points[i] + offset, half_width), (points[i] - offset, -half_width)] {
+                let p = screen_to_ndc_space(&p, camera);
+                self.vertices.extend([p.x as f32, p.y as f32, l as f32, d as f32]);
+            }
         }
 
-        builder.end(true);
-        let path = builder.build();
-        // Let's use our own custom vertex type instead of the default one.
-        // Will contain the result of the tessellation.
-        let mut geometry: VertexBuffers<[f32; 2], u16> = VertexBuffers::new();
-        let mut tessellator = FillTessellator::new();
-        {
-            // Compute the tessellation.
-            tessellator.tessellate_path(
-                &path,
-                &FillOptions::default(),
-                &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| {
-                    vertex.position().to_array()
-                }),
-            ).unwrap_abort();
+        let off_idx = self.indices.len();
+        for i in 0..(n as u32 - 1) {
+            let idx = first_vertex + 2 * i;
+            self.indices.extend([idx, idx + 1, idx + 2, idx + 1, idx + 3, idx + 2]);
         }
-        let num_vertices = (self.vertices.len() / 2) as u16;
 
-        self.vertices.extend(geometry.vertices.iter().flatten());
-        for i in geometry.indices.iter_mut() {
-            *i += num_vertices;
+        // A dash pattern of odd length is repeated, as for the canvas API
+        let mut dash = style.dash.iter().copied().filter(|d| *d >= 0.0).collect::<Vec<_>>();
+        if dash.len() % 2 == 1 {
+            dash.extend_from_within(..);
         }
+        // The length of the pattern has been checked by `check_dash`
+        let num_dash = if dash.iter().sum::<f32>() > 0.0 { dash.len() } else { 0 };
+        let mut dashes = [0.0; MAX_NUM_DASH];
+        dashes[..num_dash].copy_from_slice(&dash[..num_dash]);
 
-        let num_idx = geometry.indices.len();
-        let off_idx = self.indices.len();
-        self.indices.extend(geometry.indices);
-
-        self.meta.push(
-            LineMeta {
-                off_idx,
-                num_idx,
-                thickness,
-                color: color.clone(),
+        let num_idx = self.indices.len() - off_idx;
+        // Paths of the same style are drawn at once
+        if let Some(last) = self.meta.last_mut() {
+            let ColorRGB { r, g, b } = style.color;
+            let same_style = last.color.r == r && last.color.g == g && last.color.b == b
+                && last.opacity == style.opacity
+                && last.half_width == 0.5 * style.width
+                && last.num_dash == num_dash as i32
+                && last.dash == dashes;
+
+            if same_style {
+                last.num_idx += num_idx;
+                return;
             }
-        );
+        }
+
+        self.meta.push(LineMeta {
+            color: style.color,
+            opacity: style.opacity,
+            half_width: 0.5 * style.width,
+            dash: dashes,
+            num_dash: num_dash as i32,
+            off_idx,
+            num_idx,
+        });
     }
-}
 
-impl RenderManager for RasterizedLinesRenderManager {
-    fn begin_frame(&mut self) {
+    pub fn begin_frame(&mut self) {
         self.vertices.clear();
         self.indices.clear();
 
         self.meta.clear();
     }
 
-    fn end_frame(&mut self) {
+    pub fn end_frame(&mut self) {
         // update to the GPU
         self.vao.bind_for_update()
-            .update_array(0, WebGl2RenderingContext::STREAM_DRAW, VecData(&self.vertices))
-            .update_element_array(WebGl2RenderingContext::STREAM_DRAW, VecData(&self.indices));
+            .update_array(
+                "vertices",
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData(&self.vertices),
+            )
+            .update_element_array(
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData(&self.indices),
+            );
     }
 
-    fn draw(&mut self, window_size: &Vector2<f32>) -> Result<(), JsValue> {
+    pub fn draw(&self) {
+        if self.meta.is_empty() {
+            return;
+        }
+
         self.gl.enable(WebGl2RenderingContext::BLEND);
-        self.gl.blend_func(WebGl2RenderingContext::ONE, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA); // premultiplied alpha
+        self.gl.blend_func_separate(
+            WebGl2RenderingContext::SRC_ALPHA,
+            WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            WebGl2RenderingContext::ONE,
+            WebGl2RenderingContext::ONE,
+        );
+        // The strips of the lines can be either clockwise or counter-clockwise
+        self.gl.disable(WebGl2RenderingContext::CULL_FACE);
 
         let shader = self.shader.bind(&self.gl);
-        self.vao.bind(&shader);
-
         for meta in self.meta.iter() {
             shader
-                .attach_uniform("u_color", &meta.color) // Strengh of the kernel
-                .attach_uniform("u_screen_size", window_size);
-
-            self.gl.draw_elements_with_i32(
-                WebGl2RenderingContext::TRIANGLES,
-                meta.num_idx as i32,
-                WebGl2RenderingContext::UNSIGNED_SHORT,
-                (meta.off_idx as i32) * (std::mem::size_of::<u16>() as i32)
-            );
+                .attach_uniform("u_color", &meta.color)
+                .attach_uniform("u_opacity", &meta.opacity)
+                .attach_uniform("u_half_width", &meta.half_width)
+                .attach_uniform("u_dash", &&meta.dash[..])
+                .attach_uniform("u_num_dash", &meta.num_dash)
+                .bind_vertex_array_object_ref(&self.vao)
+                    .draw_elements_with_i32(
+                        WebGl2RenderingContext::TRIANGLES,
+                        Some(meta.num_idx as i32),
+                        WebGl2RenderingContext::UNSIGNED_INT,
+                        (meta.off_idx * std::mem::size_of::<u32>()) as i32,
+                    );
         }
 
+        self.gl.enable(WebGl2RenderingContext::CULL_FACE);
         self.gl.disable(WebGl2RenderingContext::BLEND);
-
-        Ok(())
     }
-}
\ No newline at end of file
+}
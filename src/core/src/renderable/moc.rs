@@ -1,10 +1,12 @@
 use crate::{healpix::{
     coverage::HEALPixCoverage,
-    cell::HEALPixCell
+    time_coverage::{SpaceTimeCoverage, TimeCoverage},
 }, shader::ShaderId, math::angle::Angle, CameraViewPort, ShaderManager};
-use al_core::{WebGlContext, VertexArrayObject, VecData};
-use moclib::{moc::{RangeMOCIterator, RangeMOCIntoIterator}, elem::cell::Cell};
+use crate::renderable::lines::{LineStyle, RasterizedLinesRenderManager};
+use al_core::shader::{SendUniforms, ShaderBound};
+use al_core::{image::format::RGBA32UI, Texture2D, WebGlContext, VertexArrayObject, SliceData, VecData};
 use std::{borrow::Cow, collections::HashMap};
+use wasm_bindgen::JsValue;
 use web_sys::WebGl2RenderingContext;
 
 use al_api::coo_system::CooSystem;
@@ -12,17 +14,25 @@ use al_api::coo_system::CooSystem;
 type MOCIdx = String;
 use crate::Abort;
 pub struct MOC {
-    vao: VertexArrayObject,
-    num_indices: Vec<usize>,
-    first_idx: Vec<usize>,
-    position: Vec<f32>,
-    indices: Vec<u32>,
+    // The perimeters of the MOCs
+    lines: RasterizedLinesRenderManager,
+
+    // A mesh covering the view, used to fill the MOCs when the raytracer is not rendering
+    screen_vao: VertexArrayObject,
+    num_screen_indices: usize,
 
     mocs: HashMap<MOCIdx, HierarchicalHpxCoverage>,
+    // The HEALPix ranges of the MOCs sent to the GPU for filling them
+    ranges: HashMap<MOCIdx, RangesTexture>,
 
-    adaptative_mocs: HashMap<MOCIdx, Option<HEALPixCoverage>>,
+    // The perimeters of the part of the MOCs in the view, as closed polylines of ICRS vertices
+    perimeters: HashMap<MOCIdx, Vec<Vec<Vector4<f64>>>>,
     params: HashMap<MOCIdx, al_api::moc::MOC>,
 
+    // Space-Time MOCs whose projection on the time window is displayed
+    st_mocs: HashMap<MOCIdx, SpaceTimeCoverage>,
+    time_window: Option<TimeCoverage>,
+
     layers: Vec<MOCIdx>,
     view: HEALPixCellsInView,
 
@@ -30,150 +40,7 @@ pub struct MOC {
 }
 
 use crate::survey::view::HEALPixCellsInView;
-use cgmath::Vector2;
-
-fn path_along_edge(cell: &HEALPixCell, n_segment_by_side: usize, camera: &CameraViewPort, idx_off: &mut u32, projection: &ProjectionType) -> Option<(Vec<f32>, Vec<u32>)> {
-    let vertices = cell
-        .path_along_cell_edge(n_segment_by_side as u32)
-        .iter()
-        .filter_map(|(lon, lat)| {
-            let xyzw = crate::math::lonlat::radec_to_xyzw(Angle(*lon), Angle(*lat));
-            let xyzw = crate::coosys::apply_coo_system(&CooSystem::ICRSJ2000, camera.get_system(), &xyzw);
-            
-            projection.model_to_normalized_device_space(&xyzw, camera)
-                .map(|v| [v.x as f32, v.y as f32])
-        })
-        .flatten()
-        .collect::<Vec<_>>();
-
-    let cell_inside = vertices.len() == 2*4*n_segment_by_side;
-
-    let invalid_tri = |tri_ccw: bool, reversed_longitude: bool| -> bool {
-        (!reversed_longitude && !tri_ccw) || (reversed_longitude && tri_ccw)
-    };
-    let reversed_longitude = camera.get_longitude_reversed();
-
-    if cell_inside {
-        let c0 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[0] as f64, vertices[1] as f64), camera);
-        let c1 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[2*n_segment_by_side] as f64, vertices[2*n_segment_by_side + 1] as f64), camera);
-        let c2 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[2*2*n_segment_by_side] as f64, vertices[2*2*n_segment_by_side + 1] as f64), camera);
-        let c3 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[3*2*n_segment_by_side] as f64, vertices[3*2*n_segment_by_side + 1] as f64), camera);
-
-        let first_tri_ccw = crate::math::vector::ccw_tri(&c0, &c1, &c2);
-        let second_tri_ccw = crate::math::vector::ccw_tri(&c1, &c2, &c3);
-        let third_tri_ccw = crate::math::vector::ccw_tri(&c2, &c3, &c0);
-        let fourth_tri_ccw = crate::math::vector::ccw_tri(&c3, &c0, &c1);
-
-        let invalid_cell = invalid_tri(first_tri_ccw, reversed_longitude) || invalid_tri(second_tri_ccw, reversed_longitude) || invalid_tri(third_tri_ccw, reversed_longitude) || invalid_tri(fourth_tri_ccw, reversed_longitude);
-
-        if !invalid_cell {
-            let vx = [c0.x, c1.x, c2.x, c3.x];
-            let vy = [c0.y, c1.y, c2.y, c3.y];
-
-            let projeted_cell = HEALPixCellProjeted {
-                ipix: cell.idx(),
-                vx,
-                vy
-            };
-
-            if crate::survey::view::project(projeted_cell, camera, projection).is_none() {
-                None
-            } else {
-                // Generate the iterator: idx_off + 1, idx_off + 1, .., idx_off + 4*n_segment - 1, idx_off + 4*n_segment - 1
-                let num_vertices = 4 * n_segment_by_side as u32;
-                let indices = std::iter::once(*idx_off as u32)
-                    .chain((2..2*num_vertices).map(|idx| idx / 2 + *idx_off))
-                    .chain(std::iter::once(*idx_off as u32))
-                    .collect();
-                *idx_off += num_vertices;
-
-                Some((vertices, indices))
-            }
-        } else {
-            None
-        }
-    } else {
-        None
-    }
-}
-use al_api::cell::HEALPixCellProjeted;
-pub fn rasterize_hpx_cell(cell: &HEALPixCell, n_segment_by_side: usize, camera: &CameraViewPort, idx_off: &mut u32, projection: &ProjectionType) -> Option<(Vec<f32>, Vec<u32>)> {
-    let n_vertices_per_segment = n_segment_by_side + 1;
-
-    let vertices = cell
-        .grid(n_segment_by_side as u32)
-        .iter()
-        .filter_map(|(lon, lat)| {
-            let xyzw = crate::math::lonlat::radec_to_xyzw(Angle(*lon), Angle(*lat));
-            let xyzw = crate::coosys::apply_coo_system(&CooSystem::ICRSJ2000, camera.get_system(), &xyzw);
-
-            projection.model_to_normalized_device_space(&xyzw, camera)
-                .map(|v| {
-                    [v.x as f32, v.y as f32]
-                })
-        })
-        .flatten()
-        .collect::<Vec<_>>();
-
-    let cell_inside = vertices.len() == 2*(n_segment_by_side+1)*(n_segment_by_side+1);
-
-    if cell_inside {
-        // Generate the iterator: idx_off + 1, idx_off + 1, .., idx_off + 4*n_segment - 1, idx_off + 4*n_segment - 1
-        let mut indices = Vec::with_capacity(n_segment_by_side * n_segment_by_side * 6);
-        let num_vertices = (n_segment_by_side+1)*(n_segment_by_side+1);
-
-        let longitude_reversed = camera.get_longitude_reversed();
-        let invalid_tri = |tri_ccw: bool, reversed_longitude: bool| -> bool {
-            (!reversed_longitude && !tri_ccw) || (reversed_longitude && tri_ccw)
-        };
-
-        for i in 0..n_segment_by_side {
-            for j in 0..n_segment_by_side {
-                let idx_0 = j + i * n_vertices_per_segment;
-                let idx_1 = j + 1 + i * n_vertices_per_segment;
-                let idx_2 = j + (i + 1) * n_vertices_per_segment;
-                let idx_3 = j + 1 + (i + 1) * n_vertices_per_segment;
-
-                let c0 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[2*idx_0] as f64, vertices[2*idx_0 + 1] as f64), camera);
-                let c1 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[2*idx_1] as f64, vertices[2*idx_1 + 1] as f64), camera);
-                let c2 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[2*idx_2] as f64, vertices[2*idx_2 + 1] as f64), camera);
-                let c3 = crate::math::projection::ndc_to_screen_space(&Vector2::new(vertices[2*idx_3] as f64, vertices[2*idx_3 + 1] as f64), camera);
-
-                let first_tri_ccw = !crate::math::vector::ccw_tri(&c0, &c1, &c2);
-                let second_tri_ccw = !crate::math::vector::ccw_tri(&c1, &c3, &c2);
-
-                if invalid_tri(first_tri_ccw, longitude_reversed) || invalid_tri(second_tri_ccw, longitude_reversed) {
-                    return None;
-                }
-
-                let vx = [c0.x, c1.x, c2.x, c3.x];
-                let vy = [c0.y, c1.y, c2.y, c3.y];
-
-                let projeted_cell = HEALPixCellProjeted {
-                    ipix: cell.idx(),
-                    vx,
-                    vy
-                };
-
-                crate::survey::view::project(projeted_cell, camera, projection)?;
-
-                indices.push(*idx_off + idx_0 as u32);
-                indices.push(*idx_off + idx_1 as u32);
-                indices.push(*idx_off + idx_2 as u32);
-
-                indices.push(*idx_off + idx_1 as u32);
-                indices.push(*idx_off + idx_3 as u32);
-                indices.push(*idx_off + idx_2 as u32);
-            }
-        }
-
-        *idx_off += num_vertices as u32;
-
-        Some((vertices, indices))
-    } else {
-        None
-    }
-}
+use cgmath::{InnerSpace, Vector2, Vector4};
 
 struct HierarchicalHpxCoverage {
     full_moc: HEALPixCoverage,
@@ -202,75 +69,224 @@ impl HierarchicalHpxCoverage {
         &self.full_moc
     }
 }
-use crate::ProjectionType;
+// The depth at which the ranges are sent to the GPU. The positions of the fragments being
+// in simple precision, deeper cells cannot be distinguished there
+const MAX_DEPTH_GPU: u8 = 20;
+// The width of the textures storing the ranges, one range per texel
+const RANGES_TEX_WIDTH: usize = 4096;
+const RANGES_TEX_PARAMS: &[(u32, u32)] = &[
+    (
+        WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+        WebGl2RenderingContext::NEAREST,
+    ),
+    (
+        WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+        WebGl2RenderingContext::NEAREST,
+    ),
+    (
+        WebGl2RenderingContext::TEXTURE_WRAP_S,
+        WebGl2RenderingContext::CLAMP_TO_EDGE,
+    ),
+    (
+        WebGl2RenderingContext::TEXTURE_WRAP_T,
+        WebGl2RenderingContext::CLAMP_TO_EDGE,
+    ),
+];
+
+// The sorted ranges of a MOC at depth MAX_DEPTH_GPU, stored in an unsigned integer texture.
+// Each texel contains the 32 high and low bits of the start and the end of a range
+pub(crate) struct RangesTexture {
+    texture: Texture2D,
+    num_ranges: i32,
+}
+
+impl RangesTexture {
+    pub(crate) fn new(gl: &WebGlContext, moc: &HEALPixCoverage) -> Result<Self, JsValue> {
+        let moc = moc.degraded(MAX_DEPTH_GPU);
+        let ranges = &moc.moc_ranges().0 .0;
+
+        let shift = 2 * (29 - MAX_DEPTH_GPU);
+        let height = ranges.len().div_ceil(RANGES_TEX_WIDTH).max(1);
+        let mut data = vec![0_u32; 4 * RANGES_TEX_WIDTH * height];
+        for (texel, range) in data.chunks_exact_mut(4).zip(ranges.iter()) {
+            let (start, end) = (range.start >> shift, range.end >> shift);
+            texel.copy_from_slice(&[(start >> 32) as u32, start as u32, (end >> 32) as u32, end as u32]);
+        }
+
+        let texture = Texture2D::create_from_raw_pixels::<RGBA32UI>(
+            gl,
+            RANGES_TEX_WIDTH as i32,
+            height as i32,
+            RANGES_TEX_PARAMS,
+            Some(&data),
+        )?;
+
+        Ok(Self {
+            texture,
+            num_ranges: ranges.len() as i32,
+        })
+    }
+}
+
+impl SendUniforms for RangesTexture {
+    fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
+        shader
+            .attach_uniform("ranges", &self.texture)
+            .attach_uniform("num_ranges", &self.num_ranges)
+    }
+}
+
+// The number of segments along each side of the mesh covering the view
+const NUM_SCREEN_SEGMENTS: usize = 32;
+
+// A grid covering the view. Its vertices are given in NDC and in world space,
+// the triangles having a vertex outside the projection being discarded
+fn screen_mesh(camera: &CameraViewPort, projection: &ProjectionType) -> (Vec<f32>, Vec<f32>, Vec<u16>) {
+    let n = NUM_SCREEN_SEGMENTS + 1;
+    let mut ndc_pos = Vec::with_capacity(2 * n * n);
+    let mut world_pos = Vec::with_capacity(3 * n * n);
+    let mut valid = Vec::with_capacity(n * n);
+
+    for i in 0..n {
+        for j in 0..n {
+            let ndc = Vector2::new(
+                2.0 * (j as f64) / (NUM_SCREEN_SEGMENTS as f64) - 1.0,
+                2.0 * (i as f64) / (NUM_SCREEN_SEGMENTS as f64) - 1.0,
+            );
+            let clip = crate::math::projection::ndc_to_clip_space(&ndc, camera);
+            let pos = projection.clip_to_world_space(&clip);
+
+            ndc_pos.extend([ndc.x as f32, ndc.y as f32]);
+            world_pos.extend(pos.map_or([0.0; 3], |pos| [pos.x as f32, pos.y as f32, pos.z as f32]));
+            valid.push(pos.is_some());
+        }
+    }
+
+    let mut indices = vec![];
+    for i in 0..NUM_SCREEN_SEGMENTS {
+        for j in 0..NUM_SCREEN_SEGMENTS {
+            let idx_0 = j + i * n;
+            let idx_1 = idx_0 + 1;
+            let idx_2 = idx_0 + n;
+            let idx_3 = idx_2 + 1;
+
+            for tri in [[idx_0, idx_1, idx_2], [idx_1, idx_3, idx_2]] {
+                if tri.iter().all(|&idx| valid[idx]) {
+                    indices.extend(tri.map(|idx| idx as u16));
+                }
+            }
+        }
+    }
+
+    (ndc_pos, world_pos, indices)
+}
+
+// The parts of a closed polyline visible in the view, in NDC. The polyline is cut where its
+// vertices cannot be projected and where it crosses the border of the projection.
+// Returns whether it is wholly visible
+fn project_polyline(vertices: &[Vector4<f64>], camera: &CameraViewPort, projection: &ProjectionType) -> (Vec<Vec<Vector2<f64>>>, bool) {
+    let project = |v: &Vector4<f64>| {
+        let v = crate::coosys::apply_coo_system(&CooSystem::ICRSJ2000, camera.get_system(), v);
+        projection.model_to_normalized_device_space(&v, camera)
+    };
+
+    let n = vertices.len();
+    let ndc = vertices.iter().map(project).collect::<Vec<_>>();
+    // A segment crossing the border of the projection has its middle projected close to one of its ends
+    let drawable = (0..n)
+        .map(|i| {
+            let j = (i + 1) % n;
+            let middle = (vertices[i] + vertices[j]).truncate().normalize().extend(1.0);
+
+            match (ndc[i], ndc[j], project(&middle)) {
+                (Some(a), Some(b), Some(m)) => (a - m).magnitude().max((b - m).magnitude()) <= 0.75 * (a - b).magnitude() + 1e-9,
+                _ => false,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let Some(cut) = drawable.iter().position(|drawable| !drawable) else {
+        return (vec![ndc.into_iter().flatten().collect()], true);
+    };
+
+    // Start after a cut so that the parts are not split at the end of the polyline
+    let mut paths = vec![];
+    let mut path = vec![];
+    for k in 1..=n {
+        let i = (cut + k) % n;
+        if drawable[i] {
+            if path.is_empty() {
+                path.extend(ndc[i]);
+            }
+            path.extend(ndc[(i + 1) % n]);
+        } else if !path.is_empty() {
+            paths.push(std::mem::take(&mut path));
+        }
+    }
+    if !path.is_empty() {
+        paths.push(path);
+    }
+
+    (paths, false)
+}
+
+use crate::{math::projection::Projection, ProjectionType};
+use crate::renderable::hips::{raytracing::RayTracer, ID, ID_R};
 impl MOC {
-    pub fn new(gl: &WebGlContext) -> Self {
-        let mut vao = VertexArrayObject::new(gl);
-
-        // layout (location = 0) in vec2 ndc_pos;
-        //let vertices = vec![0.0; MAX_NUM_FLOATS_TO_DRAW];
-        //let indices = vec![0_u16; MAX_NUM_INDICES_TO_DRAW];
-
-        //let vertices = vec![];
-        let position = vec![];
-        let indices = vec![];
-        #[cfg(feature = "webgl2")]
-        vao.bind_for_update()
+    pub fn new(gl: &WebGlContext) -> Result<Self, JsValue> {
+        let lines = RasterizedLinesRenderManager::new(gl)?;
+
+        let mut screen_vao = VertexArrayObject::new(gl);
+        screen_vao.bind_for_update()
             .add_array_buffer_single(
                 2,
                 "ndc_pos",
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<f32>(&position),
-            )
-            // Set the element buffer
-            .add_element_buffer(
-                WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<u32>(&indices),
+                SliceData::<f32>(&[]),
             )
-            .unbind();
-        #[cfg(feature = "webgl1")]
-        vao.bind_for_update()
-            .add_array_buffer(
-                2,
-                "ndc_pos",
+            .add_array_buffer_single(
+                3,
+                "world_pos",
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<f32>(&position),
+                SliceData::<f32>(&[]),
             )
-            // Set the element buffer
             .add_element_buffer(
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<u32>(&indices),
+                SliceData::<u16>(&[]),
             )
             .unbind();
-
-        let num_indices = vec![0];
-        let first_idx = vec![0];
+        let num_screen_indices = 0;
 
         let gl = gl.clone();
         let mocs = HashMap::new();
-        let adaptative_mocs = HashMap::new();
+        let ranges = HashMap::new();
+        let perimeters = HashMap::new();
         let layers = vec![];
         let params = HashMap::new();
+        let st_mocs = HashMap::new();
+        let time_window = None;
         let view = HEALPixCellsInView::new();
         
-        Self {
-            position,
-            indices,
+        Ok(Self {
+            lines,
+
+            screen_vao,
+            num_screen_indices,
 
             mocs,
-            adaptative_mocs,
+            ranges,
+            perimeters,
             params,
 
-            layers,
+            st_mocs,
+            time_window,
 
-            num_indices,
-            first_idx,
+            layers,
 
-            vao,
             gl,
 
             view,
-        }
+        })
     }
 
     pub fn reset_frame(&mut self) {
@@ -282,13 +298,13 @@ impl MOC {
         let depth = view_depth + 6;
 
         let fov_moc = crate::survey::view::compute_view_coverage(camera, view_depth, &CooSystem::ICRSJ2000);
-        self.adaptative_mocs = self.layers.iter()
+        self.perimeters = self.layers.iter()
             .map(|layer| {
                 let params = self.params.get(layer).unwrap_abort();
                 let coverage = self.mocs.get(layer).unwrap_abort();
 
-                let moc = if !params.is_showing() {
-                    None
+                let perimeter = if !params.is_showing() {
+                    vec![]
                 } else {
                     let moc = if params.is_adaptative_display() {
                         let partially_degraded_moc = coverage.get(depth);
@@ -298,38 +314,49 @@ impl MOC {
                         fov_moc.intersection(full_moc)
                     };
 
-                    Some(HEALPixCoverage(moc))
+                    HEALPixCoverage(moc)
+                        .perimeter()
+                        .into_iter()
+                        .map(|polyline| {
+                            polyline
+                                .into_iter()
+                                .map(|(lon, lat)| crate::math::lonlat::radec_to_xyzw(Angle(lon), Angle(lat)))
+                                .collect()
+                        })
+                        .collect()
                 };
 
-                (layer.clone(), moc)
+                (layer.clone(), perimeter)
             }).collect();
-        
     }
 
     pub fn insert(&mut self, moc: HEALPixCoverage, params: al_api::moc::MOC, camera: &CameraViewPort, projection: &ProjectionType) {
         let key = params.get_uuid().clone();
 
+        self.ranges.insert(key.clone(), RangesTexture::new(&self.gl, &moc).unwrap_abort());
         self.mocs.insert(key.clone(), HierarchicalHpxCoverage::new(moc));
         self.params.insert(key.clone(), params);
         self.layers.push(key);
 
         self.recompute_draw_mocs(camera);
         self.update_buffers(camera, projection);
+        self.update_screen_mesh(camera, projection);
         // Compute or retrieve the mocs to render
     }
 
-    pub fn remove(&mut self, params: &al_api::moc::MOC, camera: &CameraViewPort) -> Option<al_api::moc::MOC> {
+    pub fn remove(&mut self, params: &al_api::moc::MOC, camera: &CameraViewPort, projection: &ProjectionType) -> Option<al_api::moc::MOC> {
         let key = params.get_uuid();
 
         self.mocs.remove(key);
+        self.ranges.remove(key);
+        self.st_mocs.remove(key);
         let moc = self.params.remove(key);
 
         if let Some(index) = self.layers.iter().position(|x| x == key) {
             self.layers.remove(index);
-            self.num_indices.remove(index);
-            self.first_idx.remove(index);
 
             self.recompute_draw_mocs(camera);
+            self.update_buffers(camera, projection);
             moc
         } else {
             None
@@ -346,146 +373,84 @@ impl MOC {
         old_params
     }
 
+    /// Insert a Space-Time MOC, displayed as the sky area observed during the time window
+    pub fn insert_space_time(&mut self, moc: SpaceTimeCoverage, params: al_api::moc::MOC, camera: &CameraViewPort, projection: &ProjectionType) {
+        let coverage = moc.time_fold(self.time_window.as_ref());
+        self.st_mocs.insert(params.get_uuid().clone(), moc);
+
+        self.insert(coverage, params, camera, projection);
+    }
+
+    /// Set the time window on which the Space-Time MOCs are projected
+    ///
+    /// The whole sky area observed by them is displayed if `None`
+    pub fn set_time_window(&mut self, time_window: Option<TimeCoverage>, camera: &CameraViewPort, projection: &ProjectionType) {
+        self.time_window = time_window;
+
+        if self.st_mocs.is_empty() {
+            return;
+        }
+
+        for (key, moc) in self.st_mocs.iter() {
+            let coverage = moc.time_fold(self.time_window.as_ref());
+            self.ranges.insert(key.clone(), RangesTexture::new(&self.gl, &coverage).unwrap_abort());
+            self.mocs.insert(key.clone(), HierarchicalHpxCoverage::new(coverage));
+        }
+
+        self.recompute_draw_mocs(camera);
+        self.update_buffers(camera, projection);
+    }
+
+    pub fn get_space_time(&self, params: &al_api::moc::MOC) -> Option<&SpaceTimeCoverage> {
+        self.st_mocs.get(params.get_uuid())
+    }
+
     pub fn get(&self, params: &al_api::moc::MOC) -> Option<&HEALPixCoverage> {
         let key = params.get_uuid();
         self.mocs.get(key).map(|coverage| coverage.get_full_moc())
     }
 
     fn update_buffers(&mut self, camera: &CameraViewPort, projection: &ProjectionType) {
-        self.indices.clear();
-        self.position.clear();
-        self.num_indices.clear();
-        self.first_idx.clear();
-
-        let mut idx_off = 0;
+        self.lines.begin_frame();
 
         for layer in self.layers.iter() {
-            let moc = self.adaptative_mocs.get(layer).unwrap_abort();
             let params = self.params.get(layer).unwrap_abort();
+            let style = LineStyle {
+                width: params.get_line_width(),
+                dash: params.get_line_dash().to_vec(),
+                color: *params.get_color(),
+                opacity: params.get_opacity(),
+            };
 
-            if let Some(moc) = moc {
-                let depth_max = moc.depth();
-                let mut indices_moc = vec![];
-                if params.get_opacity() == 1.0 {
-                    let positions_moc = (&(moc.0)).into_range_moc_iter()
-                        .cells()
-                        .filter_map(|Cell { depth, idx, .. }| {
-                            let delta_depth = depth_max - depth;
-                            let n_segment_by_side = (1 << delta_depth) as usize;
-    
-                            let cell = HEALPixCell(depth, idx);
-                            if let Some((vertices_cell, indices_cell)) = path_along_edge(
-                                &cell,
-                                n_segment_by_side,
-                                camera,
-                                &mut idx_off,
-                                projection
-                            ) {
-                                // Generate the iterator: idx_off + 1, idx_off + 1, .., idx_off + 4*n_segment - 1, idx_off + 4*n_segment - 1
-                                indices_moc.extend(indices_cell);
-    
-                                Some(vertices_cell)
-                            } else if depth < 3 {
-                                let mut vertices = vec![];
-    
-                                let depth_sub_cell = 3;
-                                let delta_depth_sub_cell = depth_max - depth_sub_cell;
-                                let n_segment_by_side_sub_cell = (1 << delta_depth_sub_cell) as usize;
-    
-                                for sub_cell in cell.get_children_cells(3 - depth) {
-                                    if let Some((vertices_sub_cell, indices_sub_cell)) = path_along_edge(
-                                        &sub_cell,
-                                        n_segment_by_side_sub_cell,
-                                        camera,
-                                        &mut idx_off,
-                                        projection
-                                    ) {
-                                        indices_moc.extend(indices_sub_cell);
-                                        vertices.extend(vertices_sub_cell);
-                                    }
-                                }
-    
-                                Some(vertices)
-                            } else {
-                                None
-                            }
-                        })
-                        .flatten()
-                        .collect::<Vec<_>>();
-    
-                    self.first_idx.push(self.indices.len());
-                    self.num_indices.push(indices_moc.len());
-    
-                    self.position.extend(&positions_moc);
-                    self.indices.extend(&indices_moc);
-                } else {
-                    let positions_moc = (&(moc.0)).into_range_moc_iter()
-                        .cells()
-                        .filter_map(|Cell { depth, idx, .. }| {
-                            let delta_depth = (depth_max as i32 - depth as i32).max(0);
-                            let n_segment_by_side = (1 << delta_depth) as usize;
-
-                            let cell = HEALPixCell(depth, idx);
-                            if depth < 3 {
-                                let mut vertices = vec![];
-    
-                                let depth_sub_cell = 3;
-                                let delta_depth_sub_cell = depth_max - depth_sub_cell;
-                                let n_segment_by_side_sub_cell = (1 << delta_depth_sub_cell) as usize;
-    
-                                for sub_cell in cell.get_children_cells(3 - depth) {
-                                    if let Some((vertices_sub_cell, indices_sub_cell)) = rasterize_hpx_cell(
-                                        &sub_cell,
-                                        n_segment_by_side_sub_cell,
-                                        camera,
-                                        &mut idx_off,
-                                        projection
-                                    ) {
-                                        indices_moc.extend(indices_sub_cell);
-                                        vertices.extend(vertices_sub_cell);
-                                    }
-                                }
-    
-                                Some(vertices)
-                            } else if let Some((vertices_cell, indices_cell)) = rasterize_hpx_cell(
-                                &cell,
-                                n_segment_by_side,
-                                camera,
-                                &mut idx_off,
-                                projection
-                            ) {
-                                // Generate the iterator: idx_off + 1, idx_off + 1, .., idx_off + 4*n_segment - 1, idx_off + 4*n_segment - 1
-                                indices_moc.extend(indices_cell);
-    
-                                Some(vertices_cell)
-                            } else {
-                                None
-                            }
-                        })
-                        .flatten()
-                        .collect::<Vec<_>>();
-
-                    self.first_idx.push(self.indices.len());
-                    self.num_indices.push(indices_moc.len());
-    
-                    self.position.extend(&positions_moc);
-                    self.indices.extend(&indices_moc);
-                } 
-            } else {
-                self.first_idx.push(self.indices.len());
-                self.num_indices.push(0);
+            for polyline in self.perimeters.get(layer).unwrap_abort() {
+                let (paths, closed) = project_polyline(polyline, camera, projection);
+                for path in paths {
+                    self.lines.add_path(&path, closed, &style, camera);
+                }
             }
         }
 
-        self.vao.bind_for_update()
+        self.lines.end_frame();
+    }
+
+    fn update_screen_mesh(&mut self, camera: &CameraViewPort, projection: &ProjectionType) {
+        let (ndc_pos, world_pos, indices) = screen_mesh(camera, projection);
+        self.num_screen_indices = indices.len();
+
+        self.screen_vao.bind_for_update()
             .update_array(
                 "ndc_pos",
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData(&self.position),
+                VecData(&ndc_pos),
+            )
+            .update_array(
+                "world_pos",
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData(&world_pos),
             )
             .update_element_array(
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<u32>(&self.indices),
+                VecData::<u16>(&indices),
             );
     }
 
@@ -502,6 +467,7 @@ impl MOC {
         }
 
         self.update_buffers(camera, projection);
+        self.update_screen_mesh(camera, projection);
     }
     
     pub fn is_empty(&self) -> bool {
@@ -512,6 +478,7 @@ impl MOC {
         &self,
         shaders: &mut ShaderManager,
         camera: &CameraViewPort,
+        raytracer: &RayTracer,
     ) {
         if self.is_empty() {
             return;
@@ -526,37 +493,53 @@ impl MOC {
 
         self.gl.enable(WebGl2RenderingContext::BLEND);
 
-        let shader = shaders
-            .get(
-                &self.gl,
-                &ShaderId(Cow::Borrowed("GridVS_CPU"), Cow::Borrowed("GridFS_CPU")),
-            )
-            .unwrap_abort();
-        let shaderbound = shader.bind(&self.gl);
-        for (idx, layer) in self.layers.iter().enumerate() {
-            let moc = self.params.get(layer).unwrap_abort();
-            //if moc.is_showing() {
-                let mode = if moc.get_opacity() == 1.0 {
-                    WebGl2RenderingContext::LINES
-                } else {
-                    WebGl2RenderingContext::TRIANGLES
-                };
+        // The MOCs are given in ICRS
+        let rl = if camera.get_longitude_reversed() { ID_R } else { ID };
+        let model = camera.get_system().to(&CooSystem::ICRSJ2000) * camera.get_w2m() * rl;
+        let raytracing = raytracer.is_rendering(camera);
 
-                let color = moc.get_color();
-                shaderbound
+        for layer in self.layers.iter() {
+            let moc = self.params.get(layer).unwrap_abort();
+            if moc.is_showing() && moc.is_filled() {
+                // The cells covered are found for each fragment of a mesh covering the view
+                let ranges = self.ranges.get(layer).unwrap_abort();
+                let vs = if raytracing { "RayTracerVS" } else { "MocVS" };
+                let shader = shaders
+                    .get(
+                        &self.gl,
+                        &ShaderId(Cow::Borrowed(vs), Cow::Borrowed("MocFS")),
+                    )
+                    .unwrap_abort()
+                    .bind(&self.gl);
+                shader
                     .attach_uniforms_from(camera)
-                    .attach_uniform("color", color)
-                    .attach_uniform("opacity", &moc.get_opacity())
-                    .bind_vertex_array_object_ref(&self.vao)
+                    .attach_uniform("model", &model)
+                    .attach_uniforms_from(ranges)
+                    .attach_uniform("color", moc.get_fill_color())
+                    .attach_uniform("opacity", &moc.get_fill_opacity());
+
+                // The winding of the triangles of the meshes depends on the projection
+                self.gl.disable(WebGl2RenderingContext::CULL_FACE);
+                if raytracing {
+                    raytracer.draw(&shader);
+                } else {
+                    shader
+                        .bind_vertex_array_object_ref(&self.screen_vao)
                         .draw_elements_with_i32(
-                            mode,
-                            Some(self.num_indices[idx] as i32),
-                            WebGl2RenderingContext::UNSIGNED_INT,
-                            (self.first_idx[idx] * std::mem::size_of::<u32>()) as i32
+                            WebGl2RenderingContext::TRIANGLES,
+                            Some(self.num_screen_indices as i32),
+                            WebGl2RenderingContext::UNSIGNED_SHORT,
+                            0
                         );
-            //}
+                }
+                self.gl.enable(WebGl2RenderingContext::CULL_FACE);
+            }
+
         }
 
         self.gl.disable(WebGl2RenderingContext::BLEND);
+
+        // The perimeters are drawn over the filled MOCs
+        self.lines.draw();
     }
-}
\ No newline at end of file
+}
@@ -2,6 +2,7 @@ pub mod catalog;
 pub mod final_pass;
 pub mod grid;
 pub mod labels;
+pub mod lines;
 pub mod moc;
 pub mod image;
 pub mod hips;
@@ -9,7 +10,7 @@ pub mod hips;
 use crate::renderable::image::Image;
 
 use al_core::image::format::ChannelType;
-pub use hips::HiPS;
+pub use hips::{HiPS, MocMask};
 
 pub use labels::TextRenderManager;
 pub use catalog::Manager;
@@ -29,6 +30,7 @@ use al_core::colormap::Colormaps;
 use crate::Abort;
 use crate::ProjectionType;
 use crate::camera::CameraViewPort;
+use crate::healpix::coverage::HEALPixCoverage;
 use crate::shader::ShaderId;
 use crate::{shader::ShaderManager, survey::config::HiPSConfig};
 
@@ -54,6 +56,9 @@ pub struct Layers {
     urls: HashMap<LayerId, Url>,
     // Layers given in a specific order to draw
     layers: Vec<LayerId>,
+    // The MOCs masking the display of HiPS layers
+    masks: HashMap<LayerId, MocMask>,
+    no_mask: MocMask,
 
     raytracer: RayTracer,
     // A vao that takes all the screen
@@ -106,6 +111,8 @@ impl Layers {
         let meta = HashMap::new();
         let urls = HashMap::new();
         let layers = Vec::new();
+        let masks = HashMap::new();
+        let no_mask = MocMask::none(gl)?;
 
         // - The raytracer is a mesh covering the view. Each pixel of this mesh
         //   is unprojected to get its (ra, dec). Then we query ang2pix to get
@@ -160,6 +167,8 @@ impl Layers {
             meta,
             urls,
             layers,
+            masks,
+            no_mask,
 
             raytracer,
 
@@ -203,6 +212,10 @@ impl Layers {
         Ok(())
     }
 
+    pub fn get_raytracer(&self) -> &RayTracer {
+        &self.raytracer
+    }
+
     pub fn set_background_color(&mut self, color: ColorRGB) {
         self.background_color = color;
     }
@@ -268,7 +281,9 @@ impl Layers {
             if let Some(survey) = self.surveys.get_mut(url) {
                 let hips_cfg = survey.get_config();
 
-                let fully_covering_survey = (survey.is_allsky() || hips_cfg.get_format().get_channel() == ChannelType::RGB8U) && meta.opacity == 1.0;
+                let fully_covering_survey = (survey.is_allsky() || hips_cfg.get_format().get_channel() == ChannelType::RGB8U)
+                    && meta.opacity == 1.0
+                    && !self.masks.contains_key(layer);
                 if fully_covering_survey {
                     idx_start_layer = idx_layer;
                 }
@@ -285,12 +300,14 @@ impl Layers {
                     survey.update(camera, projection);
 
                     // 2. Draw it if its opacity is not null
+                    let mask = self.masks.get(layer).unwrap_or(&self.no_mask);
                     survey.draw(
                         shaders,
                         colormaps,
                         camera,
                         raytracer,
-                        draw_opt
+                        draw_opt,
+                        mask,
                     )?;
                 } else if let Some(image) = self.images.get_mut(url) {
                     image.update(camera, projection)?;
@@ -327,6 +344,7 @@ impl Layers {
             .position(|l| layer == l)
             .ok_or(err_layer_not_found)?;
         self.layers.remove(id_layer);
+        self.masks.remove(layer);
 
         // Loop over all the meta for its longitude reversed property
         // and set the camera to it if there is at least one
@@ -377,6 +395,9 @@ impl Layers {
         // Add the new
         self.meta.insert(new_layer.to_string(), meta);
         self.urls.insert(new_layer.to_string(), url);
+        if let Some(mask) = self.masks.remove(layer) {
+            self.masks.insert(new_layer.to_string(), mask);
+        }
 
         Ok(())
     }
@@ -544,6 +565,29 @@ impl Layers {
         Ok(fits)
     }
 
+    /// Mask the display of a HiPS layer with a MOC
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The name of the layer
+    /// * `moc` - The MOC, given in ICRS. The layer is shown only over it
+    /// * `inverted` - Show the layer only outside of the MOC instead
+    pub fn set_moc_mask(&mut self, layer: &str, moc: &HEALPixCoverage, inverted: bool) -> Result<(), JsValue> {
+        if self.get_hips_from_layer(layer).is_none() {
+            return Err(JsValue::from_str(&format!("Layer {:?} is not a HiPS layer, so cannot be masked.", layer)));
+        }
+
+        let mask = MocMask::new(&self.gl, moc, inverted)?;
+        self.masks.insert(layer.to_string(), mask);
+
+        Ok(())
+    }
+
+    /// Show a HiPS layer entirely again
+    pub fn remove_moc_mask(&mut self, layer: &str) {
+        self.masks.remove(layer);
+    }
+
     pub fn get_layer_cfg(&self, layer: &str) -> Result<ImageMetadata, JsValue> {
         self.meta
             .get(layer)
@@ -0,0 +1,463 @@
+//! Streaming reader of delimited text tables
+//!
+//! Handles CSV, TSV (e.g. the tiles of HiPS catalogs) and whitespace separated ASCII tables.
+//! The delimiter is guessed from the first line. Lines beginning with '#' are comments,
+//! and a line of dashes following the header (or its units) is skipped.
+//!
+//! Catalogs are written back as CSV by `write_csv`.
+use super::{
+    find_position_columns, parse_dec, parse_ra, Attributes, Error, Field, RowError, Rows, TableBuilder,
+    TableReader, Value,
+};
+use crate::renderable::catalog::Source;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Delimiter {
+    Char(char),
+    Whitespace,
+}
+
+impl Delimiter {
+    fn guess(line: &str) -> Self {
+        ['\t', ',', ';', '|']
+            .iter()
+            .find(|&&d| line.contains(d))
+            .map(|&d| Delimiter::Char(d))
+            .unwrap_or(Delimiter::Whitespace)
+    }
+
+    fn split(&self, line: &str) -> Vec<String> {
+        match self {
+            Delimiter::Whitespace => line.split_whitespace().map(|c| c.to_string()).collect(),
+            Delimiter::Char(d) => {
+                let mut cells = vec![];
+                let mut cell = String::new();
+                let mut in_quotes = false;
+
+                let mut chars = line.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' if in_quotes && chars.peek() == Some(&'"') => {
+                            cell.push('"');
+                            chars.next();
+                        }
+                        '"' => in_quotes = !in_quotes,
+                        c if c == *d && !in_quotes => {
+                            cells.push(cell.trim().to_string());
+                            cell.clear();
+                        }
+                        c => cell.push(c),
+                    }
+                }
+                cells.push(cell.trim().to_string());
+
+                cells
+            }
+        }
+    }
+}
+
+fn is_number(cell: &str) -> bool {
+    cell.parse::<f64>().is_ok()
+}
+
+// Cells standing for a missing value, whatever the type of the column
+fn is_null(cell: &str) -> bool {
+    ["", "-", "--", "n/a", "na", "null", "none"].contains(&cell.to_lowercase().as_str())
+}
+
+// The index of the first of two successive cells that can be read as RA and Dec
+fn position_pair(cells: &[String]) -> Option<usize> {
+    let values = cells.iter().map(|c| Value::Text(c.clone())).collect::<Vec<_>>();
+    values
+        .windows(2)
+        .position(|v| parse_ra(&v[0]).is_some() && parse_dec(&v[1]).is_some())
+}
+
+// The number of rows read before deciding the type of the columns
+const TYPE_SAMPLE_ROWS: usize = 100;
+
+// e.g. "------- --------" or "---|---", each cell being made of at least two dashes
+// so that a row of "-" null values is not taken as a separator
+fn is_separator(line: &str) -> bool {
+    let mut cells = line
+        .split(|c: char| "+|,;".contains(c) || c.is_whitespace())
+        .filter(|c| !c.is_empty())
+        .peekable();
+
+    cells.peek().is_some() && cells.all(|c| c.len() > 1 && c.chars().all(|c| c == '-' || c == '='))
+}
+
+pub struct DelimitedParser {
+    // Bytes of the line not yet complete
+    buf: Vec<u8>,
+    // Number of lines read
+    line: usize,
+    delimiter: Option<Delimiter>,
+
+    // The column names if given in a header
+    header: Option<Vec<String>>,
+    // The line following the header may give the units
+    units: Option<Vec<String>>,
+    expect_units: bool,
+    // The line following the header (or its units) may separate it from the data
+    expect_separator: bool,
+
+    // Columns description given apart from the table (e.g. by the
+    // Metadata.xml of a HiPS catalog) and matched by name with the header
+    metadata: Vec<Field>,
+
+    // The first rows, kept with their line number until the type of the columns is known
+    sample: Vec<(usize, Vec<String>)>,
+    table: Option<TableBuilder>,
+    errors: Vec<RowError>,
+}
+
+impl Default for DelimitedParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DelimitedParser {
+    pub fn new() -> Self {
+        Self {
+            buf: vec![],
+            line: 0,
+            delimiter: None,
+
+            header: None,
+            units: None,
+            expect_units: false,
+            expect_separator: false,
+
+            metadata: vec![],
+
+            sample: vec![],
+            table: None,
+            errors: vec![],
+        }
+    }
+
+    pub fn with_metadata(fields: &[Field]) -> Self {
+        let mut parser = Self::new();
+        parser.metadata = fields.to_vec();
+
+        parser
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Result<(), Error> {
+        self.line += 1;
+
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_start_matches('\u{feff}').trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        if self.expect_separator && is_separator(line) {
+            self.expect_separator = false;
+            return Ok(());
+        }
+
+        let delimiter = *self.delimiter.get_or_insert_with(|| Delimiter::guess(line));
+        let cells = delimiter.split(line);
+
+        if self.table.is_none() {
+            // Detect the header: none of its cells is a number nor a position
+            let is_header = !cells.iter().any(|c| is_number(c)) && position_pair(&cells).is_none();
+            if self.header.is_none() && self.sample.is_empty() && is_header {
+                self.header = Some(cells);
+                self.expect_units = true;
+                self.expect_separator = true;
+                return Ok(());
+            }
+
+            // Units e.g. "deg", "h:m:s", "mag"
+            if self.expect_units && self.sample.is_empty() {
+                self.expect_units = false;
+
+                let is_units_line = cells.iter().all(|c| {
+                    let c = Value::Text(c.clone());
+                    parse_ra(&c).is_none() && parse_dec(&c).is_none()
+                });
+                if is_units_line {
+                    self.units = Some(cells);
+                    return Ok(());
+                }
+            }
+
+            self.expect_separator = false;
+            self.sample.push((self.line, cells));
+            if self.sample.len() >= TYPE_SAMPLE_ROWS {
+                self.start_table()?;
+            }
+
+            return Ok(());
+        }
+
+        self.push_row(self.line, cells);
+
+        Ok(())
+    }
+
+    fn push_row(&mut self, line: usize, cells: Vec<String>) {
+        let row = cells
+            .into_iter()
+            .map(|c| if c.is_empty() { Value::Null } else { Value::Text(c) })
+            .collect::<Vec<_>>();
+
+        if let Some(table) = &mut self.table {
+            if let Err(err) = table.push_row(row) {
+                self.errors.push(RowError {
+                    line,
+                    message: err.to_string(),
+                });
+            }
+        }
+    }
+
+    // Create the table from the first data rows and add them to it
+    fn start_table(&mut self) -> Result<(), Error> {
+        let sample = std::mem::take(&mut self.sample);
+        let num_columns = sample.first().map_or(0, |(_, cells)| cells.len());
+
+        let fields = (0..num_columns)
+            .map(|idx| {
+                let name = self
+                    .header
+                    .as_ref()
+                    .and_then(|h| h.get(idx))
+                    .cloned()
+                    .unwrap_or_else(|| format!("col{}", idx + 1));
+                let meta = self.metadata.iter().find(|f| f.name == name);
+                let unit = self
+                    .units
+                    .as_ref()
+                    .and_then(|u| u.get(idx))
+                    .filter(|u| !u.is_empty())
+                    .cloned()
+                    .or_else(|| meta.and_then(|f| f.unit.clone()));
+
+                // The type is given by the metadata or else deduced from the values
+                // of the sample, a column having only missing values being numeric
+                let numeric = meta.map_or_else(
+                    || {
+                        sample
+                            .iter()
+                            .filter_map(|(_, cells)| cells.get(idx))
+                            .filter(|c| !is_null(c))
+                            .all(|c| is_number(c))
+                    },
+                    |f| f.numeric,
+                );
+
+                Field {
+                    ucd: meta.and_then(|f| f.ucd.clone()),
+                    name,
+                    unit,
+                    numeric,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let table = if find_position_columns(&fields).is_some() {
+            TableBuilder::new(fields)?
+        } else {
+            // Without header, or if its names are not recognized, the position is given
+            // by the first two successive columns that can be read as RA and Dec
+            let ra = sample
+                .iter()
+                .find_map(|(_, cells)| position_pair(cells))
+                .ok_or(Error::PositionColumnsNotFound)?;
+
+            TableBuilder::with_position_columns(fields, ra, ra + 1)
+        };
+        self.table = Some(table);
+
+        for (line, cells) in sample {
+            self.push_row(line, cells);
+        }
+
+        Ok(())
+    }
+}
+
+impl TableReader for DelimitedParser {
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut start = 0;
+        while let Some(end) = self.buf[start..].iter().position(|&b| b == b'\n') {
+            let line = self.buf[start..(start + end)].to_vec();
+            self.process_line(&line)?;
+            start += end + 1;
+        }
+        self.buf.drain(..start);
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(Vec<Source>, Attributes, Vec<RowError>), Error> {
+        if !self.buf.is_empty() {
+            let line = std::mem::take(&mut self.buf);
+            self.process_line(&line)?;
+        }
+
+        if self.table.is_none() && !self.sample.is_empty() {
+            self.start_table()?;
+        }
+
+        let table = self.table.ok_or_else(|| Error::Parsing {
+            message: "no data rows found in the table".to_string(),
+        })?;
+
+        let (sources, attributes) = table.build();
+        Ok((sources, attributes, self.errors))
+    }
+}
+
+// Quote a CSV cell if needed (RFC 4180)
+fn csv_cell(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write sources and their attributes as a CSV table
+///
+/// The positions come first as the `_RAJ2000` and `_DEJ2000` columns, in degrees.
+/// Null values are left empty.
+pub fn write_csv(sources: &[Source], attributes: &Attributes) -> Vec<u8> {
+    let Rows { fields, rows } = Rows::new(sources, attributes);
+
+    let mut csv = fields.iter().map(|f| csv_cell(f)).collect::<Vec<_>>().join(",");
+    csv.push_str("\r\n");
+    for row in rows {
+        let cells = row
+            .into_iter()
+            .map(|value| match value {
+                Value::Numeric(v) if v.is_finite() => v.to_string(),
+                Value::Text(s) => csv_cell(&s),
+                _ => String::new(),
+            })
+            .collect::<Vec<_>>();
+
+        csv.push_str(&cells.join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DelimitedParser;
+    use crate::table::{Column, TableReader};
+
+    #[test]
+    fn tsv_with_units_and_sexagesimal() {
+        let tsv = "# comment\n\
+            name\tRAJ2000\tDEJ2000\tmag\n\
+            \t\"h:m:s\"\t\"d:m:s\"\tmag\n\
+            ----\t-------\t-------\t---\n\
+            a\t01 00 00.0\t-30 30 00\t12.5\n\
+            b\t12:00:00\tnot a dec\t\n\
+            c\t180.0\t45.0\t\r\n\
+            d\t10.0\t-45.0\t8";
+
+        let mut parser = DelimitedParser::new();
+        for chunk in tsv.as_bytes().chunks(11) {
+            parser.feed(chunk).unwrap();
+        }
+        let (sources, attributes, errors) = parser.finish().unwrap();
+
+        assert_eq!(sources.len(), 3);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 6);
+
+        let (lon, lat) = sources[0].lonlat();
+        assert!((lon.to_degrees() - 15.0).abs() < 1e-4);
+        assert!((lat.to_degrees() + 30.5).abs() < 1e-4);
+
+        assert_eq!(attributes.fields()[3].unit.as_deref(), Some("mag"));
+        match attributes.get_column("mag") {
+            Some(Column::Numeric(mags)) => {
+                assert!(mags[0] == 12.5 && mags[1].is_nan() && mags[2] == 8.0)
+            }
+            _ => panic!("mag column not found"),
+        }
+    }
+
+    #[test]
+    fn csv_without_header() {
+        let csv = "10.5,-20.25,1,\"x, y\"\n11.5,-21.25,2,z\n";
+
+        let mut parser = DelimitedParser::new();
+        parser.feed(csv.as_bytes()).unwrap();
+        let (sources, attributes, errors) = parser.finish().unwrap();
+
+        assert_eq!(sources.len(), 2);
+        assert!(errors.is_empty());
+        match attributes.get_column("col4") {
+            Some(Column::Text(values)) => assert_eq!(values, &["x, y", "z"]),
+            _ => panic!("col4 column not found"),
+        }
+    }
+
+    #[test]
+    fn types_from_a_sample_of_rows() {
+        // No header: the first row has no plain number but a sexagesimal position
+        let tsv = "01:00:00\t-30:30:00\tstar\tN/A\n\
+            02:00:00\t+10:00:00\tgalaxy\t12.5\n\
+            03:00:00\t+20:00:00\t42\t13.5\n";
+
+        let mut parser = DelimitedParser::new();
+        parser.feed(tsv.as_bytes()).unwrap();
+        let (sources, attributes, errors) = parser.finish().unwrap();
+
+        assert_eq!(sources.len(), 3);
+        assert!(errors.is_empty());
+        match attributes.get_column("col3") {
+            Some(Column::Text(values)) => assert_eq!(values, &["star", "galaxy", "42"]),
+            _ => panic!("col3 should be a text column"),
+        }
+        match attributes.get_column("col4") {
+            Some(Column::Numeric(values)) => assert!(values[0].is_nan() && values[1] == 12.5),
+            _ => panic!("col4 should be a numeric column"),
+        }
+    }
+
+    #[test]
+    fn header_with_unknown_names() {
+        let csv = "alpha;delta;mag\n10.5;-20.25;12\n11.5;-21.25;13\n";
+
+        let mut parser = DelimitedParser::new();
+        parser.feed(csv.as_bytes()).unwrap();
+        let (sources, attributes, errors) = parser.finish().unwrap();
+
+        assert_eq!(sources.len(), 2);
+        assert!(errors.is_empty());
+
+        let (lon, lat) = sources[1].lonlat();
+        assert!((lon.to_degrees() - 11.5).abs() < 1e-4);
+        assert!((lat.to_degrees() + 21.25).abs() < 1e-4);
+        assert!(matches!(attributes.get_column("mag"), Some(Column::Numeric(_))));
+    }
+
+    #[test]
+    fn row_of_null_values() {
+        let csv = "ra|dec|mag\n------|------|---\n10.5|-20.25|12\n-|-|-\n11.5|-21.25|-\n";
+
+        let mut parser = DelimitedParser::new();
+        parser.feed(csv.as_bytes()).unwrap();
+        let (sources, _, errors) = parser.finish().unwrap();
+
+        // The separator is skipped but the row of null values is kept (and rejected)
+        assert_eq!(sources.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 4);
+    }
+}
@@ -0,0 +1,523 @@
+//! Catalog tables parsed directly in Rust
+//!
+//! The readers of this module take the raw bytes of a table file
+//! (possibly chunked as they come from a fetched stream) and fill
+//! a `TableBuilder`. The builder keeps the positions as `Source` for the
+//! catalog renderable and the other columns as attribute buffers.
+pub mod delimited;
+pub mod votable;
+
+use crate::math::angle::{Angle, ArcDeg};
+use crate::renderable::catalog::Source;
+
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+#[derive(Debug)]
+pub enum Error {
+    PositionColumnsNotFound,
+    InvalidPosition { message: String },
+    Parsing { message: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::PositionColumnsNotFound => {
+                write!(f, "RA/Dec columns have not been found in the table")
+            }
+            Error::InvalidPosition { message } => write!(f, "{}", message),
+            Error::Parsing { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(err: Error) -> Self {
+        JsValue::from_str(&err.to_string())
+    }
+}
+
+/// A row that could not be added to the table
+#[derive(Debug, Clone)]
+pub struct RowError {
+    /// The line of the file for text tables, the row index
+    /// (starting from 1) otherwise
+    pub line: usize,
+    pub message: String,
+}
+
+/// Readers fed with the chunks of bytes of a table file
+pub trait TableReader {
+    /// Process a new chunk of bytes
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), Error>;
+
+    /// Get the sources and their attributes once all the bytes have been fed
+    /// along with the rows that have been discarded
+    fn finish(self) -> Result<(Vec<Source>, Attributes, Vec<RowError>), Error>;
+}
+
+/// Description of a column
+#[derive(Debug, Clone, Default)]
+pub struct Field {
+    pub name: String,
+    pub ucd: Option<String>,
+    pub unit: Option<String>,
+    /// Whether the values are stored as numbers
+    pub numeric: bool,
+}
+
+/// A value read from a table cell
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Numeric(f64),
+    Text(String),
+    Null,
+}
+
+impl Value {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Numeric(v) => Some(*v),
+            Value::Text(s) => s.trim().parse::<f64>().ok(),
+            Value::Null => None,
+        }
+    }
+}
+
+/// Attribute buffer of a column
+#[derive(Debug, Clone)]
+pub enum Column {
+    Numeric(Vec<f64>),
+    Text(Vec<String>),
+}
+
+impl Column {
+    fn new(field: &Field) -> Self {
+        if field.numeric {
+            Column::Numeric(vec![])
+        } else {
+            Column::Text(vec![])
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        match self {
+            Column::Numeric(values) => values.push(value.as_f64().unwrap_or(f64::NAN)),
+            Column::Text(values) => values.push(match value {
+                Value::Numeric(v) => v.to_string(),
+                Value::Text(s) => s,
+                Value::Null => String::new(),
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Numeric(values) => values.len(),
+            Column::Text(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Value {
+        match self {
+            Column::Numeric(values) => Value::Numeric(values[idx]),
+            Column::Text(values) => Value::Text(values[idx].clone()),
+        }
+    }
+
+    // Reorder the rows so that the i-th value becomes the one
+    // at the index rows[i]
+    fn permute(&mut self, rows: &[u32]) {
+        match self {
+            Column::Numeric(values) => {
+                *values = rows.iter().map(|&r| values[r as usize]).collect();
+            }
+            Column::Text(values) => {
+                let mut old = std::mem::take(values)
+                    .into_iter()
+                    .map(Some)
+                    .collect::<Vec<_>>();
+                *values = rows
+                    .iter()
+                    .map(|&r| old[r as usize].take().unwrap_or_default())
+                    .collect();
+            }
+        }
+    }
+}
+
+/// The attribute columns of a catalog, stored in the same order as its sources
+#[derive(Debug, Clone, Default)]
+pub struct Attributes {
+    fields: Box<[Field]>,
+    columns: Box<[Column]>,
+}
+
+impl Attributes {
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn get_column(&self, name: &str) -> Option<&Column> {
+        self.fields
+            .iter()
+            .position(|f| f.name == name)
+            .map(|idx| &self.columns[idx])
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// Append the rows of other attributes
+    ///
+    /// The columns are matched by name, the values of the columns
+    /// not present in `other` are left empty. `num_rows` is the number of rows
+    /// appended, `other` may have no column at all (e.g. sources given by their position only)
+    pub fn append(&mut self, other: &Attributes, num_rows: usize) {
+        if self.fields.is_empty() {
+            *self = other.clone();
+            return;
+        }
+
+        for (field, column) in self.fields.iter().zip(self.columns.iter_mut()) {
+            match (column, other.get_column(&field.name)) {
+                (Column::Numeric(values), Some(Column::Numeric(others))) => values.extend_from_slice(others),
+                (Column::Text(values), Some(Column::Text(others))) => values.extend_from_slice(others),
+                (column, other) => {
+                    for idx in 0..num_rows {
+                        let value = match other {
+                            Some(Column::Numeric(others)) => Value::Numeric(others[idx]),
+                            Some(Column::Text(others)) => Value::Text(others[idx].clone()),
+                            None => Value::Null,
+                        };
+                        column.push(value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply the permutation computed when sorting the sources
+    /// so that the attributes follow their sources
+    pub fn permute(&mut self, rows: &[u32]) {
+        for column in self.columns.iter_mut() {
+            column.permute(rows);
+        }
+    }
+
+    /// Apply the permutation to the column at `idx` only
+    pub fn permute_column(&mut self, idx: usize, rows: &[u32]) {
+        self.columns[idx].permute(rows);
+    }
+
+    /// Get the attributes of a subset of the rows
+    pub fn select(&self, rows: &[u32]) -> Attributes {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| match column {
+                Column::Numeric(values) => Column::Numeric(rows.iter().map(|&r| values[r as usize]).collect()),
+                Column::Text(values) => Column::Text(rows.iter().map(|&r| values[r as usize].clone()).collect()),
+            })
+            .collect();
+
+        Attributes {
+            fields: self.fields.clone(),
+            columns,
+        }
+    }
+}
+
+/// Rows of a catalog sent back to javascript
+///
+/// The positions of the sources, as drawn, come first as the
+/// `_RAJ2000` and `_DEJ2000` columns in degrees, followed by the attributes
+#[derive(Debug, Serialize)]
+pub struct Rows {
+    pub fields: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Rows {
+    pub fn new(sources: &[Source], attributes: &Attributes) -> Self {
+        let fields = ["_RAJ2000", "_DEJ2000"]
+            .iter()
+            .map(|name| name.to_string())
+            .chain(attributes.fields().iter().map(|f| f.name.clone()))
+            .collect();
+
+        // The attributes may not be given (e.g. sources added by their position only)
+        let with_attributes = attributes.num_rows() == sources.len();
+        let rows = sources
+            .iter()
+            .enumerate()
+            .map(|(idx, source)| {
+                let (lon, lat) = source.lonlat();
+                let ra = (lon as f64).to_degrees().rem_euclid(360.0);
+                let dec = (lat as f64).to_degrees();
+
+                let mut row = vec![Value::Numeric(ra), Value::Numeric(dec)];
+                row.extend(attributes.columns().iter().map(|column| {
+                    if with_attributes {
+                        column.get(idx)
+                    } else {
+                        Value::Null
+                    }
+                }));
+                row
+            })
+            .collect();
+
+        Self { fields, rows }
+    }
+}
+
+/// Find the index of the RA and Dec columns
+///
+/// The main position given by the UCDs is first looked for (UCD1+ and UCD1 flavors),
+/// then any equatorial position UCD and finally the usual column names.
+pub fn find_position_columns(fields: &[Field]) -> Option<(usize, usize)> {
+    let find = |ucd1p: &str, ucd1: &str, names: &[&str]| -> Option<usize> {
+        let ucds = fields
+            .iter()
+            .map(|f| f.ucd.as_deref().unwrap_or("").to_lowercase())
+            .collect::<Vec<_>>();
+
+        ucds.iter()
+            .position(|ucd| {
+                let mut words = ucd.split(';').map(str::trim);
+                words.clone().any(|w| w == ucd1p) && words.any(|w| w == "meta.main")
+            })
+            .or_else(|| ucds.iter().position(|ucd| ucd == ucd1))
+            .or_else(|| ucds.iter().position(|ucd| ucd.split(';').any(|w| w.trim() == ucd1p)))
+            .or_else(|| {
+                fields
+                    .iter()
+                    .position(|f| names.contains(&f.name.trim().to_lowercase().as_str()))
+            })
+    };
+
+    let ra = find(
+        "pos.eq.ra",
+        "pos_eq_ra_main",
+        &["ra", "_raj2000", "raj2000", "ra_icrs", "ra_deg", "radeg", "ra(deg)", "alpha"],
+    )?;
+    let dec = find(
+        "pos.eq.dec",
+        "pos_eq_dec_main",
+        &["dec", "de", "_dej2000", "dej2000", "decj2000", "dec_icrs", "de_icrs", "dec_deg", "dedeg", "dec(deg)", "delta"],
+    )?;
+
+    Some((ra, dec))
+}
+
+/// The columns giving the astrometry of the sources
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AstrometryColumns {
+    /// Proper motion in right ascension, including the cos(dec) factor
+    pub pmra: usize,
+    pub pmdec: usize,
+    pub parallax: Option<usize>,
+    pub radial_velocity: Option<usize>,
+    /// The epoch of the positions when given per source (e.g. `ref_epoch` of Gaia)
+    pub ref_epoch: Option<usize>,
+}
+
+/// Find the proper motion columns, and the parallax and radial velocity ones if present
+///
+/// The columns are looked for by their UCD and then by their usual names.
+pub fn find_astrometry_columns(fields: &[Field]) -> Option<AstrometryColumns> {
+    let find = |ucd_words: &[&str], names: &[&str]| -> Option<usize> {
+        fields
+            .iter()
+            .position(|f| {
+                let ucd = f.ucd.as_deref().unwrap_or("").to_lowercase();
+                let words = ucd.split(';').map(str::trim).collect::<Vec<_>>();
+                f.numeric && ucd_words.iter().all(|w| words.contains(w))
+            })
+            .or_else(|| {
+                fields.iter().position(|f| {
+                    f.numeric && names.contains(&f.name.trim().to_lowercase().as_str())
+                })
+            })
+    };
+
+    let pmra = find(&["pos.pm", "pos.eq.ra"], &["pmra", "pm_ra", "pmra_cosdec", "pmalpha"])?;
+    let pmdec = find(&["pos.pm", "pos.eq.dec"], &["pmdec", "pmde", "pm_dec", "pmdelta"])?;
+    let parallax = find(&["pos.parallax.trig"], &["parallax", "plx"])
+        .or_else(|| find(&["pos.parallax"], &[]));
+    let radial_velocity = find(&["spect.dopplerveloc.opt"], &["radial_velocity", "rv", "hrv"])
+        .or_else(|| find(&["phys.veloc", "pos.heliocentric"], &[]));
+    let ref_epoch = find(&["meta.ref", "time.epoch"], &["ref_epoch", "epoch"]);
+
+    Some(AstrometryColumns {
+        pmra,
+        pmdec,
+        parallax,
+        radial_velocity,
+        ref_epoch,
+    })
+}
+
+/// Find the index of the main magnitude column
+///
+/// Given by its UCD (the main one first), the magnitude columns
+/// being the ones with a `phot.mag` UCD
+pub fn find_magnitude_column(fields: &[Field]) -> Option<usize> {
+    let is_mag = |f: &Field| {
+        f.numeric
+            && f.ucd
+                .as_deref()
+                .map(|ucd| ucd.to_lowercase().split(';').any(|w| w.trim() == "phot.mag"))
+                .unwrap_or(false)
+    };
+
+    fields
+        .iter()
+        .position(|f| is_mag(f) && f.ucd.as_deref().unwrap_or("").to_lowercase().contains("meta.main"))
+        .or_else(|| fields.iter().position(is_mag))
+}
+
+// Parse a sexagesimal angle e.g. "12 34 56.7", "-12:34:56.7" or "12h34m56.7s".
+// Returns the value in units of its first component and whether that
+// component is in degrees (i.e. has a degree marker)
+fn parse_sexagesimal(s: &str) -> Option<(f64, bool)> {
+    let s = s.trim();
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(s) => (-1.0, s),
+        None => (1.0, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let degrees = s.contains('d') || s.contains('°');
+
+    let components = s
+        .split(|c: char| c.is_whitespace() || ":hdms°'\"".contains(c))
+        .filter(|c| !c.is_empty())
+        .map(|c| c.parse::<f64>().ok().filter(|c| c.is_finite() && *c >= 0.0))
+        .collect::<Option<Vec<_>>>()?;
+
+    if components.is_empty() || components.len() > 3 {
+        return None;
+    }
+    // Only the last component can have a fractional part
+    // and minutes and seconds are lower than 60
+    let (_, firsts) = components.split_last()?;
+    if firsts.iter().any(|c| c.fract() != 0.0) || components[1..].iter().any(|c| *c >= 60.0) {
+        return None;
+    }
+
+    let value = components
+        .iter()
+        .rev()
+        .fold(0.0, |v, c| c + v / 60.0);
+
+    Some((sign * value, degrees))
+}
+
+/// Parse a right ascension given in decimal degrees or
+/// in sexagesimal (hours unless a degree marker is present)
+pub fn parse_ra(value: &Value) -> Option<f64> {
+    if let Some(ra) = value.as_f64() {
+        return Some(ra).filter(|v| v.is_finite());
+    }
+
+    match value {
+        Value::Text(s) => parse_sexagesimal(s).map(|(v, degrees)| if degrees { v } else { v * 15.0 }),
+        _ => None,
+    }
+}
+
+/// Parse a declination given in decimal degrees or in sexagesimal
+pub fn parse_dec(value: &Value) -> Option<f64> {
+    if let Some(dec) = value.as_f64() {
+        return Some(dec).filter(|v| v.is_finite());
+    }
+
+    match value {
+        Value::Text(s) => parse_sexagesimal(s).map(|(v, _)| v),
+        _ => None,
+    }
+}
+
+/// Accumulates the rows of a table
+pub struct TableBuilder {
+    fields: Vec<Field>,
+    ra: usize,
+    dec: usize,
+
+    sources: Vec<Source>,
+    columns: Vec<Column>,
+}
+
+impl TableBuilder {
+    pub fn new(fields: Vec<Field>) -> Result<Self, Error> {
+        let (ra, dec) = find_position_columns(&fields).ok_or(Error::PositionColumnsNotFound)?;
+
+        Ok(Self::with_position_columns(fields, ra, dec))
+    }
+
+    pub fn with_position_columns(fields: Vec<Field>, ra: usize, dec: usize) -> Self {
+        let columns = fields.iter().map(Column::new).collect();
+
+        Self {
+            fields,
+            ra,
+            dec,
+            sources: vec![],
+            columns,
+        }
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Add a row to the table
+    ///
+    /// The row is discarded if its position cannot be read
+    pub fn push_row(&mut self, row: Vec<Value>) -> Result<(), Error> {
+        if row.len() != self.fields.len() {
+            return Err(Error::Parsing {
+                message: format!("{} values found, {} expected", row.len(), self.fields.len()),
+            });
+        }
+
+        let (ra, dec) = match (parse_ra(&row[self.ra]), parse_dec(&row[self.dec])) {
+            (Some(ra), Some(dec)) if (-90.0..=90.0).contains(&dec) => (ra, dec),
+            _ => {
+                return Err(Error::InvalidPosition {
+                    message: format!("invalid position: {:?} {:?}", row[self.ra], row[self.dec]),
+                })
+            }
+        };
+
+        let lon: Angle<f32> = ArcDeg(ra as f32).into();
+        let lat: Angle<f32> = ArcDeg(dec as f32).into();
+        self.sources.push(Source::new(lon, lat));
+
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+
+        Ok(())
+    }
+
+    pub fn build(self) -> (Vec<Source>, Attributes) {
+        let attributes = Attributes {
+            fields: self.fields.into_boxed_slice(),
+            columns: self.columns.into_boxed_slice(),
+        };
+
+        (self.sources, attributes)
+    }
+}
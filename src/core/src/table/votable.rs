@@ -0,0 +1,920 @@
+//! Streaming VOTable reader
+//!
+//! Only the first table of the document is read. The TABLEDATA, BINARY and BINARY2
+//! serializations are supported, the binary ones being base64 encoded inside a STREAM
+//! element. Bytes can be fed as they arrive from a fetched stream.
+use super::{Attributes, Error, Field, RowError, Rows, TableBuilder, TableReader, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DataType {
+    Boolean,
+    Bit,
+    UnsignedByte,
+    Short,
+    Int,
+    Long,
+    Char,
+    UnicodeChar,
+    Float,
+    Double,
+    FloatComplex,
+    DoubleComplex,
+}
+
+impl DataType {
+    fn parse(datatype: &str) -> Result<Self, Error> {
+        let dt = match datatype {
+            "boolean" => DataType::Boolean,
+            "bit" => DataType::Bit,
+            "unsignedByte" => DataType::UnsignedByte,
+            "short" => DataType::Short,
+            "int" => DataType::Int,
+            "long" => DataType::Long,
+            "char" => DataType::Char,
+            "unicodeChar" => DataType::UnicodeChar,
+            "float" => DataType::Float,
+            "double" => DataType::Double,
+            "floatComplex" => DataType::FloatComplex,
+            "doubleComplex" => DataType::DoubleComplex,
+            _ => {
+                return Err(Error::Parsing {
+                    message: format!("unknown VOTable datatype {}", datatype),
+                })
+            }
+        };
+
+        Ok(dt)
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(
+            self,
+            DataType::Boolean | DataType::Bit | DataType::Char | DataType::UnicodeChar
+        )
+    }
+
+    // Number of bytes taken by `count` elements in the binary serializations
+    fn num_bytes(&self, count: usize) -> usize {
+        let size = match self {
+            DataType::Bit => return count.div_ceil(8),
+            DataType::Boolean | DataType::UnsignedByte | DataType::Char => 1,
+            DataType::Short | DataType::UnicodeChar => 2,
+            DataType::Int | DataType::Float => 4,
+            DataType::Long | DataType::Double | DataType::FloatComplex => 8,
+            DataType::DoubleComplex => 16,
+        };
+
+        size * count
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArraySize {
+    Scalar,
+    Fixed(usize),
+    // Variable length arrays are prefixed by their number of elements.
+    // The factor is the product of the fixed dimensions e.g. 3 for "3x*"
+    Variable(usize),
+}
+
+impl ArraySize {
+    fn parse(arraysize: Option<&str>) -> Result<Self, Error> {
+        let arraysize = match arraysize {
+            Some(arraysize) => arraysize.trim(),
+            None => return Ok(ArraySize::Scalar),
+        };
+
+        let err = || Error::Parsing {
+            message: format!("invalid VOTable arraysize {}", arraysize),
+        };
+        let dims = arraysize.split('x').collect::<Vec<_>>();
+        let (last, fixed) = dims.split_last().ok_or_else(err)?;
+
+        let factor = fixed.iter().try_fold(1_usize, |f, d| {
+            d.parse::<usize>().map(|d| f * d).map_err(|_| err())
+        })?;
+
+        if last.ends_with('*') {
+            Ok(ArraySize::Variable(factor))
+        } else {
+            let last = last.parse::<usize>().map_err(|_| err())?;
+            Ok(ArraySize::Fixed(factor * last))
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BinaryField {
+    datatype: DataType,
+    arraysize: ArraySize,
+}
+
+impl BinaryField {
+    fn decode(&self, data: &[u8], count: usize) -> Value {
+        match self.datatype {
+            DataType::Char => {
+                let s = String::from_utf8_lossy(data);
+                Value::Text(s.trim_end_matches('\0').trim_end().to_string())
+            }
+            DataType::UnicodeChar => {
+                let chars = data
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect::<Vec<_>>();
+                let s = String::from_utf16_lossy(&chars);
+                Value::Text(s.trim_end_matches('\0').trim_end().to_string())
+            }
+            DataType::Boolean => Value::Text(
+                data.iter()
+                    .map(|b| match b {
+                        b'T' | b't' | b'1' => 'T',
+                        b'F' | b'f' | b'0' => 'F',
+                        _ => '?',
+                    })
+                    .collect(),
+            ),
+            DataType::Bit => Value::Text(
+                (0..count)
+                    .map(|i| if data[i / 8] & (0x80 >> (i % 8)) != 0 { '1' } else { '0' })
+                    .collect(),
+            ),
+            _ => {
+                let mut values = data
+                    .chunks_exact(self.datatype.num_bytes(1))
+                    .flat_map(|c| self.decode_number(c));
+
+                if count == 1 && !matches!(self.datatype, DataType::FloatComplex | DataType::DoubleComplex) {
+                    values.next().map(Value::Numeric).unwrap_or(Value::Null)
+                } else {
+                    Value::Text(values.map(|v| v.to_string()).collect::<Vec<_>>().join(" "))
+                }
+            }
+        }
+    }
+
+    // Decode one number. Complex numbers give their two parts
+    fn decode_number(&self, c: &[u8]) -> Vec<f64> {
+        let f32_be = |c: &[u8]| f32::from_be_bytes([c[0], c[1], c[2], c[3]]) as f64;
+        let f64_be = |c: &[u8]| f64::from_be_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]);
+
+        match self.datatype {
+            DataType::UnsignedByte => vec![c[0] as f64],
+            DataType::Short => vec![i16::from_be_bytes([c[0], c[1]]) as f64],
+            DataType::Int => vec![i32::from_be_bytes([c[0], c[1], c[2], c[3]]) as f64],
+            DataType::Long => vec![i64::from_be_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]) as f64],
+            DataType::Float => vec![f32_be(c)],
+            DataType::Double => vec![f64_be(c)],
+            DataType::FloatComplex => vec![f32_be(&c[..4]), f32_be(&c[4..])],
+            DataType::DoubleComplex => vec![f64_be(&c[..8]), f64_be(&c[8..])],
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum State {
+    // Reading the metadata until the data of the first table
+    Header,
+    TableData,
+    // Inside the base64 STREAM of a BINARY or BINARY2 element
+    Stream,
+    Done,
+}
+
+// A start or end tag
+struct Tag {
+    name: String,
+    attributes: Vec<(String, String)>,
+    closing: bool,
+    self_closing: bool,
+}
+
+impl Tag {
+    fn parse(raw: &[u8]) -> Self {
+        // raw is the content between '<' and '>'
+        let raw = String::from_utf8_lossy(raw);
+        let mut raw = raw.trim();
+
+        let closing = raw.starts_with('/');
+        if closing {
+            raw = &raw[1..];
+        }
+        let self_closing = raw.ends_with('/');
+        if self_closing {
+            raw = &raw[..raw.len() - 1];
+        }
+
+        let name_end = raw.find(char::is_whitespace).unwrap_or(raw.len());
+        let name = &raw[..name_end];
+        // Remove the namespace prefix
+        let name = name.rsplit(':').next().unwrap_or(name).to_string();
+
+        let mut attributes = vec![];
+        let mut rest = &raw[name_end..];
+        while let Some(eq) = rest.find('=') {
+            let key = rest[..eq].trim().to_string();
+            let after = rest[eq + 1..].trim_start();
+            let quote = match after.chars().next() {
+                Some(q @ '"') | Some(q @ '\'') => q,
+                _ => break,
+            };
+            let after = &after[1..];
+            let end = match after.find(quote) {
+                Some(end) => end,
+                None => break,
+            };
+            attributes.push((key, unescape(&after[..end])));
+            rest = &after[end + 1..];
+        }
+
+        Tag {
+            name,
+            attributes,
+            closing,
+            self_closing,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// Replace the XML predefined and character entities
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse::<u32>().ok().and_then(char::from_u32),
+            _ => None,
+        };
+
+        if let Some(c) = c {
+            out.push(c);
+        } else {
+            out.push_str(&rest[..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+
+    out
+}
+
+// Find the index of the '>' ending a tag starting at `start`
+// taking care of the quoted attribute values
+fn find_tag_end(buf: &[u8], start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, &c) in buf.iter().enumerate().skip(start) {
+        match (quote, c) {
+            (None, b'"') | (None, b'\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, b'>') => return Some(i),
+            _ => (),
+        }
+    }
+
+    None
+}
+
+fn find(buf: &[u8], start: usize, pattern: &[u8]) -> Option<usize> {
+    buf[start..]
+        .windows(pattern.len())
+        .position(|w| w == pattern)
+        .map(|i| i + start)
+}
+
+// A markup item read from the buffer
+enum Markup {
+    Tag(Tag),
+    // Content of a CDATA section
+    CData(String),
+    // Comments, processing instructions and declarations
+    Ignored,
+}
+
+/// Push style VOTable reader
+///
+/// ```ignore
+/// let mut parser = VOTableParser::new();
+/// while let Some(chunk) = stream.next().await {
+///     parser.feed(&chunk)?;
+/// }
+/// let (sources, attributes, errors) = parser.finish()?;
+/// ```
+pub struct VOTableParser {
+    // Bytes received but not processed yet
+    buf: Vec<u8>,
+    state: State,
+
+    fields: Vec<Field>,
+    binary_fields: Vec<BinaryField>,
+    // The columns of the last table having no data, e.g. the one of a Metadata.xml
+    described_fields: Vec<Field>,
+    table: Option<TableBuilder>,
+
+    // TABLEDATA
+    row: Vec<Value>,
+    cell: Option<String>,
+
+    // BINARY/BINARY2
+    binary2: bool,
+    // Decoded bytes not yet read as rows
+    binary: Vec<u8>,
+    // Base64 bit accumulator
+    acc: u32,
+    acc_bits: u32,
+
+    num_rows: usize,
+    errors: Vec<RowError>,
+}
+
+impl Default for VOTableParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VOTableParser {
+    pub fn new() -> Self {
+        Self {
+            buf: vec![],
+            state: State::Header,
+
+            fields: vec![],
+            binary_fields: vec![],
+            described_fields: vec![],
+            table: None,
+
+            row: vec![],
+            cell: None,
+
+            binary2: false,
+            binary: vec![],
+            acc: 0,
+            acc_bits: 0,
+
+            num_rows: 0,
+            errors: vec![],
+        }
+    }
+
+    // Read the markup beginning at `lt`. Returns the markup and the index
+    // following it or None if the buffer does not contain it entirely
+    fn read_markup(&self, lt: usize) -> Option<(Markup, usize)> {
+        let buf = &self.buf;
+        let rest = &buf[lt..];
+
+        if rest.len() < 9 && (b"<![CDATA[".starts_with(rest) || b"<!--".starts_with(rest)) {
+            return None;
+        }
+
+        if rest.starts_with(b"<!--") {
+            let end = find(buf, lt + 4, b"-->")?;
+            Some((Markup::Ignored, end + 3))
+        } else if rest.starts_with(b"<![CDATA[") {
+            let end = find(buf, lt + 9, b"]]>")?;
+            let text = String::from_utf8_lossy(&buf[lt + 9..end]).into_owned();
+            Some((Markup::CData(text), end + 3))
+        } else if rest.starts_with(b"<?") || rest.starts_with(b"<!") {
+            let end = find_tag_end(buf, lt + 1)?;
+            Some((Markup::Ignored, end + 1))
+        } else {
+            let end = find_tag_end(buf, lt + 1)?;
+            Some((Markup::Tag(Tag::parse(&buf[lt + 1..end])), end + 1))
+        }
+    }
+
+    fn process_tag(&mut self, tag: Tag) -> Result<(), Error> {
+        match (&self.state, tag.name.as_str(), tag.closing) {
+            (State::Header, "FIELD", false) => {
+                let datatype = DataType::parse(tag.get("datatype").unwrap_or("char"))?;
+                let arraysize = ArraySize::parse(tag.get("arraysize"))?;
+
+                let numeric = datatype.is_numeric()
+                    && !matches!(datatype, DataType::FloatComplex | DataType::DoubleComplex)
+                    && matches!(arraysize, ArraySize::Scalar | ArraySize::Fixed(1));
+                let name = tag
+                    .get("name")
+                    .or_else(|| tag.get("ID"))
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("col{}", self.fields.len() + 1));
+
+                self.fields.push(Field {
+                    name,
+                    ucd: tag.get("ucd").map(|ucd| ucd.to_string()),
+                    unit: tag.get("unit").map(|unit| unit.to_string()),
+                    numeric,
+                });
+                self.binary_fields.push(BinaryField { datatype, arraysize });
+            }
+            (State::Header, "TABLEDATA", false) => {
+                self.start_table()?;
+                self.state = State::TableData;
+            }
+            (State::Header, "BINARY", false) => {
+                self.binary2 = false;
+            }
+            (State::Header, "BINARY2", false) => {
+                self.binary2 = true;
+            }
+            (State::Header, "STREAM", false) => {
+                if tag.get("href").is_some() {
+                    return Err(Error::Parsing {
+                        message: "VOTable remote streams are not supported".to_string(),
+                    });
+                }
+                if tag.get("encoding") != Some("base64") {
+                    return Err(Error::Parsing {
+                        message: "only base64 encoded VOTable streams are supported".to_string(),
+                    });
+                }
+
+                self.start_table()?;
+                self.state = if tag.self_closing { State::Done } else { State::Stream };
+            }
+            (State::Header, "FITS", false) => {
+                return Err(Error::Parsing {
+                    message: "the FITS serialization of VOTable is not supported".to_string(),
+                });
+            }
+            (State::Header, "TABLE", true) => {
+                // The table has no data, look for the next one but keep its description
+                self.described_fields = std::mem::take(&mut self.fields);
+                self.binary_fields.clear();
+            }
+            (State::TableData, "TR", false) => {
+                self.row.clear();
+            }
+            (State::TableData, "TD", false) => {
+                if tag.self_closing {
+                    self.row.push(Value::Null);
+                } else {
+                    self.cell = Some(String::new());
+                }
+            }
+            (State::TableData, "TD", true) => {
+                let value = match self.cell.take() {
+                    Some(text) if !text.trim().is_empty() => Value::Text(text),
+                    _ => Value::Null,
+                };
+                self.row.push(value);
+            }
+            (State::TableData, "TR", true) => {
+                let row = std::mem::take(&mut self.row);
+                self.push_row(row);
+            }
+            (State::TableData, "TABLEDATA", true) | (State::Stream, "STREAM", true) => {
+                self.state = State::Done;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// The columns of the table read so far
+    ///
+    /// Useful for documents giving only the description of a table
+    /// such as the Metadata.xml of HiPS catalogs.
+    pub fn fields(&self) -> &[Field] {
+        match &self.table {
+            Some(table) => table.fields(),
+            None if self.fields.is_empty() => &self.described_fields,
+            None => &self.fields,
+        }
+    }
+
+    fn start_table(&mut self) -> Result<(), Error> {
+        let fields = std::mem::take(&mut self.fields);
+        self.table = Some(TableBuilder::new(fields)?);
+
+        Ok(())
+    }
+
+    fn push_row(&mut self, row: Vec<Value>) {
+        if let Some(table) = &mut self.table {
+            self.num_rows += 1;
+            if let Err(err) = table.push_row(row) {
+                self.errors.push(RowError {
+                    line: self.num_rows,
+                    message: err.to_string(),
+                });
+            }
+        }
+    }
+
+    fn decode_base64(&mut self, text: &[u8]) {
+        for &c in text {
+            let v = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                // Whitespaces and padding
+                _ => continue,
+            };
+
+            self.acc = (self.acc << 6) | (v as u32);
+            self.acc_bits += 6;
+            if self.acc_bits >= 8 {
+                self.acc_bits -= 8;
+                self.binary.push((self.acc >> self.acc_bits) as u8);
+                self.acc &= (1 << self.acc_bits) - 1;
+            }
+        }
+    }
+
+    // Read all the complete rows from the decoded binary stream
+    fn read_binary_rows(&mut self) {
+        let mut pos = 0;
+        while let Some((row, num_bytes)) = self.read_binary_row(&self.binary[pos..]) {
+            pos += num_bytes;
+            self.push_row(row);
+        }
+        self.binary.drain(..pos);
+    }
+
+    fn read_binary_row(&self, bytes: &[u8]) -> Option<(Vec<Value>, usize)> {
+        let mut pos = 0;
+
+        // BINARY2 rows start with a flag per field telling whether its value is null
+        let nulls = if self.binary2 {
+            let n = self.binary_fields.len().div_ceil(8);
+            pos = n;
+            Some(bytes.get(..n)?)
+        } else {
+            None
+        };
+
+        let mut row = Vec::with_capacity(self.binary_fields.len());
+        for (i, field) in self.binary_fields.iter().enumerate() {
+            let count = match field.arraysize {
+                ArraySize::Scalar => 1,
+                ArraySize::Fixed(n) => n,
+                ArraySize::Variable(factor) => {
+                    let n = bytes.get(pos..pos + 4)?;
+                    pos += 4;
+                    (u32::from_be_bytes([n[0], n[1], n[2], n[3]]) as usize) * factor
+                }
+            };
+
+            let len = field.datatype.num_bytes(count);
+            let data = bytes.get(pos..pos + len)?;
+            pos += len;
+
+            let is_null = nulls
+                .map(|flags| flags[i / 8] & (0x80 >> (i % 8)) != 0)
+                .unwrap_or(false);
+            row.push(if is_null {
+                Value::Null
+            } else {
+                field.decode(data, count)
+            });
+        }
+
+        Some((row, pos))
+    }
+}
+
+impl TableReader for VOTableParser {
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        if self.state == State::Done {
+            return Ok(());
+        }
+
+        self.buf.extend_from_slice(chunk);
+
+        let mut pos = 0;
+        while self.state != State::Done {
+            let lt = match find(&self.buf, pos, b"<") {
+                Some(lt) => lt,
+                None => {
+                    // No markup for now, consume what can be consumed without waiting
+                    if self.state == State::Stream {
+                        let text = self.buf[pos..].to_vec();
+                        self.decode_base64(&text);
+                        pos = self.buf.len();
+                    } else if self.state == State::Header {
+                        pos = self.buf.len();
+                    }
+                    break;
+                }
+            };
+
+            // Text preceding the markup
+            match self.state {
+                State::TableData => {
+                    if let Some(cell) = &mut self.cell {
+                        cell.push_str(&unescape(&String::from_utf8_lossy(&self.buf[pos..lt])));
+                    }
+                }
+                State::Stream => {
+                    let text = self.buf[pos..lt].to_vec();
+                    self.decode_base64(&text);
+                }
+                _ => (),
+            }
+            pos = lt;
+
+            let (markup, end) = match self.read_markup(lt) {
+                Some(m) => m,
+                // The markup is not complete, wait for the next chunk
+                None => break,
+            };
+            pos = end;
+
+            match markup {
+                Markup::Tag(tag) => self.process_tag(tag)?,
+                Markup::CData(text) => {
+                    if let Some(cell) = &mut self.cell {
+                        cell.push_str(&text);
+                    }
+                }
+                Markup::Ignored => (),
+            }
+
+            if self.state == State::Stream {
+                self.read_binary_rows();
+            }
+        }
+
+        if self.state == State::Stream {
+            self.read_binary_rows();
+        }
+
+        self.buf.drain(..pos);
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(Vec<Source>, Attributes, Vec<RowError>), Error> {
+        let table = self.table.ok_or_else(|| Error::Parsing {
+            message: "no table data found in the VOTable".to_string(),
+        })?;
+
+        let (sources, attributes) = table.build();
+        Ok((sources, attributes, self.errors))
+    }
+}
+
+use crate::renderable::catalog::Source;
+
+// Replace the characters that cannot appear as is in XML text and attribute values
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Write sources and their attributes as a VOTable in the TABLEDATA serialization
+///
+/// The positions come first as the `_RAJ2000` and `_DEJ2000` columns, in degrees.
+/// Numeric columns are written as doubles, the other ones as strings.
+pub fn write_votable(name: &str, sources: &[Source], attributes: &Attributes) -> Vec<u8> {
+    let rows = Rows::new(sources, attributes);
+    let position = |name: &str, ucd: &str| Field {
+        name: name.to_string(),
+        ucd: Some(ucd.to_string()),
+        unit: Some("deg".to_string()),
+        numeric: true,
+    };
+    let fields = vec![position("_RAJ2000", "pos.eq.ra"), position("_DEJ2000", "pos.eq.dec")]
+        .into_iter()
+        .chain(attributes.fields().iter().cloned());
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <VOTABLE version=\"1.4\" xmlns=\"http://www.ivoa.net/xml/VOTable/v1.3\">\n\
+        <RESOURCE>\n",
+    );
+    xml.push_str(&format!("<TABLE name=\"{}\">\n", escape(name)));
+    for field in fields {
+        xml.push_str(&format!("<FIELD name=\"{}\"", escape(&field.name)));
+        if field.numeric {
+            xml.push_str(" datatype=\"double\"");
+        } else {
+            xml.push_str(" datatype=\"char\" arraysize=\"*\"");
+        }
+        if let Some(ucd) = &field.ucd {
+            xml.push_str(&format!(" ucd=\"{}\"", escape(ucd)));
+        }
+        if let Some(unit) = &field.unit {
+            xml.push_str(&format!(" unit=\"{}\"", escape(unit)));
+        }
+        xml.push_str("/>\n");
+    }
+
+    xml.push_str("<DATA><TABLEDATA>\n");
+    for row in rows.rows {
+        xml.push_str("<TR>");
+        for value in row {
+            match value {
+                Value::Numeric(v) if v.is_finite() => xml.push_str(&format!("<TD>{}</TD>", v)),
+                Value::Text(s) => xml.push_str(&format!("<TD>{}</TD>", escape(&s))),
+                _ => xml.push_str("<TD/>"),
+            }
+        }
+        xml.push_str("</TR>\n");
+    }
+    xml.push_str("</TABLEDATA></DATA>\n</TABLE>\n</RESOURCE>\n</VOTABLE>\n");
+
+    xml.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VOTableParser;
+    use crate::table::{Column, TableReader};
+
+    const HEADER: &str = r#"<?xml version="1.0"?>
+<VOTABLE version="1.4" xmlns="http://www.ivoa.net/xml/VOTable/v1.3">
+<RESOURCE><TABLE>
+<FIELD name="id" datatype="char" arraysize="*"/>
+<FIELD name="RAJ2000" ucd="pos.eq.ra;meta.main" datatype="double" unit="deg"/>
+<FIELD name="DEJ2000" ucd="pos.eq.dec;meta.main" datatype="double" unit="deg"/>
+<FIELD name="mag" ucd="phot.mag" datatype="float"/>
+"#;
+
+    #[test]
+    fn tabledata_in_chunks() {
+        let doc = format!(
+            "{}<DATA><TABLEDATA>\
+            <TR><TD>a &amp; b</TD><TD>10.5</TD><TD>-5.25</TD><TD>12.0</TD></TR>\
+            <TR><TD>c</TD><TD>20.0</TD><TD>95.0</TD><TD/></TR>\
+            <TR><TD>d</TD><TD>30.0</TD><TD>45.0</TD><TD></TD></TR>\
+            </TABLEDATA></DATA></TABLE></RESOURCE></VOTABLE>",
+            HEADER
+        );
+
+        let mut parser = VOTableParser::new();
+        for chunk in doc.as_bytes().chunks(7) {
+            parser.feed(chunk).unwrap();
+        }
+        let (sources, attributes, errors) = parser.finish().unwrap();
+
+        assert_eq!(sources.len(), 2);
+        // The second row has an invalid declination
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        match attributes.get_column("id") {
+            Some(Column::Text(ids)) => assert_eq!(ids, &["a & b", "d"]),
+            _ => panic!("id column not found"),
+        }
+        match attributes.get_column("mag") {
+            Some(Column::Numeric(mags)) => assert!(mags[0] == 12.0 && mags[1].is_nan()),
+            _ => panic!("mag column not found"),
+        }
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let doc = format!(
+            "{}<DATA><TABLEDATA>\
+            <TR><TD>a &lt;b&gt;</TD><TD>10.5</TD><TD>-5.25</TD><TD></TD></TR>\
+            </TABLEDATA></DATA></TABLE></RESOURCE></VOTABLE>",
+            HEADER
+        );
+        let mut parser = VOTableParser::new();
+        parser.feed(doc.as_bytes()).unwrap();
+        let (sources, attributes, _) = parser.finish().unwrap();
+
+        let written = super::write_votable("cat", &sources, &attributes);
+        let mut parser = VOTableParser::new();
+        parser.feed(&written).unwrap();
+        let (sources2, attributes2, errors) = parser.finish().unwrap();
+
+        assert!(errors.is_empty());
+        assert_eq!(sources2.len(), 1);
+        assert_eq!(attributes2.fields().len(), 6);
+        assert_eq!(attributes2.fields()[0].name, "_RAJ2000");
+        match attributes2.get_column("id") {
+            Some(Column::Text(ids)) => assert_eq!(ids, &["a <b>"]),
+            _ => panic!("id column not found"),
+        }
+        match attributes2.get_column("mag") {
+            Some(Column::Numeric(mags)) => assert!(mags[0].is_nan()),
+            _ => panic!("mag column not found"),
+        }
+
+        let csv = String::from_utf8(crate::table::delimited::write_csv(&sources, &attributes)).unwrap();
+        assert!(csv.starts_with("_RAJ2000,_DEJ2000,id,RAJ2000,DEJ2000,mag\r\n"));
+        assert!(csv.ends_with(",a <b>,10.5,-5.25,\r\n"));
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        bytes
+            .chunks(3)
+            .flat_map(|c| {
+                let n = (c[0] as u32) << 16
+                    | (*c.get(1).unwrap_or(&0) as u32) << 8
+                    | *c.get(2).unwrap_or(&0) as u32;
+                (0..4).map(move |i| {
+                    if i > c.len() {
+                        '='
+                    } else {
+                        ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char
+                    }
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn binary2_stream() {
+        let mut bytes = vec![];
+        for (id, ra, dec, mag) in [("s1", 10.0_f64, 20.0_f64, Some(1.5_f32)), ("s22", 11.0, 21.0, None)] {
+            // null flags
+            bytes.push(if mag.is_none() { 0b0001_0000 } else { 0 });
+            bytes.extend_from_slice(&(id.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(id.as_bytes());
+            bytes.extend_from_slice(&ra.to_be_bytes());
+            bytes.extend_from_slice(&dec.to_be_bytes());
+            bytes.extend_from_slice(&mag.unwrap_or(0.0).to_be_bytes());
+        }
+
+        let doc = format!(
+            "{}<DATA><BINARY2><STREAM encoding='base64'>\n{}\n</STREAM></BINARY2></DATA></TABLE></RESOURCE></VOTABLE>",
+            HEADER,
+            base64(&bytes)
+        );
+
+        let mut parser = VOTableParser::new();
+        for chunk in doc.as_bytes().chunks(5) {
+            parser.feed(chunk).unwrap();
+        }
+        let (sources, attributes, errors) = parser.finish().unwrap();
+
+        assert_eq!(sources.len(), 2);
+        assert!(errors.is_empty());
+        match attributes.get_column("id") {
+            Some(Column::Text(ids)) => assert_eq!(ids, &["s1", "s22"]),
+            _ => panic!("id column not found"),
+        }
+        match attributes.get_column("mag") {
+            Some(Column::Numeric(mags)) => assert!(mags[0] == 1.5 && mags[1].is_nan()),
+            _ => panic!("mag column not found"),
+        }
+    }
+
+    #[test]
+    fn metadata_without_data() {
+        // Shaped as the Metadata.xml of a HiPS catalog
+        let doc = r#"<?xml version="1.0" encoding="UTF-8"?>
+<VOTABLE version="1.2" xmlns="http://www.ivoa.net/xml/VOTable/v1.2">
+<RESOURCE>
+<TABLE name="I/239/hip_main">
+<FIELD name="HIP" ucd="meta.id;meta.main" datatype="int"><DESCRIPTION>Identifier</DESCRIPTION></FIELD>
+<FIELD name="RAICRS" ucd="pos.eq.ra;meta.main" datatype="double" unit="deg"/>
+<FIELD name="DEICRS" ucd="pos.eq.dec;meta.main" datatype="double" unit="deg"/>
+<FIELD name="Vmag" ucd="phot.mag;em.opt.V" datatype="float" unit="mag"/>
+</TABLE>
+</RESOURCE>
+</VOTABLE>"#;
+
+        let mut parser = VOTableParser::new();
+        for chunk in doc.as_bytes().chunks(13) {
+            parser.feed(chunk).unwrap();
+        }
+
+        let fields = parser.fields();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[1].ucd.as_deref(), Some("pos.eq.ra;meta.main"));
+        assert_eq!(fields[3].unit.as_deref(), Some("mag"));
+        assert!(fields[3].numeric);
+    }
+}
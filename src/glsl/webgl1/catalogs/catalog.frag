@@ -2,13 +2,22 @@ precision lowp float;
 
 varying vec2 out_uv;
 varying vec3 out_p;
+varying float out_weight;
 
 uniform sampler2D kernel_texture;
 uniform float fov;
 uniform float strength;
+
+@import ./kernel;
+
 void main() {
-    vec4 color = texture2D(kernel_texture, out_uv) / max(log2(fov*100.0), 1.0);
-    color.r *= strength;
+    vec4 color;
+    if (kernel_type == 0) {
+        color = texture2D(kernel_texture, out_uv) / max(log2(fov*100.0), 1.0);
+        color.r *= strength * out_weight;
+    } else {
+        color = vec4(kernel(out_uv) * kernel_scale * out_weight, 0.0, 0.0, 1.0);
+    }
 
     gl_FragColor = color;
 }
\ No newline at end of file
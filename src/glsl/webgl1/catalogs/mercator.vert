@@ -2,7 +2,7 @@ precision lowp float;
 
 attribute vec2 offset;
 attribute in vec2 uv;
-attribute in vec3 center;
+attribute in vec4 center;
 
 uniform float current_time;
 uniform mat4 inv_model;
@@ -13,11 +13,13 @@ uniform vec2 kernel_size;
 
 varying vec2 out_uv;
 varying vec3 out_p;
+// Number of sources represented by the instance
+varying float out_weight;
 
 @import ../hips/projection;
 
 void main() {
-    vec3 p = vec3(inv_model * vec4(center, 1.0));
+    vec3 p = vec3(inv_model * vec4(center.xyz, 1.0));
     //p = check_inversed_longitude(p);
 
     vec2 center_pos_clip_space = world2clip_mercator(p);
@@ -27,4 +29,5 @@ void main() {
 
     out_uv = uv;
     out_p = p;
+    out_weight = center.w;
 }
\ No newline at end of file
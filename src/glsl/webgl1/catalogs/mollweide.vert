@@ -1,7 +1,7 @@
 precision lowp float;
 attribute vec2 offset;
 attribute vec2 uv;
-attribute vec3 center;
+attribute vec4 center;
 
 uniform float current_time;
 uniform mat4 inv_model;
@@ -12,11 +12,13 @@ uniform vec2 kernel_size;
 
 out vec2 out_uv;
 out vec3 out_p;
+// Number of sources represented by the instance
+out float out_weight;
 
 @import ../hips/projection;
 
 void main() {
-    vec3 p = vec3(inv_model * vec4(center, 1.0));
+    vec3 p = vec3(inv_model * vec4(center.xyz, 1.0));
     //p = check_inversed_longitude(p);
 
     vec2 center_pos_clip_space = world2clip_mollweide(p);
@@ -26,4 +28,5 @@ void main() {
 
     out_uv = uv;
     out_p = p;
+    out_weight = center.w;
 }
\ No newline at end of file
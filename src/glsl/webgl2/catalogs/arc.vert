@@ -2,7 +2,7 @@
 precision lowp float;
 layout (location = 0) in vec2 offset;
 layout (location = 1) in vec2 uv;
-layout (location = 2) in vec3 center;
+layout (location = 2) in vec4 center;
 
 uniform float current_time;
 uniform mat4 inv_model;
@@ -13,11 +13,13 @@ uniform vec2 kernel_size;
 
 out vec2 out_uv;
 out vec3 out_p;
+// Number of sources represented by the instance
+out float out_weight;
 
 @include "../hips/projection.glsl"
 
 void main() {
-    vec3 p = vec3(inv_model * vec4(center, 1.0f));
+    vec3 p = vec3(inv_model * vec4(center.xyz, 1.0f));
     //p = check_inversed_longitude(p);
 
     vec2 center_pos_clip_space = world2clip_arc(p);
@@ -27,4 +29,5 @@ void main() {
 
     out_uv = uv;
     out_p = p;
+    out_weight = center.w;
 }
\ No newline at end of file
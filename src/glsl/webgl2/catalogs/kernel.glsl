@@ -0,0 +1,22 @@
+uniform int kernel_type; // 0: kernel texture, 1: gaussian, 2: top-hat, 3: Epanechnikov
+// Value of the kernel at its center for a source of weight 1,
+// the density mapped to the top of the colormap being 1
+uniform highp float kernel_scale;
+
+// The billboard covers the support of the kernel
+float kernel(vec2 uv) {
+    float r = length(uv - vec2(0.5)) * 2.0;
+    if (r > 1.0) {
+        return 0.0;
+    }
+
+    if (kernel_type == 1) {
+        // Truncated at 3 sigmas
+        float u = 3.0 * r;
+        return exp(-0.5 * u * u);
+    } else if (kernel_type == 2) {
+        return 1.0;
+    } else {
+        return 1.0 - r * r;
+    }
+}
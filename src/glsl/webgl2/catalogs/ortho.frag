@@ -1,20 +1,27 @@
 #version 300 es
-precision lowp float;
+precision highp float;
 
 in vec2 out_uv;
 in vec3 out_p;
+in float out_weight;
 
 out vec4 color;
 
 uniform sampler2D kernel_texture;
-uniform float max_density; // max number of sources in a kernel sized HEALPix cell at the current depth
 uniform float fov;
 uniform float strength;
+
+@include "./kernel.glsl"
+
 void main() {
     if (out_p.z < 0.f) {
         discard;
     }
 
-    color = texture(kernel_texture, out_uv) / max(log2(fov*100.0), 1.0);
-    color.r *= strength;
+    if (kernel_type == 0) {
+        color = texture(kernel_texture, out_uv) / max(log2(fov*100.0), 1.0);
+        color.r *= strength * out_weight;
+    } else {
+        color = vec4(kernel(out_uv) * kernel_scale * out_weight, 0.0, 0.0, 1.0);
+    }
 }
\ No newline at end of file
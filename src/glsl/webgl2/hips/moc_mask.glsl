@@ -0,0 +1,19 @@
+// Mask a layer with a MOC given in ICRS
+// The functions of healpix.glsl must be included before
+@include "../moc/coverage.glsl"
+
+// 0: the layer is not masked
+// 1: the pixels outside the MOC are discarded
+// 2: the pixels inside the MOC are discarded
+uniform int mask_mode;
+// From the frame of the HiPS to ICRS
+uniform mat4 mask_model;
+
+bool is_masked(vec3 pos) {
+    if (mask_mode == 0) {
+        return false;
+    }
+
+    vec3 p = normalize(vec3(mask_model * vec4(pos, 1.0)));
+    return is_covered(hash_depth_20(p.zxy)) == (mask_mode == 2);
+}
@@ -2,6 +2,7 @@
 precision highp float;
 precision highp sampler2D;
 precision highp isampler2D;
+precision highp usampler2D;
 precision mediump int;
 
 in vec3 frag_uv_start;
@@ -9,13 +10,20 @@ in vec3 frag_uv_end;
 in float frag_blending_factor;
 in float m_start;
 in float m_end;
+in vec3 frag_pos;
 
 out vec4 out_frag_color;
 uniform float opacity;
 
 @include "../color.glsl"
+@include "../raytracer/healpix.glsl"
+@include "../moc_mask.glsl"
 
 void main() {
+    if (is_masked(frag_pos)) {
+        discard;
+    }
+
     vec4 color_start = get_color_from_texture(frag_uv_start);
     vec4 color_end = get_color_from_texture(frag_uv_end);
 
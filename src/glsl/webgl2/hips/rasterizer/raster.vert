@@ -9,12 +9,15 @@ layout (location = 2) in vec3 uv_end;
 layout (location = 3) in float time_tile_received;
 layout (location = 4) in float m0;
 layout (location = 5) in float m1;
+// The position in the frame of the HiPS
+layout (location = 6) in vec3 hips_pos;
 
 out vec3 frag_uv_start;
 out vec3 frag_uv_end;
 out float frag_blending_factor;
 out float m_start;
 out float m_end;
+out vec3 frag_pos;
 
 uniform mat4 inv_model;
 uniform vec2 ndc_to_clip;
@@ -33,4 +36,5 @@ void main() {
     frag_blending_factor = min((current_time - time_tile_received) / 500.0, 1.0);
     m_start = m0;
     m_end = m1;
+    frag_pos = hips_pos;
 }
\ No newline at end of file
@@ -21,6 +21,7 @@ uniform int num_tiles;
 
 @include "../color.glsl"
 @include "./healpix.glsl"
+@include "../moc_mask.glsl"
 
 uniform float opacity;
 
@@ -48,6 +49,10 @@ uniform sampler2D position_tex;
 uniform mat4 model;
 
 void main() {
+    if (is_masked(frag_pos)) {
+        discard;
+    }
+
     // Get the HEALPix cell idx and the uv in the texture
     vec4 c = get_tile_color(normalize(frag_pos));
     out_frag_color = vec4(c.rgb, opacity * c.a);
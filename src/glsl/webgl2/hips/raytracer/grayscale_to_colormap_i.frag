@@ -22,6 +22,7 @@ uniform float opacity;
 
 @include "../color_i.glsl"
 @include "./healpix.glsl"
+@include "../moc_mask.glsl"
 
 vec4 get_tile_color(vec3 pos) {
     HashDxDy result = hash_with_dxdy(0, pos.zxy);
@@ -57,6 +58,10 @@ uniform sampler2D position_tex;
 uniform mat4 model;
 
 void main() {
+    if (is_masked(frag_pos)) {
+        discard;
+    }
+
     /*vec2 uv = out_clip_pos * 0.5 + 0.5;
     vec3 n = texture(position_tex, uv).rgb;
 
@@ -1,10 +1,45 @@
 #version 300 es
-
 precision highp float;
-in vec4 v_rgba;
+
+in float frag_l;
+in float frag_d;
 out vec4 color;
+
+uniform vec3 u_color;
+uniform float u_opacity;
+// in pixels
+uniform float u_half_width;
+
+// The lengths of the dashes and of the gaps between them, in pixels
+uniform float u_dash[8];
+uniform int u_num_dash;
+
 void main() {
-    // Multiply vertex color with texture color (in linear space).
-    // Linear color is written and blended in Framebuffer and converted to sRGB later
-    color = v_rgba;
-}
\ No newline at end of file
+    if (u_num_dash > 0) {
+        float period = 0.0;
+        for (int i = 0; i < u_num_dash; i++) {
+            period += u_dash[i];
+        }
+
+        float d = mod(frag_l, period);
+        for (int i = 0; i < u_num_dash; i++) {
+            if (d < u_dash[i]) {
+                // Odd entries are gaps
+                if ((i & 1) == 1) {
+                    discard;
+                }
+                break;
+            }
+            d -= u_dash[i];
+        }
+    }
+
+    // Anti-aliasing: the edges fade out over one device pixel
+    float aa = max(fwidth(frag_d), 1e-3);
+    float coverage = clamp((u_half_width - abs(frag_d)) / aa + 0.5, 0.0, 1.0);
+    if (coverage <= 0.0) {
+        discard;
+    }
+
+    color = vec4(u_color, u_opacity * coverage);
+}
@@ -1,18 +1,16 @@
 #version 300 es
 precision highp float;
-layout (location = 0) in vec2 pos;
+layout (location = 0) in vec2 ndc_pos;
+// The distance from the beginning of the path, in pixels
+layout (location = 1) in float l;
+// The signed distance to the middle of the line, in pixels
+layout (location = 2) in float d;
 
-uniform vec2 u_screen_size;
-uniform vec4 u_color;
-
-out vec4 v_rgba;
+out float frag_l;
+out float frag_d;
 
 void main() {
-  gl_Position = vec4(
-        pos,
-        0.0,
-        1.0
-    );
-
-    v_rgba = u_color;
-}
\ No newline at end of file
+    gl_Position = vec4(ndc_pos, 0.0, 1.0);
+    frag_l = l;
+    frag_d = d;
+}
@@ -0,0 +1,59 @@
+// Test whether positions are covered by a MOC
+// The functions of healpix.glsl must be included before.
+// The integers are given in high precision as the including shaders may default to medium precision
+
+// The sorted HEALPix ranges of the MOC at depth 20, one per texel, stored as:
+// (start >> 32, start & 0xFFFFFFFF, end >> 32, end & 0xFFFFFFFF)
+// The texture is 4096 texels wide
+uniform highp usampler2D ranges;
+uniform highp int num_ranges;
+
+// Spread the 16 lowest bits of v on the even bits
+highp uint spread_bits(highp uint v) {
+    v = (v | (v << 8)) & 0x00FF00FFu;
+    v = (v | (v << 4)) & 0x0F0F0F0Fu;
+    v = (v | (v << 2)) & 0x33333333u;
+    v = (v | (v << 1)) & 0x55555555u;
+
+    return v;
+}
+
+// The HEALPix cell at depth 20 containing a position, its 44 bits being
+// split into the 32 highest and 32 lowest ones.
+// Depth 20 cells are ~0.2 arcsec wide, i.e. the precision of hash_with_dxdy
+highp uvec2 hash_depth_20(vec3 p) {
+    HashDxDy result = hash_with_dxdy(0, p);
+
+    highp uint i = min(uint(result.dx * 1048576.0), 1048575u);
+    highp uint j = min(uint(result.dy * 1048576.0), 1048575u);
+
+    highp uint hi = (uint(result.idx) << 8) | spread_bits(i >> 16) | (spread_bits(j >> 16) << 1);
+    highp uint lo = spread_bits(i & 0xFFFFu) | (spread_bits(j & 0xFFFFu) << 1);
+
+    return uvec2(hi, lo);
+}
+
+bool less_than(highp uvec2 a, highp uvec2 b) {
+    return a.x < b.x || (a.x == b.x && a.y < b.y);
+}
+
+highp uvec4 get_range(highp int idx) {
+    return texelFetch(ranges, ivec2(idx & 4095, idx >> 12), 0);
+}
+
+bool is_covered(highp uvec2 hash) {
+    // Binary search of the first range starting after the cell
+    highp int lo = 0;
+    highp int hi = num_ranges;
+    while (lo < hi) {
+        highp int mid = (lo + hi) >> 1;
+        if (less_than(hash, get_range(mid).xy)) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    // The cell is covered if the previous range ends after it
+    return lo > 0 && less_than(hash, get_range(lo - 1).zw);
+}
@@ -0,0 +1,22 @@
+#version 300 es
+precision highp float;
+precision highp int;
+precision highp usampler2D;
+
+in vec3 frag_pos;
+out vec4 frag_color;
+
+uniform vec3 color;
+uniform float opacity;
+
+@include "../hips/raytracer/healpix.glsl"
+@include "./coverage.glsl"
+
+void main() {
+    uvec2 hash = hash_depth_20(normalize(frag_pos).zxy);
+    if (!is_covered(hash)) {
+        discard;
+    }
+
+    frag_color = vec4(color, opacity);
+}
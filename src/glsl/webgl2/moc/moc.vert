@@ -0,0 +1,14 @@
+#version 300 es
+precision highp float;
+
+layout (location = 0) in vec2 ndc_pos;
+layout (location = 1) in vec3 world_pos;
+
+out vec3 frag_pos;
+
+uniform mat4 model;
+
+void main() {
+    frag_pos = vec3(model * vec4(world_pos, 1.0));
+    gl_Position = vec4(ndc_pos, 0.0, 1.0);
+}
@@ -208,10 +208,14 @@ export let Aladin = (function () {
         this.boxes.push(this.coogrid);
 
         // Grid
-        var color, opacity;
+        var color, opacity, frame, step, labelFormat;
         if (options.gridOptions) {
             color = options.gridOptions.color && Color.hexToRgb(options.gridOptions.color);
             opacity = options.gridOptions.opacity;
+            // The grid is drawn in the frame of the view if not given
+            frame = gridFrame(options.gridOptions.frame);
+            step = options.gridOptions.step;
+            labelFormat = options.gridOptions.labelFormat;
         } else {
             color = {r:0.0, g:1.0, b:0.0};
             opacity = 1.0;
@@ -220,6 +224,9 @@ export let Aladin = (function () {
         this.view.setGridConfig({
             color: color,
             opacity: opacity,
+            frame: frame,
+            step: step,
+            labelFormat: labelFormat,
         });
 
         if (options && options.showCooGrid) {
@@ -1292,6 +1299,93 @@ export let Aladin = (function () {
         this.cooGridEnabled = false;
     }
 
+    // The coordinate system of the backend matching a frame name, e.g. "galactic",
+    // "view" for the frame of the view
+    var gridFrame = function(frameName) {
+        if (frameName === 'view') {
+            return "view";
+        }
+
+        const frame = CooFrameEnum.fromString(frameName);
+        if (!frame) {
+            return undefined;
+        }
+
+        return frame.system == CooFrameEnum.SYSTEMS.GAL ? "GAL" : "ICRSJ2000";
+    };
+
+    // @API
+    // Draw the meridians and parallels of the coordinate grid in a frame, e.g. "galactic", that can differ from the one of the view
+    Aladin.prototype.setCooGridFrame = function(frameName) {
+        const frame = gridFrame(frameName);
+        if (!frame) {
+            console.warn('Invalid grid frame given: ' + frameName + '. Coordinate systems supported: "ICRS", "j2000", "galactic" or "view"');
+            return;
+        }
+
+        this.view.setGridConfig({frame: frame});
+    };
+
+    // The configuration of the backend matching the options of a coordinate grid
+    var gridConfig = function(options) {
+        options = options || {};
+
+        let frame;
+        if (options.frame) {
+            frame = gridFrame(options.frame);
+            if (!frame) {
+                console.warn('Invalid grid frame given: ' + options.frame + '. Coordinate systems supported: "ICRS", "j2000", "galactic" or "view"');
+            }
+        }
+
+        return {
+            enabled: options.show,
+            color: options.color && Color.hexToRgb(options.color),
+            opacity: options.opacity,
+            showLabels: options.showLabels,
+            labelSize: options.labelSize,
+            // "view" to follow the frame of the view again
+            frame: frame,
+            // in degrees, the step adapts to the field of view if not given
+            step: options.step,
+            // "dms", "decimal", "sexagesimal" or "galactic"
+            labelFormat: options.labelFormat,
+            // null to deduce the number of decimals from the step again
+            labelDigits: options.labelDigits === null ? -1 : options.labelDigits,
+            // Label the lines on the border of the view, WCSAxes style
+            borderAxes: options.borderAxes,
+            // in pixels
+            tickLength: options.tickLength,
+            // "outside" or "inside" the frame of the axes
+            borderLabels: options.borderLabels,
+            // in pixels
+            lineWidth: options.lineWidth,
+            // e.g. [4, 2] for dashes of 4 pixels spaced by 2 pixels
+            lineDash: options.lineDash,
+        };
+    };
+
+    // @API
+    // Add a coordinate grid drawn along with the other ones
+    //
+    // options: {show, color, opacity, showLabels, labelSize, frame, step, labelFormat, labelDigits, borderAxes, tickLength, borderLabels, lineWidth, lineDash}
+    // e.g. aladin.addCooGrid('gal', {frame: 'galactic', color: '#ff0000', step: 10})
+    Aladin.prototype.addCooGrid = function(id, options) {
+        this.view.addGrid(id, gridConfig(options));
+    };
+
+    // @API
+    // Remove a coordinate grid added with addCooGrid
+    Aladin.prototype.removeCooGrid = function(id) {
+        this.view.removeGrid(id);
+    };
+
+    // @API
+    // Configure a coordinate grid given its id, "default" being the one of the view
+    Aladin.prototype.setCooGridConfig = function(id, options) {
+        this.view.setGridConfigById(id, gridConfig(options));
+    };
+
     Aladin.prototype.layerByName = function (name) {
         var c = this.view.allOverlayLayers;
         for (var k = 0; k < c.length; k++) {
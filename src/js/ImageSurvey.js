@@ -536,6 +536,29 @@ export let ImageSurvey = (function () {
         return this.wasm.readPixel(x, y, this.layer);
     };
 
+    // @api
+    // Show the survey only over a MOC, or only outside of it with the inverted option
+    //
+    // The MOC must have been added to the aladin instance beforehand
+    ImageSurvey.prototype.setMocMask = function (moc, options) {
+        options = options || {};
+        if (!this.added) {
+            throw "The survey must be added to the aladin instance before being masked";
+        }
+        if (!moc.ready) {
+            throw moc.name + " is not yet ready, either because it has not been downloaded yet or because it has not been added to the aladin instance."
+        }
+
+        this.wasm.setLayerMocMask(this.layer, moc.mocParams, options.inverted === true);
+    };
+
+    // @api
+    ImageSurvey.prototype.removeMocMask = function () {
+        if (this.added) {
+            this.wasm.removeLayerMocMask(this.layer);
+        }
+    };
+
     ImageSurvey.DEFAULT_SURVEY_ID = "P/DSS2/color";
 
     return ImageSurvey;
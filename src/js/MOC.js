@@ -32,8 +32,14 @@ export let MOC = (function() {
 
         this.opacity = Math.max(0, Math.min(1, this.opacity)); // 0 <= this.opacity <= 1
         this.lineWidth = options["lineWidth"] || 1;
+        this.lineDash = options["lineDash"] || [];
         this.adaptativeDisplay = options['adaptativeDisplay'] !== false;
 
+        // Fill the MOC, only its perimeter being stroked
+        this.fill = options.fill === true;
+        this.fillColor = Color.standardizeColor(options.fillColor || this.color);
+        this.fillOpacity = options.fillOpacity !== undefined ? Math.max(0, Math.min(1, options.fillOpacity)) : 0.5;
+
         //this.proxyCalled = false; // this is a flag to check whether we already tried to load the MOC through the proxy
 
         this.isShowing = true;
@@ -72,6 +78,10 @@ export let MOC = (function() {
 
         this.view = view;
         this.mocParams = new Aladin.wasmLibs.core.MOC(this.uuid, this.opacity, this.lineWidth, this.isShowing, this.color, this.adaptativeDisplay);
+        this.mocParams.line_dash = this.lineDash;
+        this.mocParams.fill = this.fill;
+        this.mocParams.fill_color = this.fillColor;
+        this.mocParams.fill_opacity = this.fillOpacity;
 
         if (this.dataURL) {
             this.promiseFetchData
@@ -118,6 +128,10 @@ export let MOC = (function() {
         if (this.view) {
             // update the new moc params to the backend
             this.mocParams = new Aladin.wasmLibs.core.MOC(this.uuid, this.opacity, this.lineWidth, this.isShowing, this.color, this.adaptativeDisplay);
+            this.mocParams.line_dash = this.lineDash;
+            this.mocParams.fill = this.fill;
+            this.mocParams.fill_color = this.fillColor;
+            this.mocParams.fill_opacity = this.fillOpacity;
             this.view.wasm.setMocParams(this.mocParams);
             this.view.requestRedraw();
         }
@@ -159,6 +173,67 @@ export let MOC = (function() {
         return this.view.wasm.mocContains(this.mocParams, ra, dec);
     };
 
+    // returns the area of the MOC in square degrees
+    MOC.prototype.area = function() {
+        if (!this.ready) {
+            throw this.name + " is not yet ready, either because it has not been downloaded yet or because it has not been added to the aladin instance."
+        }
+
+        return this.view.wasm.getMocArea(this.mocParams);
+    };
+
+    // returns an array giving the number of cells of each order the MOC is made of, from the order 0 to its maximum order
+    MOC.prototype.numCellsPerOrder = function() {
+        if (!this.ready) {
+            throw this.name + " is not yet ready, either because it has not been downloaded yet or because it has not been added to the aladin instance."
+        }
+
+        return Array.from(this.view.wasm.getMocNumCellsPerOrder(this.mocParams));
+    };
+
+    // returns a cone containing the MOC as {ra, dec, radius} in degrees, or undefined if the MOC is empty
+    MOC.prototype.boundingCone = function() {
+        if (!this.ready) {
+            throw this.name + " is not yet ready, either because it has not been downloaded yet or because it has not been added to the aladin instance."
+        }
+
+        const cone = this.view.wasm.getMocBoundingCone(this.mocParams);
+        if (!cone) {
+            return undefined;
+        }
+
+        return {ra: cone[0], dec: cone[1], radius: cone[2]};
+    };
+
+    // returns the cells of the MOC given by their UNIQ index, as a BigUint64Array
+    MOC.prototype.uniqCells = function() {
+        if (!this.ready) {
+            throw this.name + " is not yet ready, either because it has not been downloaded yet or because it has not been added to the aladin instance."
+        }
+
+        return this.view.wasm.getMocUniqCells(this.mocParams);
+    };
+
+    // returns the ranges of cells of order 29 of the MOC, flattened as [start0, end0, start1, end1, ...] in a BigUint64Array
+    MOC.prototype.ranges = function() {
+        if (!this.ready) {
+            throw this.name + " is not yet ready, either because it has not been downloaded yet or because it has not been added to the aladin instance."
+        }
+
+        return this.view.wasm.getMocRanges(this.mocParams);
+    };
+
+    // The perimeter of the MOC, i.e. its outer boundaries and the ones of its holes
+    //
+    // returns an array of closed polylines, each being an array of [ra, dec] vertices in degrees
+    MOC.prototype.perimeter = function() {
+        if (!this.ready) {
+            throw this.name + " is not yet ready, either because it has not been downloaded yet or because it has not been added to the aladin instance."
+        }
+
+        return this.view.wasm.getMocPerimeter(this.mocParams);
+    };
+
     return MOC;
 
 })();
@@ -18,6 +18,10 @@ import ColormapCatalogFS from '../glsl/webgl2/colormaps/colormap.frag'
 import GridVS_CPU from '../glsl/webgl2/grid/grid_cpu.vert'
 import GridFS_CPU from '../glsl/webgl2/grid/grid_cpu.frag'
 
+// MOC shader
+import MocVS from '../glsl/webgl2/moc/moc.vert'
+import MocFS from '../glsl/webgl2/moc/moc.frag'
+
 // HiPS shaders
 // Raytracer
 import RayTracerVS from '../glsl/webgl2/hips/raytracer/raytracer.vert'
@@ -101,6 +105,15 @@ let shaders = [
         id: "GridFS_CPU",
         content: GridFS_CPU,
     },
+    // MOC shader
+    {
+        id: "MocVS",
+        content: MocVS,
+    },
+    {
+        id: "MocFS",
+        content: MocFS,
+    },
     // HiPS shaders
     // Raytracer
     {
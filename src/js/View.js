@@ -1364,6 +1364,27 @@ export let View = (function () {
         this.requestRedraw();
     };
 
+    View.prototype.addGrid = function (id, gridCfg) {
+        this.wasm.addGrid(id, gridCfg);
+        this.requestRedraw();
+    };
+
+    View.prototype.removeGrid = function (id) {
+        this.wasm.removeGrid(id);
+        this.requestRedraw();
+    };
+
+    View.prototype.setGridConfigById = function (id, gridCfg) {
+        // The default grid sends the events of the grid controls
+        if (id === 'default') {
+            this.setGridConfig(gridCfg);
+            return;
+        }
+
+        this.wasm.setGridConfigById(id, gridCfg);
+        this.requestRedraw();
+    };
+
     View.prototype.updateZoomState = function () {
         // Get the new zoom values from the backend
         this.zoomFactor = this.wasm.getClipZoomFactor();